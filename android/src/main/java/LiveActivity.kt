@@ -2,18 +2,41 @@ package com.astralsight.astrobox.plugin.live_activity
 
 import android.Manifest
 import android.app.Activity
+import android.os.Build
 import androidx.annotation.RequiresPermission
+import app.tauri.PermissionState
 import app.tauri.annotation.Command
+import app.tauri.annotation.Permission
+import app.tauri.annotation.PermissionCallback
 import app.tauri.annotation.TauriPlugin
 import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSArray
+import app.tauri.plugin.JSObject
 import app.tauri.plugin.Plugin
 
-@TauriPlugin
+private const val NOTIFICATION_ALIAS = "notification"
+
+@TauriPlugin(
+    permissions = [
+        Permission(strings = [Manifest.permission.POST_NOTIFICATIONS], alias = NOTIFICATION_ALIAS)
+    ]
+)
 class LiveActivity(private val activity: Activity) : Plugin(activity) {
     private val manager = LiveActivityManager(activity)
 
+    @Command
+    fun setLogChannel(invoke: Invoke) {
+        val args = invoke.parseArgs(SetLogChannelArgs::class.java)
+        PluginLog.attach(args.channel)
+        invoke.resolve()
+    }
+
     @Command
     fun createLiveActivity(invoke: Invoke) {
+        if (!manager.hasNotificationPermission()) {
+            invoke.reject("Notification permission not granted", "PermissionDenied")
+            return
+        }
         val args = invoke.parseArgs(CreateLiveActivityArgs::class.java)
         manager.create(args)
         invoke.resolve()
@@ -32,4 +55,70 @@ class LiveActivity(private val activity: Activity) : Plugin(activity) {
         manager.remove()
         invoke.resolve()
     }
+
+    @Command
+    fun postAlert(invoke: Invoke) {
+        if (!manager.hasNotificationPermission()) {
+            invoke.reject("Notification permission not granted", "PermissionDenied")
+            return
+        }
+        val args = invoke.parseArgs(PostAlertArgs::class.java)
+        manager.alert(args)
+        invoke.resolve()
+    }
+
+    @Command
+    fun getDoNotDisturb(invoke: Invoke) {
+        val ret = JSObject()
+        ret.put("enabled", manager.doNotDisturb())
+        invoke.resolve(ret)
+    }
+
+    @Command
+    fun getCapabilities(invoke: Invoke) {
+        invoke.resolve(manager.capabilities())
+    }
+
+    @Command
+    fun getVisibleActivities(invoke: Invoke) {
+        val ret = JSObject()
+        ret.put("ids", JSArray(manager.visibleActivityIds()))
+        invoke.resolve(ret)
+    }
+
+    @Command
+    fun getPermissionState(invoke: Invoke) {
+        resolvePermission(invoke)
+    }
+
+    @Command
+    fun requestPermission(invoke: Invoke) {
+        if (Build.VERSION.SDK_INT < Build.VERSION_CODES.TIRAMISU || manager.hasNotificationPermission()) {
+            resolvePermission(invoke)
+            return
+        }
+        requestPermissionForAlias(NOTIFICATION_ALIAS, invoke, "notificationPermissionCallback")
+    }
+
+    @PermissionCallback
+    private fun notificationPermissionCallback(invoke: Invoke) {
+        resolvePermission(invoke)
+    }
+
+    private fun resolvePermission(invoke: Invoke) {
+        val ret = JSObject()
+        ret.put("state", permissionState())
+        invoke.resolve(ret)
+    }
+
+    private fun permissionState(): String {
+        if (manager.hasNotificationPermission()) {
+            // 运行时权限给了，但用户仍可能在系统设置里关掉了本应用的通知
+            return if (manager.notificationsEnabled()) "granted" else "denied"
+        }
+        return when (getPermissionState(NOTIFICATION_ALIAS)) {
+            PermissionState.DENIED -> "denied"
+            else -> "notDetermined"
+        }
+    }
 }
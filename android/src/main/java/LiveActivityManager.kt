@@ -7,9 +7,8 @@ import android.app.NotificationChannel
 import android.app.NotificationManager
 import android.content.pm.PackageManager
 import android.os.Build
-import android.util.Log
 import androidx.annotation.RequiresPermission
-import androidx.core.app.ActivityCompat
+import app.tauri.plugin.JSObject
 import androidx.core.app.NotificationCompat
 import androidx.core.app.NotificationManagerCompat
 import androidx.core.content.ContextCompat
@@ -19,7 +18,7 @@ private const val TAG = "LiveActivity"
 private const val CHANNEL_ID = "live_activity"
 private const val CHANNEL_NAME = "Live Activity"
 private const val NOTIFICATION_ID = 9901
-private const val NOTIFICATION_PERMISSION_REQUEST_CODE = 1001
+private const val ALERT_NOTIFICATION_ID = 9902
 
 class LiveActivityManager(private val activity: Activity) {
     private val notificationManager = NotificationManagerCompat.from(activity)
@@ -39,26 +38,25 @@ class LiveActivityManager(private val activity: Activity) {
     @RequiresPermission(Manifest.permission.POST_NOTIFICATIONS)
     fun create(args: CreateLiveActivityArgs) {
         if (current != null) {
-            Log.i(TAG, "Live activity already exists; skip create.")
-            return
+            PluginLog.w(TAG, "Live activity already exists; refusing create.")
+            throw IllegalStateException("Live activity already exists")
         }
 
         if (!hasNotificationPermission()) {
-            Log.w(TAG, "Notification permission not granted. Requesting permission.")
-            requestNotificationPermission()
-            return
+            PluginLog.w(TAG, "Notification permission not granted. Cannot create live activity.")
+            throw SecurityException("Notification permission not granted")
         }
 
         try {
             val content = args.activity_content
             if (content?.type != "TaskQueue") {
-                Log.i(TAG, "Unsupported live activity content type: ${content?.type}")
+                PluginLog.i(TAG, "Unsupported live activity content type: ${content?.type}")
                 return
             }
 
             val data = content.data
             if (data == null) {
-                Log.i(TAG, "Missing live activity data payload.")
+                PluginLog.i(TAG, "Missing live activity data payload.")
                 return
             }
 
@@ -75,12 +73,12 @@ class LiveActivityManager(private val activity: Activity) {
 
             ensureChannel()
             notificationManager.notify(NOTIFICATION_ID, buildNotification(state))
-            Log.i(TAG, "Live activity created.")
+            PluginLog.i(TAG, "Live activity created.")
         } catch (e: SecurityException) {
-            Log.e(TAG, "SecurityException while creating notification: ${e.message}")
+            PluginLog.e(TAG, "SecurityException while creating notification: ${e.message}")
             throw e
         } catch (e: Exception) {
-            Log.e(TAG, "Failed to create live activity: ${e.message}")
+            PluginLog.e(TAG, "Failed to create live activity: ${e.message}")
             throw e
         }
     }
@@ -88,39 +86,47 @@ class LiveActivityManager(private val activity: Activity) {
     @RequiresPermission(Manifest.permission.POST_NOTIFICATIONS)
     fun update(args: UpdateLiveActivityArgs) {
         if (isEnding) {
-            Log.i(TAG, "Live activity is ending; skip update.")
+            PluginLog.i(TAG, "Live activity is ending; skip update.")
             return
         }
 
         val live = current
         if (live == null) {
-            Log.i(TAG, "No live activity to update.")
+            PluginLog.i(TAG, "No live activity to update.")
             return
         }
 
         if (!hasNotificationPermission()) {
-            Log.w(TAG, "Notification permission not granted. Cannot update live activity.")
+            PluginLog.w(TAG, "Notification permission not granted. Cannot update live activity.")
             return
         }
 
         try {
             val state = args.state ?: live.state
             live.state = state
+            val done = (state["progress"]?.toFloatOrNull() ?: 0f) >= 1f
+            if (done && state["completion"] == "hidden") {
+                // The user turned completion notifications off for this task type.
+                notificationManager.cancel(NOTIFICATION_ID)
+                current = null
+                PluginLog.i(TAG, "Live activity completed without notification.")
+                return
+            }
             ensureChannel()
             notificationManager.notify(NOTIFICATION_ID, buildNotification(state))
-            Log.i(TAG, "Live activity updated.")
+            PluginLog.i(TAG, "Live activity updated.")
         } catch (e: SecurityException) {
-            Log.e(TAG, "SecurityException while updating notification: ${e.message}")
+            PluginLog.e(TAG, "SecurityException while updating notification: ${e.message}")
             throw e
         } catch (e: Exception) {
-            Log.e(TAG, "Failed to update live activity: ${e.message}")
+            PluginLog.e(TAG, "Failed to update live activity: ${e.message}")
             throw e
         }
     }
 
     fun remove() {
         if (current == null) {
-            Log.i(TAG, "No live activity to remove.")
+            PluginLog.i(TAG, "No live activity to remove.")
             return
         }
 
@@ -129,13 +135,59 @@ class LiveActivityManager(private val activity: Activity) {
             notificationManager.cancel(NOTIFICATION_ID)
             current = null
             isEnding = false
-            Log.i(TAG, "Live activity removed successfully.")
+            PluginLog.i(TAG, "Live activity removed successfully.")
         } catch (e: Exception) {
-            Log.e(TAG, "Failed to remove live activity: ${e.message}")
+            PluginLog.e(TAG, "Failed to remove live activity: ${e.message}")
             throw e
         }
     }
 
+    @RequiresPermission(Manifest.permission.POST_NOTIFICATIONS)
+    fun alert(args: PostAlertArgs) {
+        ensureChannel()
+        val iconRes = activity.applicationInfo.icon.takeIf { it != 0 }
+            ?: android.R.drawable.ic_dialog_info
+        val notification = NotificationCompat.Builder(activity, CHANNEL_ID)
+            .setSmallIcon(iconRes)
+            .setContentTitle(args.title ?: "")
+            .setContentText(args.body ?: "")
+            .setStyle(NotificationCompat.BigTextStyle().bigText(args.body ?: ""))
+            .setSilent(args.silent)
+            .setAutoCancel(true)
+            .apply { args.group?.let { setGroup(it) } }
+            .build()
+        // A group keeps one summary notification that later summaries replace.
+        val id = args.group?.let { ALERT_NOTIFICATION_ID + it.hashCode() } ?: ALERT_NOTIFICATION_ID
+        notificationManager.notify(id, notification)
+        PluginLog.i(TAG, "Alert posted.")
+    }
+
+    fun doNotDisturb(): Boolean {
+        val systemManager = activity.getSystemService(NotificationManager::class.java)
+            ?: return false
+        return systemManager.currentInterruptionFilter != NotificationManager.INTERRUPTION_FILTER_ALL
+    }
+
+    fun capabilities(): JSObject {
+        val caps = JSObject()
+        caps.put("presents", notificationsEnabled())
+        caps.put("progressBar", true)
+        caps.put("indeterminateProgress", true)
+        // Notifications carry neither action buttons nor a large image.
+        caps.put("buttons", false)
+        caps.put("images", false)
+        caps.put("updatesInPlace", true)
+        caps.put("maxConcurrent", 1)
+        return caps
+    }
+
+    fun visibleActivityIds(): List<String> {
+        val live = current ?: return emptyList()
+        val systemManager = activity.getSystemService(NotificationManager::class.java)
+        val shown = systemManager?.activeNotifications?.any { it.id == NOTIFICATION_ID } ?: false
+        return if (shown) listOf(live.id) else emptyList()
+    }
+
     private fun ensureChannel() {
         if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.O) {
             val existingChannel = notificationManager.getNotificationChannel(CHANNEL_ID)
@@ -148,14 +200,16 @@ class LiveActivityManager(private val activity: Activity) {
                 channel.description = "Live activity notifications for ongoing tasks"
                 channel.lockscreenVisibility = Notification.VISIBILITY_PUBLIC
                 notificationManager.createNotificationChannel(channel)
-                Log.d(TAG, "Notification channel created with HIGH importance: $CHANNEL_ID")
+                PluginLog.d(TAG, "Notification channel created with HIGH importance: $CHANNEL_ID")
             } else {
-                Log.d(TAG, "Notification channel already exists: $CHANNEL_ID")
+                PluginLog.d(TAG, "Notification channel already exists: $CHANNEL_ID")
             }
         }
     }
 
-    private fun hasNotificationPermission(): Boolean {
+    fun notificationsEnabled(): Boolean = notificationManager.areNotificationsEnabled()
+
+    fun hasNotificationPermission(): Boolean {
         return if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.TIRAMISU) {
             ContextCompat.checkSelfPermission(
                 activity,
@@ -166,30 +220,19 @@ class LiveActivityManager(private val activity: Activity) {
         }
     }
 
-    private fun requestNotificationPermission() {
-        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.TIRAMISU) {
-            ActivityCompat.requestPermissions(
-                activity,
-                arrayOf(Manifest.permission.POST_NOTIFICATIONS),
-                NOTIFICATION_PERMISSION_REQUEST_CODE
-            )
-            Log.i(TAG, "Notification permission requested")
-        }
-    }
-
     private fun buildNotification(state: Map<String, String>): Notification {
         val live = current ?: run {
-            Log.w(TAG, "No live activity data available for building notification")
+            PluginLog.w(TAG, "No live activity data available for building notification")
             return NotificationCompat.Builder(activity, CHANNEL_ID).build()
         }
 
-        Log.d(TAG, "Building notification for activity: ${live.id}, state: $state")
+        PluginLog.d(TAG, "Building notification for activity: ${live.id}, state: $state")
 
         val iconRes = activity.applicationInfo.icon.takeIf { it != 0 }
             ?: android.R.drawable.ic_dialog_info
 
         val progressInfo = parseProgress(state)
-        val contentText = buildContentText(live, progressInfo)
+        val contentText = buildContentText(live, progressInfo, state["hint"])
 
         val contentTitle = live.title.ifBlank { "Live Activity" }
         val builder = NotificationCompat.Builder(activity, CHANNEL_ID)
@@ -203,6 +246,7 @@ class LiveActivityManager(private val activity: Activity) {
             .setShortCriticalText("${progressInfo.percent}%")
             .setWhen(System.currentTimeMillis())
             .setShowWhen(false)
+            .setSilent(state["silent"] == "true")
 
         if (Build.VERSION.SDK_INT >= 36) {
             builder.setRequestPromotedOngoing(true)
@@ -210,10 +254,10 @@ class LiveActivityManager(private val activity: Activity) {
                 .setProgress(progressInfo.percent)
                 .setProgressIndeterminate(progressInfo.indeterminate)
             builder.setStyle(style)
-            Log.d(TAG, "Using Android 36+ progress style: ${progressInfo.percent}%")
+            PluginLog.d(TAG, "Using Android 36+ progress style: ${progressInfo.percent}%")
         } else {
             builder.setProgress(100, progressInfo.percent, progressInfo.indeterminate)
-            Log.d(TAG, "Using legacy progress style: ${progressInfo.percent}%")
+            PluginLog.d(TAG, "Using legacy progress style: ${progressInfo.percent}%")
         }
 
         return builder.build()
@@ -221,10 +265,14 @@ class LiveActivityManager(private val activity: Activity) {
 
     private fun buildContentText(
         live: LiveActivityData,
-        progressInfo: ProgressInfo
+        progressInfo: ProgressInfo,
+        hint: String?
     ): String {
         val taskInfo = live.taskName.ifBlank { live.text }.ifBlank { live.taskType }
-        return if (progressInfo.indeterminate) {
+        return if (hint != null) {
+            // Stalled or not started yet; progress is shown as indeterminate.
+            if (taskInfo.isNotBlank()) "$taskInfo · $hint" else hint
+        } else if (progressInfo.indeterminate) {
             if (taskInfo.isNotBlank()) taskInfo else live.text
         } else {
             val percentText = "${progressInfo.percent}%"
@@ -236,13 +284,13 @@ class LiveActivityManager(private val activity: Activity) {
         val progressRaw = state["progress"]?.trim()
         val percentRaw = state["percent"]?.trim()?.removeSuffix("%")
 
-        Log.d(TAG, "Parsing progress from state - progressRaw: $progressRaw, percentRaw: $percentRaw")
+        PluginLog.d(TAG, "Parsing progress from state - progressRaw: $progressRaw, percentRaw: $percentRaw")
 
         if (!progressRaw.isNullOrBlank()) {
             val value = progressRaw.toFloatOrNull()
             if (value != null) {
                 val percent = (value * 100f).roundToInt().coerceIn(0, 100)
-                Log.d(TAG, "Progress parsed from 'progress' field: $percent%")
+                PluginLog.d(TAG, "Progress parsed from 'progress' field: $percent%")
                 return ProgressInfo(percent, false)
             }
         }
@@ -251,12 +299,12 @@ class LiveActivityManager(private val activity: Activity) {
             val value = percentRaw.toFloatOrNull()
             if (value != null) {
                 val percent = value.roundToInt().coerceIn(0, 100)
-                Log.d(TAG, "Progress parsed from 'percent' field: $percent%")
+                PluginLog.d(TAG, "Progress parsed from 'percent' field: $percent%")
                 return ProgressInfo(percent, false)
             }
         }
 
-        Log.d(TAG, "No valid progress found, using indeterminate progress")
+        PluginLog.d(TAG, "No valid progress found, using indeterminate progress")
         return ProgressInfo(0, true)
     }
 
@@ -1,6 +1,7 @@
 package com.astralsight.astrobox.plugin.live_activity
 
 import app.tauri.annotation.InvokeArg
+import app.tauri.plugin.Channel
 
 @InvokeArg
 class CreateLiveActivityArgs {
@@ -34,3 +35,16 @@ class ActivityContentTaskQueue {
 class UpdateLiveActivityArgs {
     var state: Map<String, String>? = null
 }
+
+@InvokeArg
+class SetLogChannelArgs {
+    lateinit var channel: Channel
+}
+
+@InvokeArg
+class PostAlertArgs {
+    var title: String? = null
+    var body: String? = null
+    var silent: Boolean = false
+    var group: String? = null
+}
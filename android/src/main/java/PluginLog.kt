@@ -0,0 +1,46 @@
+package com.astralsight.astrobox.plugin.live_activity
+
+import android.util.Log
+import app.tauri.plugin.Channel
+import app.tauri.plugin.JSObject
+
+/**
+ * 日志同时写入 logcat，并通过 Rust 侧挂上的通道转发进 tracing，
+ * 这样问题排查时原生日志和 Rust 日志在同一条时间线上。
+ */
+object PluginLog {
+    @Volatile
+    private var channel: Channel? = null
+
+    fun attach(channel: Channel) {
+        this.channel = channel
+    }
+
+    fun d(tag: String, message: String) {
+        Log.d(tag, message)
+        forward("debug", message)
+    }
+
+    fun i(tag: String, message: String) {
+        Log.i(tag, message)
+        forward("info", message)
+    }
+
+    fun w(tag: String, message: String) {
+        Log.w(tag, message)
+        forward("warn", message)
+    }
+
+    fun e(tag: String, message: String) {
+        Log.e(tag, message)
+        forward("error", message)
+    }
+
+    private fun forward(level: String, message: String) {
+        val target = channel ?: return
+        val payload = JSObject()
+        payload.put("level", level)
+        payload.put("message", message)
+        target.send(payload)
+    }
+}
@@ -1,4 +1,25 @@
-const COMMANDS: &[&str] = &[];
+const COMMANDS: &[&str] = &[
+    "list_activities",
+    "get_activity",
+    "capabilities",
+    "permission_state",
+    "request_permission",
+    "metrics",
+    "replay_session",
+    "diagnose",
+    "quiet_status",
+    "set_quiet_hours",
+    "get_preferences",
+    "set_preference",
+    "reset_preference",
+    "heartbeat",
+    "instance_status",
+    "hand_over",
+    "history",
+    "history_stats",
+    "clear_history",
+    "watch_in_app",
+];
 
 fn main() {
     tauri_plugin::Builder::new(COMMANDS)
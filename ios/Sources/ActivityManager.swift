@@ -10,6 +10,9 @@ public final class ActivityManager {
 
     private var currentActivity: Activity<LiveActivityAttributes>?
 
+    /// 当前是否有正在展示的实时活动。
+    var hasActivity: Bool { currentActivity != nil }
+
     /// 结束流程中的保护位，防止 end 期间又被 update 顶回去（iOS，很有意思吧）
     private var isEnding: Bool = false
 
@@ -17,7 +20,7 @@ public final class ActivityManager {
         // 冷启动或 App 重启后，如果系统里还有正在进行的活动，则接管它（仅取第一个）
         self.currentActivity = Activity<LiveActivityAttributes>.activities.first
         if let act = self.currentActivity {
-            webviewLog("Recovered existing live activity on init, ID: \(act.id)")
+            pluginLog("Recovered existing live activity on init, ID: \(act.id)")
         }
     }
 
@@ -30,16 +33,16 @@ public final class ActivityManager {
         // 单实例：已有活动则拒绝创建
         // 因为我他妈的就是不想写多活动管理
         guard currentActivity == nil else {
-            webviewLog("Error: A live activity already exists and cannot be created again.")
+            pluginLog("Error: A live activity already exists and cannot be created again.", level: "error")
             return
         }
 
         guard ActivityAuthorizationInfo().areActivitiesEnabled else {
-            webviewLog("Tip: The user has disabled live activity in the system.")
+            pluginLog("Tip: The user has disabled live activity in the system.")
             return
         }
 
-        webviewLog("Processing live activity creation request with version \(request.activityContentV)...")
+        pluginLog("Processing live activity creation request with version \(request.activityContentV)...")
 
         switch request.activityContent {
         case .taskQueue(let taskQueueData):
@@ -67,9 +70,9 @@ public final class ActivityManager {
                 )
                 self.currentActivity = activity
                 self.isEnding = false
-                webviewLog("Successfully created live activity, ID: \(activity.id)")
+                pluginLog("Successfully created live activity, ID: \(activity.id)")
             } catch {
-                webviewLog("Error: Request to create live activity failed - \(error.localizedDescription)")
+                pluginLog("Error: Request to create live activity failed - \(error.localizedDescription)", level: "error")
             }
         }
     }
@@ -93,37 +96,46 @@ public final class ActivityManager {
             return
         }
 
-        webviewLog("Cold launch: ending \(activities.count) orphaned live activity(ies).")
+        pluginLog("Cold launch: ending \(activities.count) orphaned live activity(ies).")
 
         Task.detached {
             for activity in activities {
                 await activity.end(nil, dismissalPolicy: .immediate)
             }
             await MainActor.run {
-                webviewLog("Cold launch: orphaned live activities ended.")
+                pluginLog("Cold launch: orphaned live activities ended.")
             }
         }
     }
 
+    // MARK: - Query
+
+    /// 系统当前仍在展示的活动（取创建时传入的业务 ID，而不是 ActivityKit 的 ID）。
+    public func visibleActivityIds() -> [String] {
+        Activity<LiveActivityAttributes>.activities
+            .filter { $0.activityState == .active }
+            .map { $0.attributes.id }
+    }
+
     // MARK: - Update
 
     /// 更新当前实时活动的内容状态。
     /// - Parameter newState: 新的动态内容状态字典。
     public func updateActivity(newState: [String: String]) {
         guard !isEnding else {
-            webviewLog("Skip update: activity is ending.")
+            pluginLog("Skip update: activity is ending.")
             return
         }
 
         if currentActivity == nil {
             self.currentActivity = Activity<LiveActivityAttributes>.activities.first
             if let recovered = currentActivity {
-                webviewLog("Recovered activity before update, ID: \(recovered.id)")
+                pluginLog("Recovered activity before update, ID: \(recovered.id)")
             }
         }
 
         guard let activityID = currentActivity?.id else {
-            webviewLog("Note: There are no live activities in progress to update.")
+            pluginLog("Note: There are no live activities in progress to update.")
             return
         }
 
@@ -132,7 +144,7 @@ public final class ActivityManager {
         Task.detached {
             guard let activity = Activity<LiveActivityAttributes>.activities.first(where: { $0.id == activityID }) else {
                 await MainActor.run {
-                    webviewLog("Note: Activity not found when updating.")
+                    pluginLog("Note: Activity not found when updating.")
                 }
                 return
             }
@@ -143,7 +155,7 @@ public final class ActivityManager {
             await activity.update(content)
 
             await MainActor.run {
-                webviewLog("Live activity updated successfully.")
+                pluginLog("Live activity updated successfully.")
             }
         }
     }
@@ -162,12 +174,12 @@ public final class ActivityManager {
         if currentActivity == nil {
             self.currentActivity = Activity<LiveActivityAttributes>.activities.first
             if let recovered = currentActivity {
-                webviewLog("Recovered activity for ending, ID: \(recovered.id)")
+                pluginLog("Recovered activity for ending, ID: \(recovered.id)")
             }
         }
 
         guard let activityID = currentActivity?.id else {
-            webviewLog("Note: There are no live activities in progress to end.")
+            pluginLog("Note: There are no live activities in progress to end.")
             return
         }
 
@@ -181,7 +193,7 @@ public final class ActivityManager {
                 await MainActor.run {
                     self.currentActivity = nil
                     self.isEnding = false
-                    webviewLog("Note: Activity not found when ending.")
+                    pluginLog("Note: Activity not found when ending.")
                 }
                 return
             }
@@ -202,9 +214,9 @@ public final class ActivityManager {
                 self.isEnding = false
 
                 if Activity<LiveActivityAttributes>.activities.isEmpty {
-                    webviewLog("The live activity has ended (immediate).")
+                    pluginLog("The live activity has ended (immediate).")
                 } else {
-                    webviewLog("The live activity requested to end; system may finalize shortly.")
+                    pluginLog("The live activity requested to end; system may finalize shortly.")
                 }
             }
         }
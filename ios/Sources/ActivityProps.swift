@@ -74,6 +74,13 @@ public struct UpdateLiveActivityRequest: Decodable, Sendable {
     public var state: [String: String]
 }
 
+public struct PostAlertRequest: Decodable, Sendable {
+    public var title: String
+    public var body: String
+    public var silent: Bool?
+    public var group: String?
+}
+
 public struct LiveActivityAttributes: ActivityAttributes {
     public struct ContentState: Codable, Hashable {
         public var stateItems: [String: String]
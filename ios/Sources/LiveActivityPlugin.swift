@@ -1,11 +1,12 @@
 import SwiftRs
 import Tauri
 import WebKit
+import ActivityKit
+import UserNotifications
 
 class LiveActivityPlugin: Plugin {
     override func load(webview: WKWebView) {
         Task { @MainActor in
-            WebViewLogger.shared.set(webview: webview)
             // 冷启动清理：上次进程若被杀，残留的实时活动是孤儿，直接清掉。
             if #available(iOS 16.2, *) {
                 ActivityManager.shared.endOrphanedActivities()
@@ -13,19 +14,35 @@ class LiveActivityPlugin: Plugin {
         }
     }
     
+    @objc public func setLogChannel(_ invoke: Invoke) throws {
+        let args = try invoke.parseArgs(SetLogChannelArgs.self)
+        PluginLogger.shared.attach(args.channel)
+        invoke.resolve()
+    }
+
     @objc public func createLiveActivity(_ invoke: Invoke) throws {
         let args = try invoke.parseArgs(CreateLiveActivityRequest.self)
         if #available(iOS 16.2, *) {
-          webviewLog("Joining main thread...")
+          guard ActivityAuthorizationInfo().areActivitiesEnabled else {
+              invoke.reject("The user has disabled live activity in the system.", code: "PermissionDenied")
+              return
+          }
+          pluginLog("Joining main thread...")
           Task { @MainActor in
-              webviewLog("Creating activity...")
+              // 单实例：已有活动时明确拒绝，而不是静默跳过
+              guard !ActivityManager.shared.hasActivity else {
+                  invoke.reject("A live activity already exists.", code: "AlreadyExists")
+                  return
+              }
+              pluginLog("Creating activity...")
               ActivityManager.shared.createActivity(with: args)
+              invoke.resolve()
           }
         } else {
-          webviewLog("Live Activity Unsupport this system.")
+          pluginLog("Live Activity Unsupport this system.")
           // 不支持的系统什么都不做
+          invoke.resolve()
         }
-        invoke.resolve()
     }
     
     @objc public func updateLiveActivity(_ invoke: Invoke) throws {
@@ -46,6 +63,79 @@ class LiveActivityPlugin: Plugin {
         }
         invoke.resolve()
     }
+
+    @objc public func getPermissionState(_ invoke: Invoke) throws {
+        invoke.resolve(["state": permissionState()])
+    }
+
+    /// 实时活动没有授权弹窗，只能由用户在系统设置里开关，因此请求等同于查询。
+    @objc public func requestPermission(_ invoke: Invoke) throws {
+        invoke.resolve(["state": permissionState()])
+    }
+
+    private func permissionState() -> String {
+        if #available(iOS 16.2, *) {
+            return ActivityAuthorizationInfo().areActivitiesEnabled ? "granted" : "denied"
+        }
+        return "denied"
+    }
+
+    @objc public func getCapabilities(_ invoke: Invoke) throws {
+        var presents = false
+        if #available(iOS 16.2, *) {
+            presents = ActivityAuthorizationInfo().areActivitiesEnabled
+        }
+        // 灵动岛/锁屏的样式由 Widget 决定，这里只声明插件层面能传递的能力。
+        invoke.resolve([
+            "presents": presents,
+            "progressBar": presents,
+            "indeterminateProgress": presents,
+            "buttons": false,
+            "images": presents,
+            "updatesInPlace": true,
+            "maxConcurrent": presents ? 1 : 0,
+        ])
+    }
+
+    @objc public func postAlert(_ invoke: Invoke) throws {
+        let args = try invoke.parseArgs(PostAlertRequest.self)
+        let content = UNMutableNotificationContent()
+        content.title = args.title
+        content.body = args.body
+        if args.silent != true {
+            content.sound = .default
+        }
+        // 同一分组的汇总复用同一个 identifier，新的替换旧的。
+        var identifier = UUID().uuidString
+        if let group = args.group {
+            content.threadIdentifier = group
+            identifier = "summary-\(group)"
+        }
+        let request = UNNotificationRequest(identifier: identifier, content: content, trigger: nil)
+        UNUserNotificationCenter.current().add(request) { error in
+            if let error = error {
+                pluginLog("Failed to post alert: \(error)", level: "error")
+                invoke.reject(error.localizedDescription)
+            } else {
+                invoke.resolve()
+            }
+        }
+    }
+
+    /// 专注模式的状态需要额外的 entitlement 才能读取，这里如实返回未知。
+    @objc public func getDoNotDisturb(_ invoke: Invoke) throws {
+        invoke.resolve(["enabled": NSNull()])
+    }
+
+    @objc public func getVisibleActivities(_ invoke: Invoke) throws {
+        if #available(iOS 16.2, *) {
+            Task { @MainActor in
+                invoke.resolve(["ids": ActivityManager.shared.visibleActivityIds()])
+            }
+        } else {
+            invoke.resolve(["ids": [String]()])
+        }
+    }
 }
 
 @_cdecl("init_plugin_live_activity")
@@ -0,0 +1,49 @@
+import Foundation
+import Tauri
+
+struct SetLogChannelArgs: Decodable {
+    let channel: Channel
+}
+
+private struct NativeLogLine: Encodable {
+    let level: String
+    let message: String
+}
+
+/// 把原生日志转发进 Rust 侧的 tracing；通道挂上之前退回到 print。
+final class PluginLogger: @unchecked Sendable {
+    static let shared = PluginLogger()
+
+    private let lock = NSLock()
+    private var channel: Channel?
+
+    private init() {}
+
+    func attach(_ channel: Channel) {
+        lock.lock()
+        self.channel = channel
+        lock.unlock()
+        log("info", "Logger attached to the Rust log channel.")
+    }
+
+    func log(_ level: String, _ message: String) {
+        lock.lock()
+        let channel = self.channel
+        lock.unlock()
+
+        guard let channel else {
+            print("[LiveActivity] \(message)")
+            return
+        }
+
+        do {
+            try channel.send(NativeLogLine(level: level, message: message))
+        } catch {
+            print("[LiveActivity] Failed to forward log: \(error). Message: \(message)")
+        }
+    }
+}
+
+public func pluginLog(_ message: String, level: String = "info") {
+    PluginLogger.shared.log(level, message)
+}
@@ -0,0 +1,1744 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use anyhow::Result;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::backend::Backend;
+use crate::batch::{self, CompletionBatcher};
+use crate::chain::PresenterChain;
+use crate::clock::{self, now_millis};
+#[cfg(feature = "history")]
+use crate::history::HistoryStore;
+use crate::in_app::InAppFeed;
+use crate::inhibit::SleepInhibitor;
+#[cfg(feature = "ipc")]
+use crate::instance::{self, Coordinator};
+use crate::metrics::{self, Metrics};
+use crate::models::*;
+use crate::owner::ActivityGuard;
+use crate::power::SystemPower;
+use crate::preferences::{self, PreferenceStore};
+use crate::quiet::{self, QuietGate};
+use crate::recorder::{self, Recorder};
+use crate::registry::ActivityRegistry;
+use crate::schedule::{self, Step, Timing};
+use crate::sink::SinkQueue;
+use crate::telemetry::instrument;
+use crate::throttle::{Held, Throttler};
+use crate::PluginOptions;
+
+/// Presenter reported for activities held back by quiet hours.
+const DEFERRED: &str = "deferred";
+
+/// Presenter reported for activities waiting for the visible slot.
+const QUEUED: &str = "queued";
+
+/// Presenter reported for activities whose task type the user turned off.
+const SUPPRESSED: &str = "suppressed";
+
+/// File in the app config dir holding the per-task-type preferences.
+const PREFERENCES_FILE: &str = "live-activity-preferences.json";
+
+/// File in the app data dir holding the history of ended activities.
+#[cfg(feature = "history")]
+const HISTORY_FILE: &str = "live-activity-history.json";
+
+/// `hint` of a stalled activity unless `Builder::stalled_hint` sets another.
+const DEFAULT_STALLED_HINT: &str = "Waiting…";
+
+/// How long the test activity of [`LiveActivity::diagnose`] stays on screen.
+const TEST_ACTIVITY_LINGER: Duration = Duration::from_secs(2);
+
+/// How often the end of a quiet period is checked when no call comes in.
+const QUIET_TICK: Duration = Duration::from_secs(30);
+
+/// How often `start_at`/`end_at` and the countdowns are checked.
+const SCHEDULE_TICK: Duration = Duration::from_secs(1);
+
+/// Access to the live-activity APIs.
+pub struct LiveActivity<R: Runtime> {
+    app: AppHandle<R>,
+    chain: PresenterChain,
+    registry: ActivityRegistry,
+    metrics: Arc<Metrics>,
+    recorder: Option<Recorder>,
+    /// Events wait here until the operation that emitted them releases `op`.
+    sinks: SinkQueue,
+    quiet: QuietGate,
+    preferences: PreferenceStore,
+    batcher: CompletionBatcher,
+    stalled_hint: String,
+    #[cfg(feature = "ipc")]
+    instances: Option<Coordinator>,
+    #[cfg(feature = "history")]
+    history: Option<Arc<HistoryStore>>,
+    in_app: Option<Arc<InAppFeed>>,
+    sleep: Arc<SleepInhibitor>,
+    throttle: Arc<Throttler>,
+    /// Held by every call and background check that changes what is shown, so
+    /// a check never acts on state a concurrent call is changing.
+    op: Mutex<()>,
+}
+
+impl<R: Runtime> LiveActivity<R> {
+    pub(crate) fn new(
+        app: &AppHandle<R>,
+        backend: Box<dyn Backend>,
+        options: PluginOptions,
+    ) -> Self {
+        if let Some(clock) = options.clock {
+            clock::install(clock);
+        }
+        let metrics = Arc::new(Metrics::default());
+        if let Some(export) = options.prometheus {
+            metrics::spawn_prometheus_export(metrics.clone(), export);
+        }
+        spawn_ticker(app.clone(), QUIET_TICK, |live_activity| {
+            let _op = live_activity.op();
+            live_activity.release_quiet();
+        });
+        spawn_ticker(app.clone(), SCHEDULE_TICK, Self::run_schedule);
+        #[cfg(feature = "ipc")]
+        let (backend, instances) = match options
+            .instances
+            .and_then(|opts| Some((opts, instance::lock_dir(app)?)))
+        {
+            Some((opts, dir)) => {
+                let namespace = opts
+                    .namespace
+                    .unwrap_or_else(|| std::process::id().to_string());
+                // 共用的端点只有先绑上的那个进程能用，锁里要写本实例自己的。
+                let endpoint = opts
+                    .endpoint
+                    .map(|endpoint| crate::ipc::instance_endpoint(&endpoint, &namespace));
+                #[cfg(desktop)]
+                if let Some(endpoint) = &endpoint {
+                    crate::ipc::spawn_server(app.clone(), endpoint.clone());
+                }
+                let coordinator = Coordinator::new(backend, dir, namespace, endpoint);
+                coordinator.tick();
+                spawn_ticker(app.clone(), instance::LOCK_REFRESH, Self::run_instance);
+                let backend: Box<dyn Backend> = Box::new(coordinator.clone());
+                (backend, Some(coordinator))
+            }
+            None => (backend, None),
+        };
+        let preferences_path = app
+            .path()
+            .app_config_dir()
+            .inspect_err(|err| tracing::warn!(error = %err, "no config dir for preferences"))
+            .ok()
+            .map(|dir| dir.join(PREFERENCES_FILE));
+        let mut sinks = options.sinks;
+        let sleep = Arc::new(SleepInhibitor::new(app.package_info().name.clone()));
+        sinks.push(Box::new(sleep.clone()));
+        let throttle = Arc::new(Throttler::new(
+            options.update_throttle,
+            options
+                .power
+                .unwrap_or_else(|| Arc::new(SystemPower::default())),
+            metrics.clone(),
+        ));
+        spawn_throttle_flush(app.clone(), throttle.clone());
+        #[cfg(feature = "history")]
+        let history = options.history.map(|limit| {
+            let path = app
+                .path()
+                .app_data_dir()
+                .inspect_err(|err| tracing::warn!(error = %err, "no data dir for history"))
+                .ok()
+                .map(|dir| dir.join(HISTORY_FILE));
+            let store = Arc::new(HistoryStore::load(path, limit));
+            sinks.push(Box::new(store.clone()));
+            store
+        });
+        Self {
+            app: app.clone(),
+            chain: PresenterChain::new(backend, options.fallbacks, options.retry, metrics.clone()),
+            registry: ActivityRegistry::default(),
+            metrics,
+            recorder: options.record_sessions.as_deref().and_then(open_recorder),
+            sinks: SinkQueue::new(sinks),
+            quiet: QuietGate::new(options.quiet_hours),
+            preferences: PreferenceStore::load(preferences_path),
+            batcher: CompletionBatcher::new(options.batch_window.unwrap_or(batch::DEFAULT_WINDOW)),
+            stalled_hint: options
+                .stalled_hint
+                .unwrap_or_else(|| DEFAULT_STALLED_HINT.to_string()),
+            #[cfg(feature = "ipc")]
+            instances,
+            #[cfg(feature = "history")]
+            history,
+            in_app: options.in_app,
+            sleep,
+            throttle,
+            op: Mutex::new(()),
+        }
+    }
+
+    pub fn create_live_activity(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+        // 系统弹窗可能要等很久，先在锁外问完。
+        self.chain.ask_permissions();
+        let _op = self.op();
+        let content = payload.activity_content.clone();
+        self.recorded(RecordedCall::Create(payload.clone()), || {
+            self.instrument("create", Some(content.id()), || {
+                let priority = content.priority();
+                let current = self.registry.current_id();
+                let current_priority = self.registry.current_priority();
+                let replaces = current.as_deref() == Some(content.id());
+                if !replaces && current_priority.is_some_and(|p| priority < p) {
+                    // 优先级不够，先排队，等占着位置的活动结束再展示。
+                    self.registry.queued(content.clone(), QUEUED);
+                } else {
+                    // Android、iOS 只有一个位置，正在展示时新建会被直接跳过，得先撤下来。
+                    if current.is_some() && self.chain.is_presenting() {
+                        self.chain.remove()?;
+                    }
+                    // 攒着的更新属于让位的活动，不能再推。
+                    if let Some(id) = &current {
+                        self.throttle.discard(id);
+                    }
+                    let preempts = !replaces && current_priority.is_some_and(|p| priority > p);
+                    if preempts {
+                        self.preempt();
+                    }
+                    // 同优先级的新活动直接顶掉当前的，它到这里就算结束了。
+                    let replaced = current.clone().filter(|_| !replaces && !preempts);
+                    let presenter = match self.show(payload) {
+                        Ok(presenter) => presenter,
+                        Err(err) => {
+                            // 新活动没展示出来就不登记；撤下的活动重新展示。
+                            if current.is_some() && !preempts {
+                                self.registry.preempted(QUEUED);
+                            }
+                            self.restore_next();
+                            return Err(err);
+                        }
+                    };
+                    if let Some(id) = &replaced {
+                        self.ended_early(id);
+                    }
+                    self.registry.created(content.clone(), presenter);
+                    if let Some(id) = replaced {
+                        self.emit(ActivityEvent::Ended {
+                            id,
+                            outcome: EndOutcome::Ended,
+                        });
+                    }
+                }
+                self.metrics.set_active(self.registry.len());
+                if let Some((created_at, _)) = self.registry.timestamps(content.id()) {
+                    let id = content.id();
+                    if let Some(stall_after) = content.stall_after() {
+                        spawn_watch(&self.app, id, created_at, stall_after, Self::check_stall);
+                    }
+                    if let Some(interval) = content.heartbeat() {
+                        spawn_watch(&self.app, id, created_at, interval, Self::check_heartbeat);
+                    }
+                }
+                self.emit(ActivityEvent::Created {
+                    id: content.id().to_string(),
+                    progress: parse_progress(content.state()),
+                    content: content.clone(),
+                });
+                Ok(())
+            })
+        })
+    }
+
+    /// Updates the activity `payload.id`, or the one holding the visible slot.
+    ///
+    /// Queued activities only keep the new state until they are shown.
+    pub fn update_live_activity(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+        let _op = self.op();
+        let current = self.registry.current_id();
+        let id = payload.id.clone().or_else(|| current.clone());
+        self.recorded(RecordedCall::Update(payload.clone()), || {
+            self.instrument("update", id.as_deref(), || {
+                let quiet = self.release_quiet();
+                let state = payload.state.clone();
+                let queued = id.as_deref().filter(|id| current.as_deref() != Some(*id));
+                if let Some(queued) = queued {
+                    if !self.registry.contains(queued) {
+                        anyhow::bail!("No live activity with id `{queued}`");
+                    }
+                } else if self.registry.current_presenter() != Some(SUPPRESSED)
+                    && !self.update_deferred(&state)
+                {
+                    let mut payload = payload;
+                    let current = self.registry.current_content();
+                    let preference = match &current {
+                        Some(content) => self.apply_preference(content, &mut payload.state),
+                        None => TaskPreference::default(),
+                    };
+                    // 还没到开始时间，继续显示倒计时，新状态先记下来。
+                    if let Some(hint) = id.as_deref().and_then(|id| self.registry.start_hint(id)) {
+                        schedule::announce(&mut payload.state, &hint);
+                    }
+                    if quiet.is_some() {
+                        quiet::mark_silent(&mut payload.state);
+                        // 完成提醒先攒着，安静时段结束后合并成一条。
+                        if parse_progress(&state) == Some(1.0) && preference.show_completion {
+                            if let Some(content) = current.as_ref().filter(|c| c.group().is_none())
+                            {
+                                self.quiet.completed(content.title().to_string());
+                                // 汇总里已经有它了，这里不再单独发完成通知。
+                                preferences::hide_completion(&mut payload.state);
+                            }
+                        }
+                    }
+                    let content = current.map(|mut content| {
+                        *content.state_mut() = payload.state.clone();
+                        content
+                    });
+                    match content {
+                        // 省电时只记下最新状态，间隔到了再推给系统。
+                        Some(content)
+                            if !self.throttle.admit(&content, &payload.state, now_millis()) =>
+                        {
+                            self.throttle.hold(Held {
+                                id: content.id().to_string(),
+                                payload,
+                                content: Some(content),
+                            });
+                        }
+                        content => {
+                            if let Some(presenter) = self.chain.update(payload, content)? {
+                                self.registry.moved(presenter);
+                            }
+                        }
+                    }
+                }
+                if let Some(id) = id.clone() {
+                    if let Some(content) = self.registry.content(&id) {
+                        let was_done = parse_progress(content.state()) == Some(1.0);
+                        if !was_done && parse_progress(&state) == Some(1.0) {
+                            self.finished(&content, true);
+                        }
+                    }
+                    let resumed = self.registry.updated(&id, &state);
+                    self.emit(ActivityEvent::Updated {
+                        id: id.clone(),
+                        progress: parse_progress(&state),
+                        state,
+                    });
+                    if resumed {
+                        self.emit(ActivityEvent::Resumed { id });
+                    }
+                }
+                Ok(())
+            })
+        })
+    }
+
+    /// Ends the activity holding the visible slot and shows the next queued one.
+    pub fn remove_live_activity(&self) -> Result<()> {
+        let _op = self.op();
+        let id = self.registry.current_id();
+        self.recorded(RecordedCall::Remove, || {
+            self.instrument("remove", id.as_deref(), || {
+                self.end_current(EndOutcome::Ended)
+            })
+        })
+    }
+
+    /// Ends the activity `id`, whether it holds the visible slot or is queued.
+    pub fn end_activity(&self, id: &str) -> Result<()> {
+        let _op = self.op();
+        self.end(id, EndOutcome::Ended)
+    }
+
+    /// Tells the plugin the owner of `id` is still alive.
+    ///
+    /// Only matters for activities created with `heartbeat_ms`; updates count
+    /// as heartbeats too.
+    pub fn heartbeat(&self, id: &str) -> Result<()> {
+        let _op = self.op();
+        if !self.registry.heartbeat(id) {
+            anyhow::bail!("No live activity with id `{id}`");
+        }
+        Ok(())
+    }
+
+    /// Binds the activity `id` to the returned guard; dropping the guard while
+    /// the activity still runs ends it as interrupted.
+    ///
+    /// `None` when there is no such activity.
+    pub fn guard(&self, id: &str) -> Option<ActivityGuard<R>> {
+        let (created_at, _) = self.registry.timestamps(id)?;
+        Some(ActivityGuard::new(
+            self.app.clone(),
+            id.to_string(),
+            created_at,
+        ))
+    }
+
+    /// Ends `id`; the caller holds [`LiveActivity::op`].
+    fn end(&self, id: &str, outcome: EndOutcome) -> Result<()> {
+        let call = RecordedCall::End { id: id.to_string() };
+        self.recorded(call, || {
+            self.instrument("end", Some(id), || {
+                if self.registry.current_id().as_deref() == Some(id) {
+                    return self.end_current(outcome);
+                }
+                if self.registry.contains(id) {
+                    self.ended_early(id);
+                    self.registry.removed(id);
+                    self.metrics.set_active(self.registry.len());
+                    self.emit(ActivityEvent::Ended {
+                        id: id.to_string(),
+                        outcome,
+                    });
+                }
+                Ok(())
+            })
+        })
+    }
+
+    fn end_current(&self, outcome: EndOutcome) -> Result<()> {
+        let id = self.registry.current_id();
+        if let Some(id) = &id {
+            self.throttle.discard(id);
+        }
+        self.release_quiet();
+        let suppressed = self.registry.current_presenter() == Some(SUPPRESSED);
+        if self.quiet.take_deferred().is_none() && !suppressed {
+            self.chain.remove()?;
+        }
+        if let Some(id) = id {
+            self.ended_early(&id);
+            self.registry.removed(&id);
+            self.emit(ActivityEvent::Ended { id, outcome });
+        }
+        self.restore_next();
+        self.metrics.set_active(self.registry.len());
+        Ok(())
+    }
+
+    /// Ends the activity created at `created_at` as interrupted, if it still runs.
+    pub(crate) fn interrupt(&self, id: &str, created_at: u64) {
+        let _op = self.op();
+        self.interrupt_locked(id, created_at);
+    }
+
+    fn interrupt_locked(&self, id: &str, created_at: u64) {
+        if self.registry.timestamps(id).map(|(created, _)| created) != Some(created_at) {
+            return;
+        }
+        tracing::info!(id, "owner went away, interrupting activity");
+        if let Err(err) = self.end(id, EndOutcome::Interrupted) {
+            tracing::warn!(id, error = %err, "failed to end interrupted activity");
+        }
+    }
+
+    /// Interrupts every activity owned by the webview `label`.
+    pub(crate) fn owner_gone(&self, label: &str) {
+        for id in self.registry.owned_by(label) {
+            if let Some((created_at, _)) = self.registry.timestamps(&id) {
+                self.interrupt(&id, created_at);
+            }
+        }
+    }
+
+    /// Interrupts `id` once it went its heartbeat interval without a sign of
+    /// life. Returns how long to wait before checking again, or `None` once the
+    /// activity created at `created_at` is gone.
+    fn check_heartbeat(&self, id: &str, created_at: u64) -> Option<Duration> {
+        let _op = self.op();
+        let (created, _) = self.registry.timestamps(id)?;
+        if created != created_at {
+            return None;
+        }
+        let interval = self.registry.content(id)?.heartbeat()?;
+        let silent =
+            Duration::from_millis(now_millis().saturating_sub(self.registry.alive_at(id)?));
+        if silent < interval {
+            return Some(interval - silent);
+        }
+        self.interrupt_locked(id, created_at);
+        None
+    }
+
+    /// Presents an activity that got the visible slot, honoring the user's
+    /// preferences and quiet hours. Returns the presenter it ended up with.
+    fn show(&self, mut payload: CreateLiveActivityRequest) -> Result<&'static str> {
+        let quiet = self.release_quiet();
+        let content = payload.activity_content.clone();
+        let preference = self.apply_preference(&content, payload.activity_content.state_mut());
+        if let Some(hint) = Timing::new(content.start_at(), None, now_millis()).hint {
+            schedule::announce(payload.activity_content.state_mut(), &hint);
+        }
+        let presenter = match quiet {
+            _ if !preference.enabled => {
+                // 用户关掉了这一类，只记账不展示，同样顶掉当前活动。
+                if self.quiet.take_deferred().is_none() && self.chain.is_presenting() {
+                    self.chain.remove()?;
+                }
+                SUPPRESSED
+            }
+            Some(_) if self.quiet.mode() == QuietMode::Defer => {
+                // 新建会顶掉当前活动，即使新活动被推迟也一样。
+                if self.chain.is_presenting() {
+                    self.chain.remove()?;
+                }
+                self.quiet.defer(payload);
+                DEFERRED
+            }
+            Some(_) => {
+                quiet::mark_silent(payload.activity_content.state_mut());
+                self.chain.create(payload)?
+            }
+            None => self.chain.create(payload)?,
+        };
+        Ok(presenter)
+    }
+
+    /// Moves the activity holding the visible slot back to the queue.
+    fn preempt(&self) {
+        if self.registry.current_presenter() == Some(DEFERRED) {
+            self.quiet.take_deferred();
+        }
+        if let Some(id) = self.registry.preempted(QUEUED) {
+            tracing::debug!(id, "activity preempted");
+        }
+    }
+
+    /// Gives the free slot to the most important queued activity.
+    fn restore_next(&self) {
+        if self.registry.current_id().is_some() {
+            return;
+        }
+        let Some(content) = self.registry.next_queued() else {
+            return;
+        };
+        let id = content.id().to_string();
+        let request = CreateLiveActivityRequest {
+            activity_content_v: 1,
+            activity_content: content,
+        };
+        match self.show(request) {
+            Ok(presenter) => self.registry.promoted(&id, presenter),
+            Err(err) => tracing::warn!(id, error = %err, "failed to restore queued activity"),
+        }
+    }
+
+    pub fn permission_state(&self) -> Result<PermissionState> {
+        self.instrument("permission_state", None, || {
+            self.chain.primary().permission_state()
+        })
+    }
+
+    /// Prompts the user where the platform allows it and returns the resulting state.
+    pub fn request_permission(&self) -> Result<PermissionState> {
+        self.instrument("request_permission", None, || {
+            self.chain.primary().request_permission()
+        })
+    }
+
+    /// Capabilities of the presenter showing the current activity.
+    pub fn capabilities(&self) -> Result<Capabilities> {
+        self.instrument("capabilities", None, || self.chain.active().capabilities())
+    }
+
+    pub fn quiet_hours(&self) -> Option<QuietHours> {
+        self.quiet.policy()
+    }
+
+    /// Replaces the quiet-hours policy; `None` turns quiet hours off.
+    pub fn set_quiet_hours(&self, policy: Option<QuietHours>) -> Result<()> {
+        let _op = self.op();
+        self.quiet.set_policy(policy);
+        self.release_quiet();
+        Ok(())
+    }
+
+    pub fn quiet_status(&self) -> Result<QuietStatus> {
+        let _op = self.op();
+        let reason = self.release_quiet();
+        Ok(self.quiet.status(reason))
+    }
+
+    /// Per-task-type preferences the user changed; other types use the defaults.
+    pub fn preferences(&self) -> BTreeMap<String, TaskPreference> {
+        self.preferences.all()
+    }
+
+    /// Stores the preference for `task_type`.
+    ///
+    /// Turning a type on or off takes effect with its next activity; sound and
+    /// completion settings with the next update.
+    pub fn set_preference(&self, task_type: &str, preference: TaskPreference) -> Result<()> {
+        self.preferences.set(task_type, preference)
+    }
+
+    /// Returns `task_type` to the default preference.
+    pub fn reset_preference(&self, task_type: &str) -> Result<()> {
+        self.preferences.reset(task_type)
+    }
+
+    /// Ended activities matching `query`, newest first.
+    #[cfg(feature = "history")]
+    pub fn history(&self, query: &HistoryQuery) -> Result<Vec<HistoryEntry>> {
+        Ok(self.history_store()?.query(query))
+    }
+
+    /// Success rate and mean duration of the ended activities matching `query`.
+    #[cfg(feature = "history")]
+    pub fn history_stats(&self, query: &HistoryQuery) -> Result<HistoryStats> {
+        Ok(self.history_store()?.stats(query))
+    }
+
+    #[cfg(feature = "history")]
+    pub fn clear_history(&self) -> Result<()> {
+        self.history_store()?.clear()
+    }
+
+    #[cfg(feature = "history")]
+    fn history_store(&self) -> Result<&HistoryStore> {
+        match &self.history {
+            Some(history) => Ok(history),
+            None => anyhow::bail!("activity history is not enabled"),
+        }
+    }
+
+    // 没编进 history 时命令照样注册，调用时报错即可。
+    #[cfg(not(feature = "history"))]
+    pub fn history(&self, _query: &HistoryQuery) -> Result<Vec<HistoryEntry>> {
+        anyhow::bail!("activity history is not compiled in; enable the `history` feature")
+    }
+
+    #[cfg(not(feature = "history"))]
+    pub fn history_stats(&self, _query: &HistoryQuery) -> Result<HistoryStats> {
+        anyhow::bail!("activity history is not compiled in; enable the `history` feature")
+    }
+
+    #[cfg(not(feature = "history"))]
+    pub fn clear_history(&self) -> Result<()> {
+        anyhow::bail!("activity history is not compiled in; enable the `history` feature")
+    }
+
+    /// What the device runs on, as seen by the update throttle.
+    pub fn power_state(&self) -> PowerState {
+        self.throttle.power_state()
+    }
+
+    /// Sends every [`InAppView`] of the in-app presenter to `channel`, starting
+    /// with the activity it currently shows.
+    pub fn watch_in_app(&self, channel: Channel<InAppView>) -> Result<()> {
+        match &self.in_app {
+            Some(feed) => feed.subscribe(channel),
+            None => anyhow::bail!("the in-app presenter is not enabled"),
+        }
+    }
+
+    /// How this process shares the presenter with other instances of the app;
+    /// `None` unless the plugin was built with `Builder::coordinate_instances`.
+    #[cfg(feature = "ipc")]
+    pub fn instance_status(&self) -> Option<InstanceStatus> {
+        self.instances.as_ref().map(Coordinator::status)
+    }
+
+    #[cfg(not(feature = "ipc"))]
+    pub fn instance_status(&self) -> Option<InstanceStatus> {
+        None
+    }
+
+    /// Leaves presenting to another instance of the app.
+    ///
+    /// The activities shown for other instances move to the next owner, and so
+    /// does the one of this instance; its updates are forwarded from then on.
+    #[cfg(feature = "ipc")]
+    pub fn hand_over(&self) -> Result<()> {
+        let Some(instances) = &self.instances else {
+            anyhow::bail!("instance coordination is not enabled");
+        };
+        let _op = self.op();
+        let remote = self.remote_activities();
+        instances.hand_over(remote.clone(), true)?;
+        self.end_handed_over(&remote);
+        Ok(())
+    }
+
+    /// Ends the activities handed to the next owner the usual way, so the
+    /// sinks see them go.
+    #[cfg(feature = "ipc")]
+    fn end_handed_over(&self, remote: &[CreateLiveActivityRequest]) {
+        for request in remote {
+            let id = request.activity_content.id();
+            if let Err(err) = self.end(id, EndOutcome::Interrupted) {
+                tracing::warn!(id, error = %err, "failed to end handed over activity");
+            }
+        }
+    }
+
+    #[cfg(not(feature = "ipc"))]
+    pub fn hand_over(&self) -> Result<()> {
+        anyhow::bail!("instance coordination is not compiled in; enable the `ipc` feature")
+    }
+
+    /// Hands the activities of other instances over when the app exits.
+    pub(crate) fn leave_instances(&self) {
+        #[cfg(feature = "ipc")]
+        if let Some(instances) = &self.instances {
+            instances.leave(self.remote_activities());
+        }
+    }
+
+    /// Lets the system sleep again when the app exits with activities running.
+    pub(crate) fn release_sleep(&self) {
+        self.sleep.release_all();
+    }
+
+    /// Activities forwarded to this instance by other ones, with their latest state.
+    #[cfg(feature = "ipc")]
+    fn remote_activities(&self) -> Vec<CreateLiveActivityRequest> {
+        let Some(instances) = &self.instances else {
+            return Vec::new();
+        };
+        self.registry
+            .snapshots(|_| false)
+            .into_iter()
+            .filter(|s| instance::origin(&s.content).is_some_and(|ns| ns != instances.namespace()))
+            .map(|s| {
+                let mut content = s.content;
+                *content.state_mut() = s.state;
+                CreateLiveActivityRequest {
+                    activity_content_v: 1,
+                    activity_content: content,
+                }
+            })
+            .collect()
+    }
+
+    /// Keeps the instance lock and adopts what a previous owner handed over.
+    #[cfg(feature = "ipc")]
+    fn run_instance(&self) {
+        let Some(instances) = &self.instances else {
+            return;
+        };
+        if instances.tick() {
+            let _op = self.op();
+            let remote = self.remote_activities();
+            if let Err(err) = instances.surrender(remote.clone()) {
+                tracing::warn!(error = %err, "failed to hand activities over");
+            }
+            self.end_handed_over(&remote);
+            return;
+        }
+        for request in instances.take_handover() {
+            let content = request.activity_content.clone();
+            {
+                let _op = self.op();
+                if self.registry.contains(content.id()) {
+                    continue;
+                }
+                if self.registry.current_id().is_some() {
+                    // 接手的活动不抢本实例正在展示的位置。
+                    self.registry.queued(content.clone(), QUEUED);
+                    self.metrics.set_active(self.registry.len());
+                    self.emit(ActivityEvent::Created {
+                        id: content.id().to_string(),
+                        progress: parse_progress(content.state()),
+                        content,
+                    });
+                    continue;
+                }
+            }
+            if let Err(err) = self.create_live_activity(request) {
+                tracing::warn!(id = content.id(), error = %err, "failed to adopt activity");
+            }
+        }
+    }
+
+    /// Switches `id` to the stalled presentation once it went `stall_after`
+    /// without an update. Returns how long to wait before checking again, or
+    /// `None` once the activity created at `created_at` is gone.
+    fn check_stall(&self, id: &str, created_at: u64) -> Option<Duration> {
+        let _op = self.op();
+        let (created, updated) = self.registry.timestamps(id)?;
+        if created != created_at {
+            return None;
+        }
+        let content = self.registry.content(id)?;
+        let stall_after = content.stall_after()?;
+        // 预告阶段本来就不会有进度更新。
+        if self.registry.start_hint(id).is_some() {
+            return Some(stall_after);
+        }
+        let idle = Duration::from_millis(now_millis().saturating_sub(updated));
+        if idle < stall_after {
+            return Some(stall_after - idle);
+        }
+        if self.registry.stalled(id) {
+            self.present_stall(&content);
+            self.emit(ActivityEvent::Stalled { id: id.to_string() });
+        }
+        Some(stall_after)
+    }
+
+    /// Shows the activity with indeterminate progress and a waiting hint,
+    /// if it holds the visible slot. The next real update replaces it.
+    fn present_stall(&self, content: &ActivityContent) {
+        let mut state = content.state().clone();
+        state.remove("progress");
+        state.remove("percent");
+        state.insert("stalled".to_string(), "true".to_string());
+        state.insert("hint".to_string(), self.stalled_hint.clone());
+        self.push_state(content, state);
+    }
+
+    /// Pushes a plugin-made `state` for `content`, if it holds the visible slot.
+    /// The next real update replaces it. The caller holds [`LiveActivity::op`].
+    fn push_state(&self, content: &ActivityContent, mut state: HashMap<String, String>) {
+        if self.registry.current_id().as_deref() != Some(content.id())
+            || matches!(
+                self.registry.current_presenter(),
+                Some(QUEUED | SUPPRESSED | DEFERRED)
+            )
+        {
+            return;
+        }
+        self.apply_preference(content, &mut state);
+        // 插件自己推的状态变化不需要响铃。
+        quiet::mark_silent(&mut state);
+        let payload = UpdateLiveActivityRequest { state, id: None };
+        if let Err(err) = self.chain.update(payload, None) {
+            tracing::warn!(id = content.id(), error = %err, "failed to push activity state");
+        }
+    }
+
+    /// Pushes the update the throttle held back once its interval has passed,
+    /// if its activity still holds the visible slot.
+    fn flush_throttled(&self) {
+        let _op = self.op();
+        let Some(held) = self.throttle.take_due(now_millis()) else {
+            return;
+        };
+        if self.registry.current_id().as_deref() != Some(held.id.as_str())
+            || matches!(
+                self.registry.current_presenter(),
+                Some(QUEUED | SUPPRESSED | DEFERRED)
+            )
+        {
+            return;
+        }
+        match self.chain.update(held.payload, held.content) {
+            Ok(Some(presenter)) => self.registry.moved(presenter),
+            Ok(None) => {}
+            Err(err) => {
+                tracing::warn!(id = held.id, error = %err, "failed to push held update");
+            }
+        }
+    }
+
+    /// Announces, starts and expires activities with a `start_at`/`end_at`.
+    fn run_schedule(&self) {
+        let _op = self.op();
+        let now = now_millis();
+        for (id, timing) in self.registry.timings() {
+            match timing.step(now) {
+                Step::Idle => {}
+                Step::Countdown(hint) => {
+                    self.registry.set_start_hint(&id, hint.clone());
+                    if let Some(content) = self.registry.content(&id) {
+                        let mut state = content.state().clone();
+                        schedule::announce(&mut state, &hint);
+                        self.push_state(&content, state);
+                    }
+                }
+                Step::Start => {
+                    self.registry.set_started(&id);
+                    if let Some(content) = self.registry.content(&id) {
+                        self.push_state(&content, content.state().clone());
+                    }
+                    self.emit(ActivityEvent::Started { id });
+                }
+                Step::Expire => {
+                    if let Err(err) = self.end(&id, EndOutcome::Expired) {
+                        tracing::warn!(id, error = %err, "failed to end expired activity");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Gives the deferred activity `state`, with the hints `show` added to the
+    /// state it was created with; `false` when nothing is deferred.
+    fn update_deferred(&self, state: &HashMap<String, String>) -> bool {
+        let mut state = state.clone();
+        if let Some(content) = self.registry.current_content() {
+            self.apply_preference(&content, &mut state);
+        }
+        self.quiet.update_deferred(state)
+    }
+
+    /// Looks up the preference for the activity's task type and adds its
+    /// presentation hints to `state`.
+    ///
+    /// Grouped activities never post their own completion; their group's
+    /// summary does.
+    fn apply_preference(
+        &self,
+        content: &ActivityContent,
+        state: &mut HashMap<String, String>,
+    ) -> TaskPreference {
+        let preference = self.preferences.get(content.task_type());
+        if preference.silent {
+            quiet::mark_silent(state);
+        }
+        if !preference.show_completion || content.group().is_some() {
+            preferences::hide_completion(state);
+        }
+        preference
+    }
+
+    /// Counts a grouped activity that reached 100% or ended before it.
+    fn finished(&self, content: &ActivityContent, succeeded: bool) {
+        let Some(group) = content.group() else {
+            return;
+        };
+        let preference = self.preferences.get(content.task_type());
+        if !preference.enabled || !preference.show_completion {
+            return;
+        }
+        if self.batcher.finished(group, content.title(), succeeded) {
+            spawn_batch_flush(self.app.clone(), self.batcher.window());
+        }
+    }
+
+    /// Counts the activity `id` as failed when it ends without reaching 100%.
+    fn ended_early(&self, id: &str) {
+        if let Some(content) = self.registry.content(id) {
+            if parse_progress(content.state()) != Some(1.0) {
+                self.finished(&content, false);
+            }
+        }
+    }
+
+    /// Posts the summaries of the groups whose batching window has passed.
+    fn flush_batches(&self) {
+        let _op = self.op();
+        let quiet = self.release_quiet().is_some();
+        for mut alert in self.batcher.take_due() {
+            alert.silent = quiet;
+            if let Err(err) = self.chain.alert(alert) {
+                tracing::warn!(error = %err, "failed to post completion summary");
+            }
+        }
+        if self.batcher.has_pending() {
+            spawn_batch_flush(self.app.clone(), self.batcher.window());
+        }
+    }
+
+    /// Evaluates the quiet-hours policy; once it no longer applies, shows the
+    /// deferred activity and posts the collected completions as one alert.
+    fn release_quiet(&self) -> Option<QuietReason> {
+        let reason = self
+            .quiet
+            .reason(quiet::local_minute(), || self.chain.primary().system_dnd());
+        if reason.is_some() || !self.quiet.has_pending() {
+            return reason;
+        }
+        if let Some(payload) = self.quiet.take_deferred() {
+            match self.chain.create(payload) {
+                Ok(presenter) => self.registry.moved(presenter),
+                Err(err) => tracing::warn!(error = %err, "failed to show deferred activity"),
+            }
+        }
+        let completed = self.quiet.take_completed();
+        if !completed.is_empty() {
+            if let Err(err) = self.chain.alert(quiet::summary(&completed)) {
+                tracing::warn!(error = %err, "failed to post quiet-hours summary");
+            }
+        }
+        None
+    }
+
+    pub fn metrics(&self) -> Result<MetricsSnapshot> {
+        Ok(self.metrics.snapshot())
+    }
+
+    /// Collects what support needs to explain a missing notification.
+    ///
+    /// With `test_activity`, a short activity is shown and ended through the
+    /// normal pipeline, unless another activity is running.
+    pub fn diagnose(&self, test_activity: bool) -> Result<DiagnosticReport> {
+        self.instrument("diagnose", None, || {
+            let app = AppIdentity {
+                name: self.app.package_info().name.clone(),
+                identifier: self.app.config().identifier.clone(),
+            };
+            let primary = self.chain.primary();
+            let mut checks = Vec::new();
+
+            let permission = match primary.permission_state() {
+                Ok(state) => {
+                    let status = match state {
+                        _ if state.allows_presenting() => CheckStatus::Ok,
+                        // 第一次创建时会弹授权框，查询超时也不等于被拒。
+                        PermissionState::NotDetermined | PermissionState::Unknown => {
+                            CheckStatus::Warning
+                        }
+                        _ => CheckStatus::Error,
+                    };
+                    checks.push(DiagnosticCheck::new(
+                        "permission",
+                        status,
+                        format!("{state:?}"),
+                    ));
+                    Some(state)
+                }
+                Err(err) => {
+                    checks.push(DiagnosticCheck::new(
+                        "permission",
+                        CheckStatus::Error,
+                        format!("{err:#}"),
+                    ));
+                    None
+                }
+            };
+            let capabilities = match primary.capabilities() {
+                Ok(caps) if caps.presents => Some(caps),
+                Ok(caps) => {
+                    checks.push(DiagnosticCheck::new(
+                        "backend",
+                        CheckStatus::Warning,
+                        format!("{} does not show anything on this platform", caps.backend),
+                    ));
+                    Some(caps)
+                }
+                Err(err) => {
+                    checks.push(DiagnosticCheck::new(
+                        "capabilities",
+                        CheckStatus::Error,
+                        format!("{err:#}"),
+                    ));
+                    None
+                }
+            };
+            for presenter in self.chain.presenters() {
+                checks.extend(presenter.diagnostics(&app));
+            }
+            if test_activity {
+                checks.push(self.run_test_activity());
+            }
+
+            Ok(DiagnosticReport {
+                generated_at: now_millis(),
+                plugin_version: env!("CARGO_PKG_VERSION").to_string(),
+                os: std::env::consts::OS.to_string(),
+                arch: std::env::consts::ARCH.to_string(),
+                app,
+                presenters: self
+                    .chain
+                    .presenters()
+                    .map(|p| p.name().to_string())
+                    .collect(),
+                permission,
+                capabilities,
+                checks,
+            })
+        })
+    }
+
+    fn run_test_activity(&self) -> DiagnosticCheck {
+        const NAME: &str = "test-activity";
+        const ID: &str = "live-activity-self-test";
+        if self.registry.current_id().is_some() {
+            return DiagnosticCheck::new(NAME, CheckStatus::Skipped, "another activity is running");
+        }
+        let request = CreateLiveActivityRequest {
+            activity_content_v: 1,
+            activity_content: ActivityContent::TaskQueue(ActivityContentTaskQueue {
+                id: ID.to_string(),
+                title: "通知测试".to_string(),
+                text: "这是一条测试通知，稍后会自动关闭".to_string(),
+                task_name: "Live Activity".to_string(),
+                task_type: "诊断".to_string(),
+                task_icon: String::new(),
+                state: HashMap::from([("progress".to_string(), "0.3".to_string())]),
+                priority: ActivityPriority::Normal,
+                group: None,
+                stall_after_ms: None,
+                heartbeat_ms: None,
+                owner: None,
+                start_at: None,
+                end_at: None,
+                inhibit_sleep: false,
+            }),
+        };
+        let shown = (|| {
+            self.create_live_activity(request)?;
+            let presenter = self
+                .registry
+                .snapshot(ID, |_| true)
+                .map(|s| s.backend)
+                .unwrap_or_default();
+            let visible = self.chain.visible_ids()?.iter().any(|id| id == ID);
+            self.update_live_activity(UpdateLiveActivityRequest {
+                state: HashMap::from([("progress".to_string(), "1".to_string())]),
+                id: Some(ID.to_string()),
+            })?;
+            anyhow::Ok((presenter, visible))
+        })();
+        if shown.is_ok() {
+            // 留一点时间让用户在屏幕上看到它；这期间不占着 op，别的活动照常进出。
+            clock::sleep(TEST_ACTIVITY_LINGER);
+        }
+        // 按 id 结束：期间换上来的活动不能被一起撤掉。
+        let result = self.end_activity(ID).and(shown);
+        match result {
+            Ok((presenter, true)) => {
+                DiagnosticCheck::new(NAME, CheckStatus::Ok, format!("shown by {presenter}"))
+            }
+            Ok((presenter, false)) => DiagnosticCheck::new(
+                NAME,
+                CheckStatus::Warning,
+                format!("accepted by {presenter}, but not reported as visible"),
+            ),
+            Err(err) => DiagnosticCheck::new(NAME, CheckStatus::Error, format!("{err:#}")),
+        }
+    }
+
+    /// Replays a session written by the recorder through this plugin's backend.
+    /// The replayed calls are not recorded again.
+    pub fn replay_session(&self, path: &Path, speed: f64) -> Result<ReplaySummary> {
+        let entries = recorder::read_session(path)?;
+        recorder::replay(entries, speed, |call| self.apply(call))
+    }
+
+    /// Runs a create/update/remove call received from outside the app.
+    pub(crate) fn apply(&self, call: RecordedCall) -> Result<()> {
+        match call {
+            RecordedCall::Create(payload) => self.create_live_activity(payload),
+            RecordedCall::Update(payload) => self.update_live_activity(payload),
+            RecordedCall::Remove => self.remove_live_activity(),
+            RecordedCall::End { id } => self.end_activity(&id),
+        }
+    }
+
+    /// Snapshots of every activity the plugin currently tracks, oldest first.
+    pub fn list_activities(&self) -> Result<Vec<ActivitySnapshot>> {
+        self.instrument("list", None, || {
+            let visible = self.chain.visible_ids()?;
+            Ok(self
+                .registry
+                .snapshots(|id| visible.iter().any(|v| v == id)))
+        })
+    }
+
+    pub fn get_activity(&self, id: &str) -> Result<Option<ActivitySnapshot>> {
+        self.instrument("get", Some(id), || {
+            let visible = self.chain.visible_ids()?;
+            Ok(self
+                .registry
+                .snapshot(id, |id| visible.iter().any(|v| v == id)))
+        })
+    }
+
+    fn instrument<T>(
+        &self,
+        op: &'static str,
+        id: Option<&str>,
+        f: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        instrument(&self.metrics, op, id, f)
+    }
+
+    /// Queues `event` for the sinks; they get it once `op` is released.
+    fn emit(&self, event: ActivityEvent) {
+        self.sinks.push(event);
+        // 没在操作里发出的事件没人会替它派发。
+        if self.op.try_lock().is_ok() {
+            self.sinks.dispatch_pending(&self.metrics);
+        }
+    }
+
+    fn op(&self) -> OpGuard<'_, R> {
+        OpGuard {
+            live_activity: self,
+            guard: Some(self.op.lock().unwrap()),
+        }
+    }
+
+    fn recorded(&self, call: RecordedCall, f: impl FnOnce() -> Result<()>) -> Result<()> {
+        match &self.recorder {
+            Some(recorder) => recorder.record(call, f),
+            None => f(),
+        }
+    }
+}
+
+/// Holds `op` for one operation; dispatches the events it emitted once released.
+struct OpGuard<'a, R: Runtime> {
+    live_activity: &'a LiveActivity<R>,
+    guard: Option<MutexGuard<'a, ()>>,
+}
+
+impl<R: Runtime> Drop for OpGuard<'_, R> {
+    fn drop(&mut self) {
+        // 先放锁再派发，sink 里回调插件不会死锁。
+        drop(self.guard.take());
+        let live_activity = self.live_activity;
+        live_activity.sinks.dispatch_pending(&live_activity.metrics);
+    }
+}
+
+fn open_recorder(path: &Path) -> Option<Recorder> {
+    Recorder::open(path)
+        .inspect_err(|err| {
+            tracing::warn!(error = %err, path = %path.display(), "failed to open session recorder")
+        })
+        .ok()
+}
+
+/// Runs `check` for the activity after `wait`, then again after whatever it
+/// returns, until it returns `None`.
+fn spawn_watch<R: Runtime>(
+    app: &AppHandle<R>,
+    id: &str,
+    created_at: u64,
+    wait: Duration,
+    check: fn(&LiveActivity<R>, &str, u64) -> Option<Duration>,
+) {
+    let app = app.clone();
+    let id = id.to_string();
+    std::thread::spawn(move || {
+        let mut wait = wait;
+        loop {
+            clock::sleep(wait);
+            // 插件状态没了说明应用在退出，活动也不会再有了。
+            let Some(live_activity) = app.try_state::<LiveActivity<R>>() else {
+                break;
+            };
+            match check(&live_activity, &id, created_at) {
+                Some(next) => wait = next,
+                None => break,
+            }
+        }
+    });
+}
+
+fn spawn_batch_flush<R: Runtime>(app: AppHandle<R>, window: Duration) {
+    std::thread::spawn(move || {
+        clock::sleep(window);
+        if let Some(live_activity) = app.try_state::<LiveActivity<R>>() {
+            live_activity.flush_batches();
+        }
+    });
+}
+
+/// Pushes held updates as they come due; parked while nothing is held.
+fn spawn_throttle_flush<R: Runtime>(app: AppHandle<R>, throttle: Arc<Throttler>) {
+    std::thread::spawn(move || loop {
+        clock::sleep(throttle.wait_held());
+        let Some(live_activity) = app.try_state::<LiveActivity<R>>() else {
+            break;
+        };
+        live_activity.flush_throttled();
+    });
+}
+
+fn spawn_ticker<R: Runtime>(app: AppHandle<R>, every: Duration, tick: fn(&LiveActivity<R>)) {
+    std::thread::spawn(move || loop {
+        clock::sleep(every);
+        if let Some(live_activity) = app.try_state::<LiveActivity<R>>() {
+            tick(&live_activity);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use tauri::test::{mock_app, MockRuntime};
+
+    use super::*;
+    use crate::mock::MockBackend;
+    use crate::models::test_support::{create, state, task};
+    use crate::power::ManualPower;
+
+    fn request(id: &str, priority: ActivityPriority) -> CreateLiveActivityRequest {
+        create(ActivityContentTaskQueue {
+            priority,
+            ..task(id)
+        })
+    }
+
+    /// The plugin layer over `backend`; the app keeps the handle alive.
+    fn live_activity(
+        backend: impl Backend + 'static,
+    ) -> (tauri::App<MockRuntime>, LiveActivity<MockRuntime>) {
+        live_activity_with(backend, PluginOptions::default())
+    }
+
+    fn live_activity_with(
+        backend: impl Backend + 'static,
+        mut options: PluginOptions,
+    ) -> (tauri::App<MockRuntime>, LiveActivity<MockRuntime>) {
+        // 别让跑测试的机器在用电池时把更新攒起来。
+        options
+            .power
+            .get_or_insert_with(|| Arc::new(ManualPower::new(PowerState::Ac)));
+        let app = mock_app();
+        let live_activity = LiveActivity::new(app.handle(), Box::new(backend), options);
+        (app, live_activity)
+    }
+
+    /// Quiet whenever the system do-not-disturb is on.
+    fn quiet_on_dnd(mode: QuietMode) -> PluginOptions {
+        PluginOptions {
+            quiet_hours: Some(QuietHours {
+                windows: Vec::new(),
+                honor_system_dnd: true,
+                mode,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn calls(backend: &MockBackend) -> Vec<String> {
+        backend
+            .calls()
+            .into_iter()
+            .map(|call| match call {
+                RecordedCall::Create(r) => format!("create {}", r.activity_content.id()),
+                RecordedCall::Update(_) => "update".to_string(),
+                RecordedCall::Remove => "remove".to_string(),
+                RecordedCall::End { id } => format!("end {id}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn preempting_removes_then_restores() {
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity(backend.clone());
+        live_activity
+            .create_live_activity(request("low", ActivityPriority::Low))
+            .unwrap();
+        live_activity
+            .create_live_activity(request("high", ActivityPriority::High))
+            .unwrap();
+        assert_eq!(backend.current_id().as_deref(), Some("high"));
+        assert_eq!(live_activity.registry.current_id().as_deref(), Some("high"));
+        assert!(live_activity.registry.contains("low"));
+
+        live_activity.remove_live_activity().unwrap();
+        assert_eq!(backend.current_id().as_deref(), Some("low"));
+        assert_eq!(live_activity.registry.current_id().as_deref(), Some("low"));
+        assert_eq!(
+            calls(&backend),
+            [
+                "create low",
+                "remove",
+                "create high",
+                "remove",
+                "create low"
+            ]
+        );
+    }
+
+    #[test]
+    fn equal_priority_replaces_the_current_activity() {
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity(backend.clone());
+        live_activity
+            .create_live_activity(request("first", ActivityPriority::Normal))
+            .unwrap();
+        live_activity
+            .create_live_activity(request("second", ActivityPriority::Normal))
+            .unwrap();
+        assert_eq!(backend.current_id().as_deref(), Some("second"));
+        assert!(!live_activity.registry.contains("first"));
+        assert_eq!(calls(&backend), ["create first", "remove", "create second"]);
+    }
+
+    #[test]
+    fn lower_priority_waits_in_the_queue() {
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity(backend.clone());
+        live_activity
+            .create_live_activity(request("high", ActivityPriority::High))
+            .unwrap();
+        live_activity
+            .create_live_activity(request("low", ActivityPriority::Low))
+            .unwrap();
+        assert_eq!(backend.current_id().as_deref(), Some("high"));
+        assert_eq!(calls(&backend), ["create high"]);
+    }
+
+    #[test]
+    fn first_create_asks_for_permission() {
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity(backend.clone());
+        backend.set_permission(PermissionState::NotDetermined);
+        live_activity
+            .create_live_activity(request("a", ActivityPriority::Normal))
+            .unwrap();
+        assert_eq!(backend.current_id().as_deref(), Some("a"));
+        assert_eq!(
+            live_activity.permission_state().unwrap(),
+            PermissionState::Granted
+        );
+    }
+
+    #[test]
+    fn unanswered_permission_query_still_presents() {
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity(backend.clone());
+        backend.set_permission(PermissionState::Unknown);
+        live_activity
+            .create_live_activity(request("a", ActivityPriority::Normal))
+            .unwrap();
+        live_activity.remove_live_activity().unwrap();
+
+        backend.set_permission(PermissionState::Denied);
+        assert!(live_activity
+            .create_live_activity(request("b", ActivityPriority::Normal))
+            .is_err());
+        assert_eq!(backend.current_id(), None);
+    }
+
+    #[test]
+    fn quiet_completion_goes_to_the_summary_only() {
+        let backend = Arc::new(MockBackend::new());
+        backend.set_system_dnd(Some(true));
+        let (_app, live_activity) =
+            live_activity_with(backend.clone(), quiet_on_dnd(QuietMode::Silent));
+        live_activity
+            .create_live_activity(request("a", ActivityPriority::Normal))
+            .unwrap();
+        live_activity
+            .update_live_activity(UpdateLiveActivityRequest {
+                state: state(&[("progress", "1")]),
+                id: None,
+            })
+            .unwrap();
+        let Some(RecordedCall::Update(update)) = backend.calls().pop() else {
+            panic!("expected an update");
+        };
+        assert_eq!(update.state.get("silent").map(String::as_str), Some("true"));
+        assert_eq!(
+            update.state.get("completion").map(String::as_str),
+            Some("hidden")
+        );
+        assert_eq!(live_activity.quiet_status().unwrap().pending_completions, 1);
+    }
+
+    #[test]
+    fn deferred_update_replaces_the_state() {
+        let backend = Arc::new(MockBackend::new());
+        backend.set_system_dnd(Some(true));
+        let (_app, live_activity) =
+            live_activity_with(backend.clone(), quiet_on_dnd(QuietMode::Defer));
+        let mut create = request("a", ActivityPriority::Normal);
+        *create.activity_content.state_mut() = state(&[("hint", "排队中")]);
+        live_activity.create_live_activity(create).unwrap();
+        live_activity
+            .update_live_activity(UpdateLiveActivityRequest {
+                state: state(&[("progress", "0.5")]),
+                id: None,
+            })
+            .unwrap();
+        assert!(backend.calls().is_empty());
+
+        live_activity.set_quiet_hours(None).unwrap();
+        let Some(RecordedCall::Create(shown)) = backend.calls().pop() else {
+            panic!("expected the deferred activity to be shown");
+        };
+        assert_eq!(
+            *shown.activity_content.state(),
+            state(&[("progress", "0.5")])
+        );
+    }
+
+    #[test]
+    fn background_checks_leave_a_replaced_activity_alone() {
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity(backend.clone());
+        let first = create(ActivityContentTaskQueue {
+            stall_after_ms: Some(1),
+            heartbeat_ms: Some(1),
+            ..task("a")
+        });
+        live_activity.create_live_activity(first).unwrap();
+        let (created_at, _) = live_activity.registry.timestamps("a").unwrap();
+        live_activity
+            .create_live_activity(request("b", ActivityPriority::Normal))
+            .unwrap();
+
+        assert_eq!(live_activity.check_stall("a", created_at), None);
+        assert_eq!(live_activity.check_heartbeat("a", created_at), None);
+        assert_eq!(calls(&backend), ["create a", "remove", "create b"]);
+        assert_eq!(backend.current_id().as_deref(), Some("b"));
+    }
+
+    fn stalled_hints(backend: &MockBackend) -> Vec<String> {
+        backend
+            .calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                RecordedCall::Update(update) => update.state.get("hint").cloned(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stalled_activity_shows_the_configured_hint() {
+        let stalling = || {
+            create(ActivityContentTaskQueue {
+                stall_after_ms: Some(0),
+                ..task("a")
+            })
+        };
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity(backend.clone());
+        live_activity.create_live_activity(stalling()).unwrap();
+        let (created_at, _) = live_activity.registry.timestamps("a").unwrap();
+        live_activity.check_stall("a", created_at);
+        assert_eq!(stalled_hints(&backend), ["Waiting…"]);
+
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity_with(
+            backend.clone(),
+            PluginOptions {
+                stalled_hint: Some("等待中…".to_string()),
+                ..Default::default()
+            },
+        );
+        live_activity.create_live_activity(stalling()).unwrap();
+        let (created_at, _) = live_activity.registry.timestamps("a").unwrap();
+        live_activity.check_stall("a", created_at);
+        assert_eq!(stalled_hints(&backend), ["等待中…"]);
+    }
+
+    fn check(report: &DiagnosticReport, name: &str) -> (CheckStatus, String) {
+        let check = report.checks.iter().find(|c| c.name == name).unwrap();
+        (check.status, check.detail.clone())
+    }
+
+    #[test]
+    fn diagnose_reports_permission_and_presenters() {
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity_with(
+            backend.clone(),
+            PluginOptions {
+                fallbacks: vec![Box::new(Refusing(Arc::new(MockBackend::new()), ""))],
+                ..Default::default()
+            },
+        );
+        let report = live_activity.diagnose(false).unwrap();
+        assert_eq!(report.presenters, ["mock", "refusing"]);
+        assert_eq!(report.permission, Some(PermissionState::Granted));
+        assert!(report
+            .capabilities
+            .as_ref()
+            .is_some_and(|caps| caps.presents));
+        assert_eq!(check(&report, "permission").0, CheckStatus::Ok);
+        assert!(report.checks.iter().all(|c| c.name != "test-activity"));
+
+        for (permission, status) in [
+            (PermissionState::NotDetermined, CheckStatus::Warning),
+            (PermissionState::Unknown, CheckStatus::Warning),
+            (PermissionState::Denied, CheckStatus::Error),
+        ] {
+            backend.set_permission(permission);
+            let report = live_activity.diagnose(false).unwrap();
+            assert_eq!(report.permission, Some(permission));
+            assert_eq!(check(&report, "permission").0, status, "{permission:?}");
+        }
+    }
+
+    #[test]
+    fn test_activity_is_skipped_or_reports_the_failure() {
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) =
+            live_activity(Refusing(backend.clone(), "live-activity-self-test"));
+        let report = live_activity.diagnose(true).unwrap();
+        let (status, detail) = check(&report, "test-activity");
+        assert_eq!(status, CheckStatus::Error);
+        assert!(detail.contains("refused"), "{detail}");
+        assert!(live_activity.list_activities().unwrap().is_empty());
+
+        live_activity
+            .create_live_activity(create(task("a")))
+            .unwrap();
+        let report = live_activity.diagnose(true).unwrap();
+        assert_eq!(check(&report, "test-activity").0, CheckStatus::Skipped);
+        assert_eq!(calls(&backend), ["create a"]);
+    }
+
+    #[test]
+    fn test_activity_leaves_an_activity_created_meanwhile_alone() {
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity(backend.clone());
+        let report = std::thread::scope(|scope| {
+            let diagnose = scope.spawn(|| live_activity.diagnose(true).unwrap());
+            // 测试活动停留期间不占着 op，别的活动照样能建。
+            while backend.current_id().as_deref() != Some("live-activity-self-test") {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            live_activity
+                .create_live_activity(create(task("a")))
+                .unwrap();
+            diagnose.join().unwrap()
+        });
+        let (status, detail) = check(&report, "test-activity");
+        assert_eq!(status, CheckStatus::Ok);
+        assert_eq!(detail, "shown by mock");
+        assert_eq!(backend.current_id().as_deref(), Some("a"));
+        assert_eq!(
+            calls(&backend),
+            [
+                "create live-activity-self-test",
+                "update",
+                "remove",
+                "create a"
+            ]
+        );
+    }
+
+    /// Refuses to show the activity with the id in the second field.
+    struct Refusing(Arc<MockBackend>, &'static str);
+
+    impl Backend for Refusing {
+        fn name(&self) -> &'static str {
+            "refusing"
+        }
+
+        fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+            if payload.activity_content.id() == self.1 {
+                anyhow::bail!("refused");
+            }
+            self.0.create(payload)
+        }
+
+        fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+            self.0.update(payload)
+        }
+
+        fn remove(&self) -> Result<()> {
+            self.0.remove()
+        }
+
+        fn visible_ids(&self) -> Result<Vec<String>> {
+            self.0.visible_ids()
+        }
+
+        fn capabilities(&self) -> Result<Capabilities> {
+            self.0.capabilities()
+        }
+
+        fn permission_state(&self) -> Result<PermissionState> {
+            self.0.permission_state()
+        }
+
+        fn request_permission(&self) -> Result<PermissionState> {
+            self.0.request_permission()
+        }
+    }
+
+    /// Drops a guard for every activity it sees created, which calls back into
+    /// the plugin, and keeps the events it got.
+    struct Dropping(AppHandle<MockRuntime>, Arc<Mutex<Vec<String>>>);
+
+    impl crate::ActivitySink for Dropping {
+        fn on_event(&self, event: &ActivityEvent) -> Result<()> {
+            let kind = match event {
+                ActivityEvent::Created { .. } => "created",
+                ActivityEvent::Ended { .. } => "ended",
+                _ => "other",
+            };
+            self.1
+                .lock()
+                .unwrap()
+                .push(format!("{kind} {}", event.id()));
+            if kind == "created" {
+                let live_activity = self.0.state::<LiveActivity<MockRuntime>>();
+                drop(live_activity.guard(event.id()));
+                live_activity.quiet_status()?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn sinks_may_call_back_into_the_plugin() {
+        let backend = Arc::new(MockBackend::new());
+        let app = mock_app();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let options = PluginOptions {
+            sinks: vec![Box::new(Dropping(app.handle().clone(), events.clone()))],
+            power: Some(Arc::new(ManualPower::new(PowerState::Ac))),
+            ..Default::default()
+        };
+        app.manage(LiveActivity::new(
+            app.handle(),
+            Box::new(backend.clone()),
+            options,
+        ));
+        let live_activity = app.state::<LiveActivity<MockRuntime>>();
+        live_activity
+            .create_live_activity(request("a", ActivityPriority::Normal))
+            .unwrap();
+
+        assert_eq!(calls(&backend), ["create a", "remove"]);
+        assert_eq!(*events.lock().unwrap(), ["created a", "ended a"]);
+    }
+
+    #[test]
+    fn latency_is_recorded_per_presenter_used() {
+        let primary = Arc::new(MockBackend::new());
+        let fallback = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity_with(
+            Refusing(primary.clone(), "a"),
+            PluginOptions {
+                fallbacks: vec![Box::new(fallback.clone())],
+                ..Default::default()
+            },
+        );
+        live_activity
+            .create_live_activity(request("a", ActivityPriority::Normal))
+            .unwrap();
+        assert_eq!(fallback.current_id().as_deref(), Some("a"));
+
+        let snapshot = live_activity.metrics().unwrap();
+        let latencies: Vec<_> = snapshot
+            .latencies
+            .iter()
+            .map(|h| (h.backend.as_str(), h.operation.as_str(), h.count))
+            .collect();
+        assert_eq!(
+            latencies,
+            [("mock", "create", 1), ("refusing", "create", 1)]
+        );
+        assert!(snapshot
+            .operations
+            .iter()
+            .any(|op| op.operation == "create" && op.outcome == "ok" && op.count == 1));
+    }
+
+    #[test]
+    fn failed_create_shows_the_previous_activity_again() {
+        let backend = Arc::new(MockBackend::new());
+        let (_app, live_activity) = live_activity(Refusing(backend.clone(), "bad"));
+        live_activity
+            .create_live_activity(request("a", ActivityPriority::Normal))
+            .unwrap();
+        assert!(live_activity
+            .create_live_activity(request("bad", ActivityPriority::Normal))
+            .is_err());
+        assert!(live_activity
+            .create_live_activity(request("bad", ActivityPriority::High))
+            .is_err());
+        assert_eq!(backend.current_id().as_deref(), Some("a"));
+        assert_eq!(live_activity.registry.current_id().as_deref(), Some("a"));
+        assert!(!live_activity.registry.contains("bad"));
+    }
+}
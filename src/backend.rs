@@ -0,0 +1,108 @@
+use anyhow::Result;
+
+use crate::models::*;
+
+/// A platform presenter driven by the shared plugin layer.
+///
+/// Implementations only deal with showing the activity; bookkeeping, tracing,
+/// metrics and recording happen above them so every backend behaves the same
+/// from the app's point of view. `conformance` holds the scenarios each one
+/// has to pass.
+pub trait Backend: Send + Sync {
+    /// Stable identifier reported in snapshots, traces and metrics.
+    fn name(&self) -> &'static str;
+
+    /// Shows a new activity.
+    ///
+    /// While another activity is shown, it either replaces that one or fails
+    /// and leaves it up. Single-slot platforms (Android, iOS) refuse, so the
+    /// plugin removes the current one first.
+    fn create(&self, payload: CreateLiveActivityRequest) -> Result<()>;
+
+    /// Pushes new state to the current activity; fails when there is none.
+    fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()>;
+
+    /// Ends the current activity. Succeeds when there is nothing to end.
+    fn remove(&self) -> Result<()>;
+
+    /// Ids of the activities the OS still shows.
+    fn visible_ids(&self) -> Result<Vec<String>>;
+
+    fn capabilities(&self) -> Result<Capabilities>;
+
+    fn permission_state(&self) -> Result<PermissionState>;
+
+    fn request_permission(&self) -> Result<PermissionState>;
+
+    /// Posts a one-off notification; presenters that cannot should fail so the
+    /// chain moves on.
+    fn alert(&self, _alert: AlertRequest) -> Result<()> {
+        anyhow::bail!("{} cannot post alerts", self.name())
+    }
+
+    /// Progress the current activity shows, clamped to `0.0..=1.0`; `None` when
+    /// nothing is shown or the presenter cannot read it back.
+    fn shown_progress(&self) -> Option<f32> {
+        None
+    }
+
+    /// Whether the OS do-not-disturb mode is on; `None` when it cannot be read.
+    fn system_dnd(&self) -> Option<bool> {
+        None
+    }
+
+    /// Platform checks for the diagnostics report, e.g. app identity registration.
+    fn diagnostics(&self, _app: &AppIdentity) -> Vec<DiagnosticCheck> {
+        Vec::new()
+    }
+}
+
+impl<B: Backend + ?Sized> Backend for std::sync::Arc<B> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+        (**self).create(payload)
+    }
+
+    fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+        (**self).update(payload)
+    }
+
+    fn remove(&self) -> Result<()> {
+        (**self).remove()
+    }
+
+    fn visible_ids(&self) -> Result<Vec<String>> {
+        (**self).visible_ids()
+    }
+
+    fn capabilities(&self) -> Result<Capabilities> {
+        (**self).capabilities()
+    }
+
+    fn permission_state(&self) -> Result<PermissionState> {
+        (**self).permission_state()
+    }
+
+    fn request_permission(&self) -> Result<PermissionState> {
+        (**self).request_permission()
+    }
+
+    fn alert(&self, alert: AlertRequest) -> Result<()> {
+        (**self).alert(alert)
+    }
+
+    fn shown_progress(&self) -> Option<f32> {
+        (**self).shown_progress()
+    }
+
+    fn system_dnd(&self) -> Option<bool> {
+        (**self).system_dnd()
+    }
+
+    fn diagnostics(&self, app: &AppIdentity) -> Vec<DiagnosticCheck> {
+        (**self).diagnostics(app)
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::clock;
+use crate::models::*;
+
+/// How long a group waits for more finished activities before its summary goes out.
+pub(crate) const DEFAULT_WINDOW: Duration = Duration::from_secs(5);
+
+/// Titles listed in a summary body before the rest is only counted.
+const LISTED_TITLES: usize = 5;
+
+struct Batch {
+    succeeded: Vec<String>,
+    failed: Vec<String>,
+    last: SystemTime,
+}
+
+/// Collects finished activities per `group` and turns each group into one
+/// summary once no activity of it finished for a whole window.
+pub(crate) struct CompletionBatcher {
+    window: Duration,
+    groups: Mutex<HashMap<String, Batch>>,
+}
+
+impl CompletionBatcher {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records a finished activity; `true` when nothing was pending before, so
+    /// the caller has to schedule a flush.
+    pub fn finished(&self, group: &str, title: &str, succeeded: bool) -> bool {
+        let mut groups = self.groups.lock().unwrap();
+        let idle = groups.is_empty();
+        let batch = groups.entry(group.to_string()).or_insert_with(|| Batch {
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+            last: clock::now(),
+        });
+        if succeeded {
+            batch.succeeded.push(title.to_string());
+        } else {
+            batch.failed.push(title.to_string());
+        }
+        batch.last = clock::now();
+        idle
+    }
+
+    /// Summaries of the groups whose window has passed.
+    pub fn take_due(&self) -> Vec<AlertRequest> {
+        let mut groups = self.groups.lock().unwrap();
+        let due: Vec<String> = groups
+            .iter()
+            .filter(|(_, b)| clock::now().duration_since(b.last).unwrap_or_default() >= self.window)
+            .map(|(group, _)| group.clone())
+            .collect();
+        due.into_iter()
+            .filter_map(|group| {
+                let batch = groups.remove(&group)?;
+                Some(summary(group, &batch))
+            })
+            .collect()
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.groups.lock().unwrap().is_empty()
+    }
+}
+
+fn summary(group: String, batch: &Batch) -> AlertRequest {
+    let (title, body) = match (batch.succeeded.as_slice(), batch.failed.as_slice()) {
+        // 只有一项时照常显示它自己的标题。
+        ([title], []) => (title.clone(), "传输完成".to_string()),
+        ([], [title]) => (title.clone(), "传输失败".to_string()),
+        (succeeded, []) => (format!("已发送 {} 项", succeeded.len()), list(succeeded)),
+        (succeeded, failed) => (
+            format!("已发送 {} 项，{} 项失败", succeeded.len(), failed.len()),
+            format!("失败：{}", list(failed)),
+        ),
+    };
+    AlertRequest {
+        title,
+        body,
+        silent: false,
+        group: Some(group),
+    }
+}
+
+/// Joins `titles`, counting the ones past [`LISTED_TITLES`] instead of listing them.
+pub(crate) fn list(titles: &[String]) -> String {
+    let listed = titles[..titles.len().min(LISTED_TITLES)].join("、");
+    if titles.len() > LISTED_TITLES {
+        format!("{listed} 等 {} 项", titles.len())
+    } else {
+        listed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn due(batcher: &CompletionBatcher) -> Vec<(String, String, Option<String>)> {
+        let mut alerts: Vec<_> = batcher
+            .take_due()
+            .into_iter()
+            .map(|a| (a.title, a.body, a.group))
+            .collect();
+        alerts.sort_by(|a, b| a.2.cmp(&b.2));
+        alerts
+    }
+
+    #[test]
+    fn only_the_first_finish_schedules_a_flush() {
+        let batcher = CompletionBatcher::new(DEFAULT_WINDOW);
+        assert!(batcher.finished("upload", "a.txt", true));
+        assert!(!batcher.finished("upload", "b.txt", true));
+        assert!(!batcher.finished("download", "c.txt", true));
+    }
+
+    #[test]
+    fn groups_wait_for_their_window() {
+        let batcher = CompletionBatcher::new(Duration::from_secs(3600));
+        batcher.finished("upload", "a.txt", true);
+        assert!(batcher.take_due().is_empty());
+        assert!(batcher.has_pending());
+
+        let batcher = CompletionBatcher::new(Duration::ZERO);
+        batcher.finished("upload", "a.txt", true);
+        assert_eq!(due(&batcher).len(), 1);
+        assert!(!batcher.has_pending());
+        assert!(batcher.take_due().is_empty());
+    }
+
+    #[test]
+    fn each_group_gets_its_own_summary() {
+        let batcher = CompletionBatcher::new(Duration::ZERO);
+        batcher.finished("upload", "a.txt", true);
+        batcher.finished("upload", "b.txt", true);
+        batcher.finished("download", "c.txt", false);
+        assert_eq!(
+            due(&batcher),
+            [
+                (
+                    "c.txt".to_string(),
+                    "传输失败".to_string(),
+                    Some("download".to_string())
+                ),
+                (
+                    "已发送 2 项".to_string(),
+                    "a.txt、b.txt".to_string(),
+                    Some("upload".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_text() {
+        let batcher = CompletionBatcher::new(Duration::ZERO);
+        batcher.finished("g", "a.txt", true);
+        assert_eq!(due(&batcher)[0].0, "a.txt");
+
+        batcher.finished("g", "a.txt", true);
+        batcher.finished("g", "b.txt", false);
+        let (title, body, _) = due(&batcher).remove(0);
+        assert_eq!(title, "已发送 1 项，1 项失败");
+        assert_eq!(body, "失败：b.txt");
+
+        for i in 0..7 {
+            batcher.finished("g", &format!("{i}.txt"), true);
+        }
+        let (title, body, _) = due(&batcher).remove(0);
+        assert_eq!(title, "已发送 7 项");
+        assert_eq!(body, "0.txt、1.txt、2.txt、3.txt、4.txt 等 7 项");
+    }
+}
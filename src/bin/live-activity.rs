@@ -0,0 +1,288 @@
+//! Posts live activities to a running app through its IPC endpoint.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use live_activity::ipc::{self, Client};
+use live_activity::{
+    ActivityContent, ActivityContentTaskQueue, ActivityPriority, CreateLiveActivityRequest,
+    RecordedCall, UpdateLiveActivityRequest,
+};
+
+const USAGE: &str = "\
+usage: live-activity [--endpoint PATH] [--token TOKEN] <command>
+
+commands:
+  create --id ID --title TITLE [--text TEXT] [--task-name NAME]
+         [--task-type TYPE] [--icon PATH]
+         [--priority low|normal|high|critical] [--group GROUP]
+         [--stall-after SECONDS] [--start-in SECONDS] [--end-in SECONDS]
+         [--inhibit-sleep yes|no]
+         [KEY=VALUE...]
+  update [--id ID] KEY=VALUE...
+  end [--id ID]
+
+The endpoint defaults to $LIVE_ACTIVITY_ENDPOINT, then the plugin default.
+The token defaults to $LIVE_ACTIVITY_TOKEN, then the token file the app
+writes next to the endpoint.";
+
+struct Args {
+    endpoint: PathBuf,
+    token: Option<String>,
+    command: String,
+    flags: HashMap<String, String>,
+    state: HashMap<String, String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut endpoint = std::env::var_os("LIVE_ACTIVITY_ENDPOINT").map(PathBuf::from);
+    let mut token = std::env::var("LIVE_ACTIVITY_TOKEN").ok();
+    let mut command = None;
+    let mut flags = HashMap::new();
+    let mut state = HashMap::new();
+
+    while let Some(arg) = args.next() {
+        if let Some(name) = arg.strip_prefix("--") {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for --{name}"))?;
+            match name {
+                "endpoint" => endpoint = Some(PathBuf::from(value)),
+                "token" => token = Some(value),
+                _ => {
+                    flags.insert(name.to_string(), value);
+                }
+            }
+        } else if command.is_none() {
+            command = Some(arg);
+        } else if let Some((key, value)) = arg.split_once('=') {
+            state.insert(key.to_string(), value.to_string());
+        } else {
+            return Err(format!("unexpected argument `{arg}`"));
+        }
+    }
+
+    Ok(Args {
+        endpoint: endpoint.unwrap_or_else(ipc::default_endpoint),
+        token,
+        command: command.ok_or("missing command")?,
+        flags,
+        state,
+    })
+}
+
+fn create_request(args: &mut Args) -> Result<CreateLiveActivityRequest, String> {
+    let mut take = |name: &str| args.flags.remove(name);
+    let id = take("id").ok_or("create needs --id")?;
+    let title = take("title").ok_or("create needs --title")?;
+    let priority = match take("priority").as_deref() {
+        None | Some("normal") => ActivityPriority::Normal,
+        Some("low") => ActivityPriority::Low,
+        Some("high") => ActivityPriority::High,
+        Some("critical") => ActivityPriority::Critical,
+        Some(other) => return Err(format!("unknown priority `{other}`")),
+    };
+    let content = ActivityContentTaskQueue {
+        id,
+        title,
+        text: take("text").unwrap_or_default(),
+        task_name: take("task-name").unwrap_or_default(),
+        task_type: take("task-type").unwrap_or_default(),
+        task_icon: take("icon").unwrap_or_default(),
+        state: std::mem::take(&mut args.state),
+        priority,
+        group: take("group"),
+        stall_after_ms: match take("stall-after") {
+            Some(secs) => Some(parse_secs(&secs)?),
+            None => None,
+        },
+        heartbeat_ms: None,
+        owner: None,
+        start_at: match take("start-in") {
+            Some(secs) => Some(from_now(parse_secs(&secs)?)),
+            None => None,
+        },
+        end_at: match take("end-in") {
+            Some(secs) => Some(from_now(parse_secs(&secs)?)),
+            None => None,
+        },
+        inhibit_sleep: match take("inhibit-sleep").as_deref() {
+            None | Some("no") => false,
+            Some("yes") => true,
+            Some(other) => return Err(format!("--inhibit-sleep takes yes or no, not `{other}`")),
+        },
+    };
+    Ok(CreateLiveActivityRequest {
+        activity_content_v: 1,
+        activity_content: ActivityContent::TaskQueue(content),
+    })
+}
+
+fn parse_secs(value: &str) -> Result<u64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .map(|secs| (secs * 1000.0) as u64)
+        .ok_or_else(|| format!("invalid duration `{value}`"))
+}
+
+/// Milliseconds since the Unix epoch, `ms` from now.
+fn from_now(ms: u64) -> u64 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_millis() as u64 + ms
+}
+
+/// The call the command line asks for; rejects options the command does not take.
+fn call(args: &mut Args) -> Result<RecordedCall, String> {
+    let call = match args.command.as_str() {
+        "create" => RecordedCall::Create(create_request(args)?),
+        "update" => {
+            if args.state.is_empty() {
+                return Err("update needs at least one KEY=VALUE".to_string());
+            }
+            RecordedCall::Update(UpdateLiveActivityRequest {
+                state: std::mem::take(&mut args.state),
+                id: args.flags.remove("id"),
+            })
+        }
+        "end" => match args.flags.remove("id") {
+            Some(id) => RecordedCall::End { id },
+            None => RecordedCall::Remove,
+        },
+        other => return Err(format!("unknown command `{other}`")),
+    };
+    if let Some(flag) = args.flags.keys().next() {
+        return Err(format!("unknown option --{flag}"));
+    }
+    Ok(call)
+}
+
+fn run(mut args: Args) -> Result<(), String> {
+    // 先把参数校验完再去连接，用法错误不该表现成连接错误。
+    let call = call(&mut args)?;
+    let client = match args.token.take() {
+        Some(token) => Client::connect(&args.endpoint, token),
+        None => Client::connect_with_token_file(&args.endpoint),
+    };
+    client
+        .and_then(|mut client| client.send(call))
+        .map_err(|err| format!("{err:#}"))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let args = match parse_args(args.into_iter()) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("live-activity: {err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("live-activity: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        let mut full = vec!["--endpoint", "/tmp/test.sock", "--token", "t"];
+        full.extend_from_slice(args);
+        parse_args(full.into_iter().map(String::from))
+    }
+
+    fn call_of(args: &[&str]) -> Result<RecordedCall, String> {
+        call(&mut parse(args)?)
+    }
+
+    #[test]
+    fn parses_global_options_and_state() {
+        let args = parse(&["update", "--id", "a", "progress=0.5", "hint=a=b"]).unwrap();
+        assert_eq!(args.endpoint, PathBuf::from("/tmp/test.sock"));
+        assert_eq!(args.token.as_deref(), Some("t"));
+        assert_eq!(args.command, "update");
+        assert_eq!(args.flags["id"], "a");
+        assert_eq!(args.state["progress"], "0.5");
+        // 只按第一个等号切。
+        assert_eq!(args.state["hint"], "a=b");
+
+        assert_eq!(parse(&[]).err().as_deref(), Some("missing command"));
+        assert!(parse(&["end", "--id"]).is_err());
+        assert!(parse(&["update", "stray"]).is_err());
+    }
+
+    #[test]
+    fn builds_create_requests() {
+        let call = call_of(&[
+            "create",
+            "--id",
+            "job",
+            "--title",
+            "Upload",
+            "--priority",
+            "high",
+            "--stall-after",
+            "1.5",
+            "--inhibit-sleep",
+            "yes",
+            "progress=0",
+        ])
+        .unwrap();
+        let RecordedCall::Create(request) = call else {
+            panic!("expected a create, got {call:?}");
+        };
+        let ActivityContent::TaskQueue(task) = request.activity_content;
+        assert_eq!(task.id, "job");
+        assert_eq!(task.title, "Upload");
+        assert_eq!(task.priority, ActivityPriority::High);
+        assert_eq!(task.stall_after_ms, Some(1500));
+        assert!(task.inhibit_sleep);
+        assert_eq!(task.state["progress"], "0");
+
+        assert!(call_of(&["create", "--title", "x"]).is_err());
+        assert!(call_of(&[
+            "create",
+            "--id",
+            "a",
+            "--title",
+            "x",
+            "--priority",
+            "urgent"
+        ])
+        .is_err());
+        assert!(call_of(&["create", "--id", "a", "--title", "x", "--stall-after", "-1"]).is_err());
+    }
+
+    #[test]
+    fn builds_update_and_end_calls() {
+        assert!(matches!(
+            call_of(&["update", "--id", "a", "progress=1"]),
+            Ok(RecordedCall::Update(UpdateLiveActivityRequest { id: Some(id), .. })) if id == "a"
+        ));
+        assert!(call_of(&["update"]).is_err());
+        assert!(matches!(call_of(&["end"]), Ok(RecordedCall::Remove)));
+        assert!(matches!(
+            call_of(&["end", "--id", "a"]),
+            Ok(RecordedCall::End { id }) if id == "a"
+        ));
+        assert_eq!(
+            call_of(&["end", "--colour", "red"]).err().as_deref(),
+            Some("unknown option --colour")
+        );
+        assert!(call_of(&["launch"]).is_err());
+    }
+}
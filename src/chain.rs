@@ -0,0 +1,428 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::backend::Backend;
+use crate::metrics::Metrics;
+use crate::models::*;
+use crate::retry::retry;
+use crate::Error;
+
+/// The platform backend followed by the configured fallback presenters.
+///
+/// A create goes to the first presenter that is allowed to present and accepts
+/// it; one that shows nothing on this platform is passed over when another
+/// follows. When an update fails, the activity is recreated on the next presenter
+/// with its latest content, so the user keeps seeing progress somewhere.
+/// Transient failures are retried on the same presenter before falling back.
+/// Each attempt is timed under the presenter that made it.
+pub(crate) struct PresenterChain {
+    presenters: Vec<Box<dyn Backend>>,
+    retry: RetryPolicies,
+    metrics: Arc<Metrics>,
+    /// Index of the presenter holding the current activity.
+    active: Mutex<Option<usize>>,
+}
+
+impl PresenterChain {
+    pub fn new(
+        primary: Box<dyn Backend>,
+        fallbacks: Vec<Box<dyn Backend>>,
+        retry: RetryPolicies,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        let mut presenters = vec![primary];
+        presenters.extend(fallbacks);
+        Self {
+            presenters,
+            retry,
+            metrics,
+            active: Mutex::new(None),
+        }
+    }
+
+    pub fn primary(&self) -> &dyn Backend {
+        self.presenters[0].as_ref()
+    }
+
+    pub fn presenters(&self) -> impl Iterator<Item = &dyn Backend> {
+        self.presenters.iter().map(|p| p.as_ref())
+    }
+
+    /// Presenter holding the current activity, the primary one when there is none.
+    pub fn active(&self) -> &dyn Backend {
+        let active = self.active.lock().unwrap().unwrap_or(0);
+        self.presenters[active].as_ref()
+    }
+
+    /// Asks for the permission of every presenter that has not asked yet.
+    ///
+    /// The system prompt can take a while to answer, so callers run this
+    /// before taking the operation lock; presenting itself only checks the
+    /// answer.
+    pub fn ask_permissions(&self) {
+        for presenter in &self.presenters {
+            if !matches!(
+                presenter.permission_state(),
+                Ok(PermissionState::NotDetermined)
+            ) {
+                continue;
+            }
+            if let Err(err) = presenter.request_permission() {
+                tracing::warn!(presenter = presenter.name(), error = %err, "permission request failed");
+            }
+        }
+    }
+
+    /// Whether a presenter currently holds an activity.
+    pub fn is_presenting(&self) -> bool {
+        self.active.lock().unwrap().is_some()
+    }
+
+    /// Shows the activity and returns the name of the presenter that took it.
+    pub fn create(&self, payload: CreateLiveActivityRequest) -> Result<&'static str> {
+        let previous = *self.active.lock().unwrap();
+        let index = self.present_from(0, payload)?;
+        if previous.is_some_and(|prev| prev != index) {
+            self.retire(previous);
+        }
+        *self.active.lock().unwrap() = Some(index);
+        Ok(self.presenters[index].name())
+    }
+
+    /// Pushes the update, falling back when the active presenter rejects it.
+    ///
+    /// `content` is the activity with the new state applied; it is only used to
+    /// recreate the activity on the next presenter. Returns the new presenter's
+    /// name when the activity moved.
+    pub fn update(
+        &self,
+        payload: UpdateLiveActivityRequest,
+        content: Option<ActivityContent>,
+    ) -> Result<Option<&'static str>> {
+        let Some(index) = *self.active.lock().unwrap() else {
+            // 没有活动时交给主后端，由它给出统一的错误。
+            self.timed("update", self.primary(), || self.primary().update(payload))?;
+            return Ok(None);
+        };
+        let presenter = &self.presenters[index];
+        let result = retry(&self.retry.update, "update", presenter.name(), || {
+            self.timed("update", presenter.as_ref(), || {
+                presenter.update(payload.clone())
+            })
+        });
+        let err = match result {
+            Ok(()) => return Ok(None),
+            Err(err) => err,
+        };
+        let Some(content) = content else {
+            return Err(err);
+        };
+        tracing::warn!(presenter = self.presenters[index].name(), error = %err, "update failed, falling back");
+        let request = CreateLiveActivityRequest {
+            activity_content_v: 1,
+            activity_content: content,
+        };
+        let next = self.present_from(index + 1, request).map_err(|_| err)?;
+        self.retire(Some(index));
+        *self.active.lock().unwrap() = Some(next);
+        Ok(Some(self.presenters[next].name()))
+    }
+
+    pub fn remove(&self) -> Result<()> {
+        let active = *self.active.lock().unwrap();
+        let presenter = &self.presenters[active.unwrap_or(0)];
+        retry(&self.retry.remove, "remove", presenter.name(), || {
+            self.timed("remove", presenter.as_ref(), || presenter.remove())
+        })?;
+        *self.active.lock().unwrap() = None;
+        Ok(())
+    }
+
+    /// Posts the alert through the first presenter that can.
+    pub fn alert(&self, alert: AlertRequest) -> Result<()> {
+        let mut last_err = None;
+        for presenter in &self.presenters {
+            let result = check_permission(presenter.as_ref()).and_then(|()| {
+                retry(&self.retry.alert, "alert", presenter.name(), || {
+                    self.timed("alert", presenter.as_ref(), || {
+                        presenter.alert(alert.clone())
+                    })
+                })
+            });
+            match result {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    tracing::debug!(presenter = presenter.name(), error = %err, "alert not posted");
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| anyhow::anyhow!("no presenter can post alerts")))
+    }
+
+    /// Ids shown by any presenter of the chain.
+    pub fn visible_ids(&self) -> Result<Vec<String>> {
+        let mut ids = Vec::new();
+        for presenter in &self.presenters {
+            ids.extend(presenter.visible_ids()?);
+        }
+        Ok(ids)
+    }
+
+    fn present_from(&self, start: usize, payload: CreateLiveActivityRequest) -> Result<usize> {
+        let mut last_err = None;
+        for (index, presenter) in self.presenters.iter().enumerate().skip(start) {
+            // 只是占位、实际不显示任何东西的后端（如 Linux）让给后面的呈现方。
+            if index + 1 < self.presenters.len() && !shows_anything(presenter.as_ref()) {
+                continue;
+            }
+            let result = retry(&self.retry.create, "create", presenter.name(), || {
+                check_permission(presenter.as_ref())?;
+                self.timed("create", presenter.as_ref(), || {
+                    presenter.create(payload.clone())
+                })
+            });
+            match result {
+                Ok(()) => return Ok(index),
+                Err(err) => {
+                    tracing::warn!(presenter = presenter.name(), error = %err, "presenter failed");
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| anyhow::anyhow!("no presenter left to fall back to")))
+    }
+
+    /// Ends whatever a presenter that lost the activity still shows.
+    fn retire(&self, index: Option<usize>) {
+        let Some(presenter) = index.map(|i| &self.presenters[i]) else {
+            return;
+        };
+        if let Err(err) = self.timed("remove", presenter.as_ref(), || presenter.remove()) {
+            tracing::debug!(presenter = presenter.name(), error = %err, "failed to clear previous presenter");
+        }
+    }
+
+    /// Runs one call on `presenter` and records its latency under that presenter.
+    fn timed<T>(
+        &self,
+        op: &'static str,
+        presenter: &dyn Backend,
+        f: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let started = Instant::now();
+        let result = f();
+        self.metrics
+            .observe(op, presenter.name(), started.elapsed());
+        result
+    }
+}
+
+/// Whether the presenter may present; one that was never asked may not, see
+/// [`PresenterChain::ask_permissions`].
+fn check_permission(presenter: &dyn Backend) -> Result<()> {
+    match presenter.permission_state()? {
+        // 系统没及时答复时照样去展示，真被拒了由系统报错。
+        PermissionState::Unknown => Ok(()),
+        state if state.allows_presenting() => Ok(()),
+        _ => Err(Error::PermissionDenied.into()),
+    }
+}
+
+fn shows_anything(presenter: &dyn Backend) -> bool {
+    presenter.capabilities().map_or(true, |caps| caps.presents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockBackend;
+    use crate::models::test_support::*;
+
+    /// A mock under another name that can be told to show nothing, to refuse
+    /// one activity or to reject every update.
+    struct Presenter {
+        name: &'static str,
+        mock: Arc<MockBackend>,
+        presents: bool,
+        refuses: Option<&'static str>,
+        rejects_updates: bool,
+    }
+
+    impl Presenter {
+        fn new(name: &'static str) -> (Arc<MockBackend>, Self) {
+            let mock = Arc::new(MockBackend::new());
+            let presenter = Self {
+                name,
+                mock: mock.clone(),
+                presents: true,
+                refuses: None,
+                rejects_updates: false,
+            };
+            (mock, presenter)
+        }
+    }
+
+    impl Backend for Presenter {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+            if self.refuses == Some(payload.activity_content.id()) {
+                anyhow::bail!("refused");
+            }
+            self.mock.create(payload)
+        }
+
+        fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+            if self.rejects_updates {
+                anyhow::bail!("rejected");
+            }
+            self.mock.update(payload)
+        }
+
+        fn remove(&self) -> Result<()> {
+            self.mock.remove()
+        }
+
+        fn visible_ids(&self) -> Result<Vec<String>> {
+            self.mock.visible_ids()
+        }
+
+        fn capabilities(&self) -> Result<Capabilities> {
+            Ok(Capabilities {
+                presents: self.presents,
+                ..self.mock.capabilities()?
+            })
+        }
+
+        fn permission_state(&self) -> Result<PermissionState> {
+            self.mock.permission_state()
+        }
+
+        fn request_permission(&self) -> Result<PermissionState> {
+            self.mock.request_permission()
+        }
+    }
+
+    fn chain(primary: Presenter, fallback: Presenter) -> PresenterChain {
+        PresenterChain::new(
+            Box::new(primary),
+            vec![Box::new(fallback)],
+            RetryPolicies::default(),
+            Arc::new(Metrics::default()),
+        )
+    }
+
+    fn calls(mock: &MockBackend) -> Vec<String> {
+        mock.calls()
+            .into_iter()
+            .map(|call| match call {
+                RecordedCall::Create(r) => format!("create {}", r.activity_content.id()),
+                RecordedCall::Update(_) => "update".to_string(),
+                RecordedCall::Remove => "remove".to_string(),
+                RecordedCall::End { id } => format!("end {id}"),
+            })
+            .collect()
+    }
+
+    fn update(pairs: &[(&str, &str)]) -> UpdateLiveActivityRequest {
+        UpdateLiveActivityRequest {
+            state: state(pairs),
+            id: None,
+        }
+    }
+
+    #[test]
+    fn rejected_update_recreates_the_activity_on_the_next_presenter() {
+        let (first, mut primary) = Presenter::new("first");
+        primary.rejects_updates = true;
+        let (second, fallback) = Presenter::new("second");
+        let chain = chain(primary, fallback);
+        assert_eq!(chain.create(create(task("a"))).unwrap(), "first");
+
+        // 没有最新内容就没法在下一个呈现方重建，只能报错。
+        assert!(chain.update(update(&[("progress", "0.2")]), None).is_err());
+        assert_eq!(chain.active().name(), "first");
+
+        let latest = content(ActivityContentTaskQueue {
+            state: state(&[("progress", "0.5")]),
+            ..task("a")
+        });
+        let moved = chain.update(update(&[("progress", "0.5")]), Some(latest));
+        assert_eq!(moved.unwrap(), Some("second"));
+        assert_eq!(chain.active().name(), "second");
+        assert_eq!(calls(&first), ["create a", "remove"]);
+        assert_eq!(calls(&second), ["create a"]);
+        assert_eq!(second.progress(), Some(0.5));
+
+        assert_eq!(
+            chain.update(update(&[("progress", "0.6")]), None).unwrap(),
+            None
+        );
+        assert_eq!(calls(&second), ["create a", "update"]);
+    }
+
+    #[test]
+    fn moving_to_another_presenter_retires_the_previous_one() {
+        let (first, mut primary) = Presenter::new("first");
+        primary.refuses = Some("b");
+        let (second, fallback) = Presenter::new("second");
+        let chain = chain(primary, fallback);
+        chain.create(create(task("a"))).unwrap();
+
+        assert_eq!(chain.create(create(task("b"))).unwrap(), "second");
+        assert_eq!(calls(&first), ["create a", "remove"]);
+        assert_eq!(first.current_id(), None);
+        assert_eq!(second.current_id().as_deref(), Some("b"));
+
+        chain.remove().unwrap();
+        assert_eq!(calls(&second), ["create b", "remove"]);
+        assert!(!chain.is_presenting());
+    }
+
+    #[test]
+    fn presenters_that_show_nothing_are_passed_over_unless_last() {
+        let (first, mut primary) = Presenter::new("first");
+        primary.presents = false;
+        let (second, fallback) = Presenter::new("second");
+        let chain = chain(primary, fallback);
+        assert_eq!(chain.create(create(task("a"))).unwrap(), "second");
+        assert!(calls(&first).is_empty());
+        assert_eq!(calls(&second), ["create a"]);
+
+        // 最后一个呈现方即使不显示也照样接下，免得活动无处可去。
+        let (_, mut primary) = Presenter::new("first");
+        primary.refuses = Some("a");
+        let (last, mut fallback) = Presenter::new("second");
+        fallback.presents = false;
+        let chain = self::chain(primary, fallback);
+        assert_eq!(chain.create(create(task("a"))).unwrap(), "second");
+        assert_eq!(calls(&last), ["create a"]);
+    }
+
+    #[test]
+    fn presenters_without_permission_are_skipped_until_asked() {
+        let (first, primary) = Presenter::new("first");
+        first.set_permission(PermissionState::NotDetermined);
+        let (second, fallback) = Presenter::new("second");
+        let chain = chain(primary, fallback);
+        assert_eq!(chain.create(create(task("a"))).unwrap(), "second");
+        assert!(calls(&first).is_empty());
+        chain.remove().unwrap();
+
+        // 问过之后 mock 视作用户同意，主后端重新接手。
+        chain.ask_permissions();
+        assert_eq!(first.permission_state().unwrap(), PermissionState::Granted);
+        assert_eq!(chain.create(create(task("b"))).unwrap(), "first");
+
+        chain.remove().unwrap();
+        first.set_permission(PermissionState::Denied);
+        chain.ask_permissions();
+        assert_eq!(chain.create(create(task("c"))).unwrap(), "second");
+        assert_eq!(calls(&second), ["create a", "remove", "create c"]);
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Source of time for everything the plugin schedules or waits for.
+///
+/// The plugin and the platform backends read the time and sleep only through
+/// the clock installed with `Builder::clock`, so tests can swap in a
+/// [`ManualClock`] and move time forward themselves.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    /// Blocks the calling thread until `duration` has passed on this clock.
+    fn sleep(&self, duration: Duration);
+}
+
+/// The wall clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// A clock that only moves when told to.
+///
+/// Threads sleeping on it wake up once [`ManualClock::advance`] moved it past
+/// their deadline.
+pub struct ManualClock {
+    now: Mutex<SystemTime>,
+    moved: Condvar,
+}
+
+impl ManualClock {
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(start),
+            moved: Condvar::new(),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+        self.moved.notify_all();
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(UNIX_EPOCH)
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        let until = *now + duration;
+        while *now < until {
+            now = self.moved.wait(now).unwrap();
+        }
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration)
+    }
+}
+
+static CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+/// Installs the process-wide clock.
+pub(crate) fn install(clock: Arc<dyn Clock>) {
+    *CLOCK.write().unwrap() = Some(clock);
+}
+
+fn current() -> Arc<dyn Clock> {
+    CLOCK
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| Arc::new(SystemClock))
+}
+
+pub(crate) fn now() -> SystemTime {
+    current().now()
+}
+
+/// Milliseconds since the Unix epoch.
+pub(crate) fn now_millis() -> u64 {
+    now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+pub(crate) fn sleep(duration: Duration) {
+    current().sleep(duration)
+}
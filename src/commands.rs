@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use tauri::{command, ipc::Channel, AppHandle, Runtime};
+
+use crate::models::*;
+use crate::LiveActivityExt;
+use crate::Result;
+
+#[command]
+pub(crate) async fn list_activities<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<Vec<ActivitySnapshot>> {
+    Ok(app.live_activity().list_activities()?)
+}
+
+#[command]
+pub(crate) async fn get_activity<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+) -> Result<Option<ActivitySnapshot>> {
+    Ok(app.live_activity().get_activity(&id)?)
+}
+
+#[command]
+pub(crate) async fn capabilities<R: Runtime>(app: AppHandle<R>) -> Result<Capabilities> {
+    Ok(app.live_activity().capabilities()?)
+}
+
+#[command]
+pub(crate) async fn permission_state<R: Runtime>(app: AppHandle<R>) -> Result<PermissionState> {
+    Ok(app.live_activity().permission_state()?)
+}
+
+#[command]
+pub(crate) async fn request_permission<R: Runtime>(app: AppHandle<R>) -> Result<PermissionState> {
+    // macOS 会一直等到用户点了系统弹窗，最长一分钟。
+    let state =
+        tauri::async_runtime::spawn_blocking(move || app.live_activity().request_permission())
+            .await
+            .map_err(anyhow::Error::from)??;
+    Ok(state)
+}
+
+#[command]
+pub(crate) async fn metrics<R: Runtime>(app: AppHandle<R>) -> Result<MetricsSnapshot> {
+    Ok(app.live_activity().metrics()?)
+}
+
+#[command]
+pub(crate) async fn diagnose<R: Runtime>(
+    app: AppHandle<R>,
+    test_activity: Option<bool>,
+) -> Result<DiagnosticReport> {
+    // 测试活动会停留几秒，同样放到阻塞线程池。
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        app.live_activity().diagnose(test_activity.unwrap_or(false))
+    })
+    .await
+    .map_err(anyhow::Error::from)??;
+    Ok(report)
+}
+
+#[command]
+pub(crate) async fn quiet_status<R: Runtime>(app: AppHandle<R>) -> Result<QuietStatus> {
+    Ok(app.live_activity().quiet_status()?)
+}
+
+#[command]
+pub(crate) async fn set_quiet_hours<R: Runtime>(
+    app: AppHandle<R>,
+    policy: Option<QuietHours>,
+) -> Result<()> {
+    Ok(app.live_activity().set_quiet_hours(policy)?)
+}
+
+#[command]
+pub(crate) async fn get_preferences<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<BTreeMap<String, TaskPreference>> {
+    Ok(app.live_activity().preferences())
+}
+
+#[command]
+pub(crate) async fn set_preference<R: Runtime>(
+    app: AppHandle<R>,
+    task_type: String,
+    preference: TaskPreference,
+) -> Result<()> {
+    Ok(app.live_activity().set_preference(&task_type, preference)?)
+}
+
+#[command]
+pub(crate) async fn reset_preference<R: Runtime>(
+    app: AppHandle<R>,
+    task_type: String,
+) -> Result<()> {
+    Ok(app.live_activity().reset_preference(&task_type)?)
+}
+
+#[command]
+pub(crate) async fn heartbeat<R: Runtime>(app: AppHandle<R>, id: String) -> Result<()> {
+    Ok(app.live_activity().heartbeat(&id)?)
+}
+
+#[command]
+pub(crate) async fn instance_status<R: Runtime>(
+    app: AppHandle<R>,
+) -> Result<Option<InstanceStatus>> {
+    Ok(app.live_activity().instance_status())
+}
+
+#[command]
+pub(crate) async fn hand_over<R: Runtime>(app: AppHandle<R>) -> Result<()> {
+    // 交接要写文件、可能还要连 IPC，别占着异步线程。
+    tauri::async_runtime::spawn_blocking(move || app.live_activity().hand_over())
+        .await
+        .map_err(anyhow::Error::from)??;
+    Ok(())
+}
+
+#[command]
+pub(crate) async fn history<R: Runtime>(
+    app: AppHandle<R>,
+    query: Option<HistoryQuery>,
+) -> Result<Vec<HistoryEntry>> {
+    Ok(app.live_activity().history(&query.unwrap_or_default())?)
+}
+
+#[command]
+pub(crate) async fn history_stats<R: Runtime>(
+    app: AppHandle<R>,
+    query: Option<HistoryQuery>,
+) -> Result<HistoryStats> {
+    Ok(app
+        .live_activity()
+        .history_stats(&query.unwrap_or_default())?)
+}
+
+#[command]
+pub(crate) async fn clear_history<R: Runtime>(app: AppHandle<R>) -> Result<()> {
+    Ok(app.live_activity().clear_history()?)
+}
+
+#[command]
+pub(crate) async fn replay_session<R: Runtime>(
+    app: AppHandle<R>,
+    path: PathBuf,
+    speed: f64,
+) -> Result<ReplaySummary> {
+    // 回放按录制时的间隔 sleep，放到阻塞线程池里跑。
+    let summary = tauri::async_runtime::spawn_blocking(move || {
+        app.live_activity().replay_session(&path, speed)
+    })
+    .await
+    .map_err(anyhow::Error::from)??;
+    Ok(summary)
+}
+
+#[command]
+pub(crate) async fn watch_in_app<R: Runtime>(
+    app: AppHandle<R>,
+    channel: Channel<InAppView>,
+) -> Result<()> {
+    Ok(app.live_activity().watch_in_app(channel)?)
+}
@@ -0,0 +1,199 @@
+//! Scenarios every [`Backend`] has to pass, whatever it presents.
+//!
+//! Each scenario first clears whatever the backend is showing, so the same
+//! instance can run them back to back; the platform backends keep their state
+//! in statics and cannot be recreated.
+//!
+//! Built for the crate's own tests and, with the `conformance` feature, for
+//! out-of-tree presenters:
+//!
+//! ```ignore
+//! #[test]
+//! fn my_backend_conforms() {
+//!     live_activity::conformance::run_all(&MyBackend::default());
+//! }
+//! ```
+//!
+//! A scenario panics on the first assertion that fails.
+
+use crate::backend::Backend;
+use crate::models::test_support::{create, state as to_state, task};
+use crate::models::*;
+
+fn create_request(id: &str, state: &[(&str, &str)]) -> CreateLiveActivityRequest {
+    create(ActivityContentTaskQueue {
+        title: "任务执行中".to_string(),
+        text: "正在推送资源".to_string(),
+        task_name: "HyperBox".to_string(),
+        task_type: "快应用".to_string(),
+        state: to_state(state),
+        ..task(id)
+    })
+}
+
+fn update_request(state: &[(&str, &str)]) -> UpdateLiveActivityRequest {
+    UpdateLiveActivityRequest {
+        state: to_state(state),
+        id: None,
+    }
+}
+
+fn assert_visible(backend: &dyn Backend, expected: &[&str]) {
+    let caps = backend.capabilities().unwrap();
+    if !caps.presents {
+        // 不展示任何东西的后端只需要保证不报告可见活动。
+        assert!(backend.visible_ids().unwrap().is_empty());
+        return;
+    }
+    assert_eq!(backend.visible_ids().unwrap(), expected);
+}
+
+fn assert_progress(backend: &dyn Backend, expected: f32) {
+    // 读不回进度的后端只检查到可见为止。
+    if let Some(shown) = backend.shown_progress() {
+        assert!(
+            (shown - expected).abs() < 1e-6,
+            "shows progress {shown}, expected {expected}"
+        );
+    }
+}
+
+/// Creates an activity, pushes progress a few times and ends it.
+pub fn create_update_end(backend: &dyn Backend) {
+    backend.remove().unwrap();
+    backend
+        .create(create_request("a", &[("progress", "0")]))
+        .unwrap();
+    assert_visible(backend, &["a"]);
+    for step in 1..=5 {
+        let progress = format!("{:.2}", step as f32 * 0.15);
+        backend
+            .update(update_request(&[("progress", &progress)]))
+            .unwrap();
+    }
+    backend
+        .update(update_request(&[("percent", "90")]))
+        .unwrap();
+    backend.remove().unwrap();
+    assert_visible(backend, &[]);
+}
+
+/// An update with nothing shown fails and shows nothing.
+pub fn update_without_create(backend: &dyn Backend) {
+    backend.remove().unwrap();
+    assert!(backend
+        .update(update_request(&[("progress", "0.5")]))
+        .is_err());
+    assert_visible(backend, &[]);
+}
+
+/// A create while another activity is shown either replaces it or fails and
+/// leaves it up; reporting success while the old one stays is drift.
+pub fn double_create(backend: &dyn Backend) {
+    backend.remove().unwrap();
+    backend.create(create_request("first", &[])).unwrap();
+    match backend.create(create_request("second", &[])) {
+        Ok(()) => assert_visible(backend, &["second"]),
+        Err(_) => assert_visible(backend, &["first"]),
+    }
+    backend
+        .update(update_request(&[("progress", "0.3")]))
+        .unwrap();
+    backend.remove().unwrap();
+    assert_visible(backend, &[]);
+}
+
+/// Removing with nothing shown succeeds, also twice in a row.
+pub fn remove_without_create(backend: &dyn Backend) {
+    backend.remove().unwrap();
+    backend.remove().unwrap();
+    assert_visible(backend, &[]);
+}
+
+/// Progress outside `0..1` is clamped and unparsable progress keeps the last
+/// value; the activity stays up throughout.
+pub fn out_of_range_progress(backend: &dyn Backend) {
+    backend.remove().unwrap();
+    backend
+        .create(create_request("a", &[("progress", "-3")]))
+        .unwrap();
+    assert_progress(backend, 0.0);
+    for (state, expected) in [
+        (("percent", "-20"), 0.0),
+        (("progress", "0.4"), 0.4),
+        (("progress", "NaN"), 0.4),
+        (("percent", "250"), 1.0),
+        (("progress", "1.7"), 1.0),
+    ] {
+        backend.update(update_request(&[state])).unwrap();
+        assert_progress(backend, expected);
+    }
+    assert_visible(backend, &["a"]);
+    backend.remove().unwrap();
+    assert_visible(backend, &[]);
+}
+
+/// Runs every scenario on the same instance.
+pub fn run_all(backend: &dyn Backend) {
+    create_update_end(backend);
+    update_without_create(backend);
+    double_create(backend);
+    remove_without_create(backend);
+    out_of_range_progress(backend);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockBackend;
+
+    #[test]
+    fn mock_create_update_end() {
+        let backend = MockBackend::new();
+        create_update_end(&backend);
+        assert_eq!(backend.calls().len(), 9);
+    }
+
+    #[test]
+    fn mock_update_without_create() {
+        update_without_create(&MockBackend::new());
+    }
+
+    #[test]
+    fn mock_double_create() {
+        let backend = MockBackend::new();
+        double_create(&backend);
+        assert_eq!(backend.current_id(), None);
+    }
+
+    #[test]
+    fn mock_refuses_second_create() {
+        let backend = MockBackend::new();
+        backend.create(create_request("first", &[])).unwrap();
+        assert!(backend.create(create_request("second", &[])).is_err());
+        assert_eq!(backend.current_id().as_deref(), Some("first"));
+    }
+
+    #[test]
+    fn mock_remove_without_create() {
+        remove_without_create(&MockBackend::new());
+    }
+
+    #[test]
+    fn mock_out_of_range_progress() {
+        let backend = MockBackend::new();
+        out_of_range_progress(&backend);
+        assert_eq!(backend.progress(), None);
+    }
+
+    #[test]
+    fn mock_runs_all_scenarios_on_one_instance() {
+        run_all(&MockBackend::new());
+    }
+
+    #[cfg(all(desktop, target_os = "linux"))]
+    #[test]
+    fn linux_backend_conforms() {
+        run_all(&crate::desktop::NativeBackend);
+    }
+}
@@ -1,12 +1,11 @@
-use anyhow::Result;
 use serde::de::DeserializeOwned;
 use tauri::{plugin::PluginApi, AppHandle, Runtime};
 
-#[cfg(target_os = "windows")]
+#[cfg(all(target_os = "windows", feature = "windows-toast"))]
 #[path = "./win/implementation.rs"]
 pub mod imp;
 
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", feature = "macos-notifications"))]
 #[path = "./macos/implementation.rs"]
 pub mod imp;
 
@@ -14,33 +13,65 @@ pub mod imp;
 #[path = "./linux/implementation.rs"]
 pub mod imp;
 
-use imp::core;
+#[cfg(target_os = "linux")]
+pub(crate) use imp::core::LinuxBackend as NativeBackend;
+#[cfg(all(target_os = "macos", feature = "macos-notifications"))]
+pub(crate) use imp::core::MacosBackend as NativeBackend;
+#[cfg(all(target_os = "windows", feature = "windows-toast"))]
+pub(crate) use imp::core::WindowsBackend as NativeBackend;
 
-use crate::models::*;
+use crate::backend::Backend;
+use crate::in_app::InAppBackend;
+use crate::taskbar::TaskbarBackend;
+use crate::LiveActivity;
+use crate::PluginOptions;
 
 pub fn init<R: Runtime, C: DeserializeOwned>(
     app: &AppHandle<R>,
     _api: PluginApi<R, C>,
+    mut options: PluginOptions,
 ) -> crate::Result<LiveActivity<R>> {
-    Ok(LiveActivity(app.clone()))
-}
-
-/// Access to the live-activity APIs.
-pub struct LiveActivity<R: Runtime>(AppHandle<R>);
-
-impl<R: Runtime> LiveActivity<R> {
-    pub fn create_live_activity(&self, payload: CreateLiveActivityRequest) -> Result<()> {
-        core::create_live_activity(self, payload)?;
-        Ok(())
+    let backend = match options.backend.take() {
+        Some(backend) => backend,
+        None => default_backend()?,
+    };
+    if let Some(feed) = options.in_app.clone() {
+        options
+            .fallbacks
+            .push(Box::new(InAppBackend::new(app.clone(), feed)));
     }
-
-    pub fn update_live_activity(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
-        core::update_live_activity(self, payload)?;
-        Ok(())
+    if options.taskbar_fallback {
+        options
+            .fallbacks
+            .push(Box::new(TaskbarBackend::new(app.clone())));
     }
+    Ok(LiveActivity::new(app, backend, options))
+}
+
+#[cfg(any(
+    target_os = "linux",
+    all(target_os = "macos", feature = "macos-notifications"),
+    all(target_os = "windows", feature = "windows-toast"),
+))]
+fn default_backend() -> crate::Result<Box<dyn Backend>> {
+    Ok(Box::new(NativeBackend))
+}
 
-    pub fn remove_live_activity(&self) -> Result<()> {
-        core::remove_live_activity(self)?;
-        Ok(())
+#[cfg(not(any(
+    target_os = "linux",
+    all(target_os = "macos", feature = "macos-notifications"),
+    all(target_os = "windows", feature = "windows-toast"),
+)))]
+fn default_backend() -> crate::Result<Box<dyn Backend>> {
+    // 只有显式打开 mock 时才拿它顶上；它什么都不显示，得让人看得出来。
+    #[cfg(feature = "mock")]
+    {
+        tracing::warn!("no presenter for this platform is compiled in; using MockBackend");
+        return Ok(Box::new(crate::MockBackend::default()));
     }
+    #[cfg(not(feature = "mock"))]
+    Err(anyhow::anyhow!(
+        "no presenter for this platform is compiled in; enable its feature or pass Builder::backend"
+    )
+    .into())
 }
@@ -9,6 +9,20 @@ pub enum Error {
     #[cfg(mobile)]
     #[error(transparent)]
     PluginInvoke(#[from] tauri::plugin::mobile::PluginInvokeError),
+    #[error("notification permission has not been granted")]
+    PermissionDenied,
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        // 平台实现统一用 anyhow 传错，这里把其中夹带的插件错误还原出来。
+        match err.downcast::<Error>() {
+            Ok(err) => err,
+            Err(err) => Error::Other(err),
+        }
+    }
 }
 
 impl Serialize for Error {
@@ -0,0 +1,323 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+
+use crate::clock::now_millis;
+use crate::models::*;
+use crate::sink::ActivitySink;
+
+/// An activity that has not ended yet.
+struct Open {
+    content: ActivityContent,
+    started_at: u64,
+    peak_progress: Option<f32>,
+}
+
+/// Bounded record of ended activities, persisted as JSON in the app data dir.
+///
+/// Fed as a sink, so it sees exactly what the other sinks see; the oldest
+/// entries are dropped once `limit` is reached.
+pub(crate) struct HistoryStore {
+    path: Option<PathBuf>,
+    limit: usize,
+    open: Mutex<HashMap<String, Open>>,
+    entries: Mutex<VecDeque<HistoryEntry>>,
+}
+
+impl HistoryStore {
+    /// Loads `path`; a missing or unreadable file starts empty. Without a path
+    /// the history only lasts for this run.
+    pub fn load(path: Option<PathBuf>, limit: usize) -> Self {
+        let mut entries = path
+            .as_deref()
+            .filter(|p| p.exists())
+            .map(read)
+            .transpose()
+            .unwrap_or_else(|err| {
+                tracing::warn!(error = %err, "failed to load activity history");
+                None
+            })
+            .unwrap_or_default();
+        while entries.len() > limit {
+            entries.pop_front();
+        }
+        Self {
+            path,
+            limit,
+            open: Mutex::new(HashMap::new()),
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Entries matching `query`, newest first.
+    pub fn query(&self, query: &HistoryQuery) -> Vec<HistoryEntry> {
+        let entries = self.entries.lock().unwrap();
+        let matching = entries.iter().rev().filter(|e| query.matches(e)).cloned();
+        match query.limit {
+            Some(limit) => matching.take(limit).collect(),
+            None => matching.collect(),
+        }
+    }
+
+    /// Aggregates over every entry matching `query`, ignoring its `limit`.
+    pub fn stats(&self, query: &HistoryQuery) -> HistoryStats {
+        let entries = self.entries.lock().unwrap();
+        let mut stats = HistoryStats::default();
+        let mut total_ms = 0u64;
+        for entry in entries.iter().filter(|e| query.matches(e)) {
+            stats.count += 1;
+            if entry.succeeded {
+                stats.succeeded += 1;
+            }
+            total_ms += entry.ended_at.saturating_sub(entry.started_at);
+        }
+        stats.failed = stats.count - stats.succeeded;
+        if stats.count > 0 {
+            stats.success_rate = Some(stats.succeeded as f64 / stats.count as f64);
+            stats.mean_duration_ms = Some(total_ms / stats.count as u64);
+        }
+        stats
+    }
+
+    pub fn clear(&self) -> anyhow::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clear();
+        self.save(&entries)
+    }
+
+    fn closed(&self, id: &str, outcome: EndOutcome) -> anyhow::Result<()> {
+        let Some(open) = self.open.lock().unwrap().remove(id) else {
+            return Ok(());
+        };
+        let state = open.content.state();
+        let failure = state.get("error").filter(|e| !e.is_empty()).cloned();
+        let entry = HistoryEntry {
+            id: id.to_string(),
+            task_type: open.content.task_type().to_string(),
+            outcome,
+            succeeded: failure.is_none() && parse_progress(state) == Some(1.0),
+            started_at: open.started_at,
+            ended_at: now_millis(),
+            peak_progress: open.peak_progress,
+            failure,
+            content: open.content,
+        };
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(entry);
+        while entries.len() > self.limit {
+            entries.pop_front();
+        }
+        self.save(&entries)
+    }
+
+    fn save(&self, entries: &VecDeque<HistoryEntry>) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        crate::fs::write_atomic(path, &serde_json::to_vec(entries)?)
+            .with_context(|| format!("write {}", path.display()))
+    }
+}
+
+impl ActivitySink for Arc<HistoryStore> {
+    fn on_event(&self, event: &ActivityEvent) -> anyhow::Result<()> {
+        match event {
+            ActivityEvent::Created {
+                id,
+                content,
+                progress,
+            } => {
+                self.open.lock().unwrap().insert(
+                    id.clone(),
+                    Open {
+                        content: content.clone(),
+                        started_at: now_millis(),
+                        peak_progress: *progress,
+                    },
+                );
+            }
+            ActivityEvent::Updated {
+                id,
+                state,
+                progress,
+            } => {
+                if let Some(open) = self.open.lock().unwrap().get_mut(id) {
+                    *open.content.state_mut() = state.clone();
+                    if progress.is_some_and(|p| open.peak_progress.map_or(true, |peak| p > peak)) {
+                        open.peak_progress = *progress;
+                    }
+                }
+            }
+            ActivityEvent::Ended { id, outcome } => self.closed(id, *outcome)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "history"
+    }
+}
+
+impl HistoryQuery {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        // 时间范围按“活动期间和区间有重叠”算，跨过起点的任务也能查到。
+        self.from.map_or(true, |from| entry.ended_at >= from)
+            && self.to.map_or(true, |to| entry.started_at < to)
+            && self
+                .task_type
+                .as_deref()
+                .map_or(true, |t| entry.task_type == t)
+    }
+}
+
+fn read(path: &Path) -> anyhow::Result<VecDeque<HistoryEntry>> {
+    let data = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_support::task;
+
+    fn content(id: &str, task_type: &str) -> ActivityContent {
+        ActivityContent::TaskQueue(ActivityContentTaskQueue {
+            task_type: task_type.to_string(),
+            ..task(id)
+        })
+    }
+
+    fn entry(
+        id: &str,
+        task_type: &str,
+        started_at: u64,
+        ended_at: u64,
+        succeeded: bool,
+    ) -> HistoryEntry {
+        HistoryEntry {
+            id: id.to_string(),
+            task_type: task_type.to_string(),
+            content: content(id, task_type),
+            outcome: EndOutcome::Ended,
+            succeeded,
+            started_at,
+            ended_at,
+            peak_progress: None,
+            failure: None,
+        }
+    }
+
+    fn store(entries: Vec<HistoryEntry>) -> HistoryStore {
+        let store = HistoryStore::load(None, 100);
+        store.entries.lock().unwrap().extend(entries);
+        store
+    }
+
+    fn ids(entries: Vec<HistoryEntry>) -> Vec<String> {
+        entries.into_iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn stats_count_success_rate_and_mean_duration() {
+        let store = store(vec![
+            entry("a", "upload", 0, 1_000, true),
+            entry("b", "upload", 1_000, 4_000, false),
+            entry("c", "upload", 2_000, 4_000, true),
+            entry("d", "upload", 3_000, 5_000, true),
+        ]);
+        let stats = store.stats(&HistoryQuery::default());
+        assert_eq!((stats.count, stats.succeeded, stats.failed), (4, 3, 1));
+        assert_eq!(stats.success_rate, Some(0.75));
+        assert_eq!(stats.mean_duration_ms, Some(2_000));
+
+        let empty = HistoryStore::load(None, 10).stats(&HistoryQuery::default());
+        assert_eq!(empty.count, 0);
+        assert_eq!(empty.success_rate, None);
+        assert_eq!(empty.mean_duration_ms, None);
+    }
+
+    #[test]
+    fn time_range_matches_overlapping_entries() {
+        let store = store(vec![
+            entry("before", "sync", 0, 999, true),
+            entry("across-start", "sync", 500, 1_500, true),
+            entry("inside", "sync", 1_200, 1_800, true),
+            entry("across-end", "sync", 1_900, 2_500, true),
+            entry("after", "sync", 2_000, 3_000, true),
+        ]);
+        let query = HistoryQuery {
+            from: Some(1_000),
+            to: Some(2_000),
+            ..Default::default()
+        };
+        assert_eq!(
+            ids(store.query(&query)),
+            ["across-end", "inside", "across-start"]
+        );
+        // 统计和查询用同一个范围，只是不受条数限制。
+        let limited = HistoryQuery {
+            limit: Some(1),
+            ..query.clone()
+        };
+        assert_eq!(ids(store.query(&limited)), ["across-end"]);
+        assert_eq!(store.stats(&limited).count, 3);
+    }
+
+    #[test]
+    fn task_type_filters_queries_and_stats() {
+        let store = store(vec![
+            entry("a", "upload", 0, 1_000, true),
+            entry("b", "download", 0, 3_000, false),
+            entry("c", "upload", 0, 2_000, false),
+        ]);
+        let query = HistoryQuery {
+            task_type: Some("upload".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(store.query(&query)), ["c", "a"]);
+        let stats = store.stats(&query);
+        assert_eq!((stats.count, stats.succeeded), (2, 1));
+        assert_eq!(stats.mean_duration_ms, Some(1_500));
+    }
+
+    #[test]
+    fn drops_the_oldest_entries_at_the_limit() {
+        let store = Arc::new(HistoryStore::load(None, 2));
+        for id in ["a", "b", "c"] {
+            store
+                .on_event(&ActivityEvent::Created {
+                    id: id.to_string(),
+                    content: content(id, "upload"),
+                    progress: None,
+                })
+                .unwrap();
+            store
+                .on_event(&ActivityEvent::Ended {
+                    id: id.to_string(),
+                    outcome: EndOutcome::Ended,
+                })
+                .unwrap();
+        }
+        assert_eq!(ids(store.query(&HistoryQuery::default())), ["c", "b"]);
+    }
+
+    #[test]
+    fn load_trims_to_the_limit() {
+        let dir =
+            std::env::temp_dir().join(format!("live-activity-history-{}", std::process::id()));
+        let path = dir.join("history.json");
+        let stored = HistoryStore::load(Some(path.clone()), 10);
+        stored.entries.lock().unwrap().extend([
+            entry("a", "sync", 0, 1, true),
+            entry("b", "sync", 1, 2, true),
+        ]);
+        stored.save(&stored.entries.lock().unwrap()).unwrap();
+
+        let loaded = HistoryStore::load(Some(path), 1);
+        assert_eq!(ids(loaded.query(&HistoryQuery::default())), ["b"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::backend::Backend;
+use crate::models::*;
+
+/// Event carrying every [`InAppView`] the in-app presenter produces.
+pub(crate) const IN_APP_EVENT: &str = "live-activity://in-app";
+
+/// Latest view and the channels subscribed through `watch_in_app`.
+#[derive(Default)]
+pub(crate) struct InAppFeed {
+    latest: Mutex<Option<InAppView>>,
+    channels: Mutex<Vec<Channel<InAppView>>>,
+}
+
+impl InAppFeed {
+    /// Adds `channel` and sends it the activity currently shown, if any.
+    pub fn subscribe(&self, channel: Channel<InAppView>) -> Result<()> {
+        if let Some(view) = self.latest.lock().unwrap().clone() {
+            channel.send(view)?;
+        }
+        self.channels.lock().unwrap().push(channel);
+        Ok(())
+    }
+
+    fn publish(&self, view: InAppView) {
+        // 结束后的画面只推一次，之后订阅的前端不需要再看到它。
+        *self.latest.lock().unwrap() = view.outcome.is_none().then(|| view.clone());
+        // 发送失败说明对应的页面已经没了，顺手清掉。
+        self.channels
+            .lock()
+            .unwrap()
+            .retain(|channel| channel.send(view.clone()).is_ok());
+    }
+}
+
+/// Presents activities inside the app's own webview.
+///
+/// Every create, update and end becomes an [`InAppView`], emitted as
+/// [`IN_APP_EVENT`] and sent to the subscribed channels, so the frontend can
+/// draw a banner when the OS shows nothing. Needs no permission.
+pub(crate) struct InAppBackend<R: Runtime> {
+    app: AppHandle<R>,
+    feed: Arc<InAppFeed>,
+    current: Mutex<Option<ActivityContent>>,
+}
+
+impl<R: Runtime> InAppBackend<R> {
+    pub fn new(app: AppHandle<R>, feed: Arc<InAppFeed>) -> Self {
+        Self {
+            app,
+            feed,
+            current: Mutex::new(None),
+        }
+    }
+
+    fn send(&self, view: InAppView) {
+        if let Err(err) = self.app.emit(IN_APP_EVENT, &view) {
+            tracing::debug!(error = %err, "failed to emit in-app view");
+        }
+        self.feed.publish(view);
+    }
+}
+
+impl<R: Runtime> Backend for InAppBackend<R> {
+    fn name(&self) -> &'static str {
+        "in-app"
+    }
+
+    fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+        let content = payload.activity_content;
+        self.send(view(&content, content.state(), None));
+        *self.current.lock().unwrap() = Some(content);
+        Ok(())
+    }
+
+    fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+        let mut current = self.current.lock().unwrap();
+        let Some(content) = current.as_mut() else {
+            anyhow::bail!("No active live activity to update");
+        };
+        *content.state_mut() = payload.state;
+        self.send(view(content, content.state(), None));
+        Ok(())
+    }
+
+    fn remove(&self) -> Result<()> {
+        let Some(content) = self.current.lock().unwrap().take() else {
+            return Ok(());
+        };
+        let state = content.state();
+        self.send(view(&content, state, Some(outcome(state))));
+        Ok(())
+    }
+
+    fn visible_ids(&self) -> Result<Vec<String>> {
+        Ok(self
+            .current
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|c| c.id().to_string())
+            .collect())
+    }
+
+    fn capabilities(&self) -> Result<Capabilities> {
+        Ok(Capabilities {
+            backend: self.name().to_string(),
+            presents: true,
+            progress_bar: true,
+            indeterminate_progress: true,
+            buttons: true,
+            images: true,
+            updates_in_place: true,
+            max_concurrent: 1,
+        })
+    }
+
+    fn permission_state(&self) -> Result<PermissionState> {
+        Ok(PermissionState::Granted)
+    }
+
+    fn request_permission(&self) -> Result<PermissionState> {
+        Ok(PermissionState::Granted)
+    }
+}
+
+fn outcome(state: &HashMap<String, String>) -> InAppOutcome {
+    if state.get("error").is_some_and(|e| !e.is_empty()) {
+        InAppOutcome::Failed
+    } else if parse_progress(state) == Some(1.0) {
+        InAppOutcome::Succeeded
+    } else {
+        InAppOutcome::Ended
+    }
+}
+
+fn view(
+    content: &ActivityContent,
+    state: &HashMap<String, String>,
+    outcome: Option<InAppOutcome>,
+) -> InAppView {
+    let ActivityContent::TaskQueue(task) = content;
+    let flag = |key: &str, value: &str| state.get(key).is_some_and(|v| v == value);
+    let hint = state.get("hint").cloned();
+    let progress = parse_progress(state).filter(|_| hint.is_none());
+    let progress_text = hint.unwrap_or_else(|| match (state.get("percent"), progress) {
+        (Some(percent), _) => format!("{percent}%"),
+        (None, Some(p)) => format!("{:.0}%", p * 100.0),
+        (None, None) => String::new(),
+    });
+    let subtitle = if task.task_name.is_empty() {
+        task.text.clone()
+    } else {
+        format!("{} · {}", task.text, task.task_name)
+    };
+    InAppView {
+        id: task.id.clone(),
+        task_type: task.task_type.clone(),
+        icon: task.task_icon.clone(),
+        title: task.title.clone(),
+        subtitle,
+        progress,
+        indeterminate: progress.is_none(),
+        progress_text,
+        silent: flag("silent", "true"),
+        // 结束后才给关闭按钮，进行中的横幅跟着活动走。
+        actions: match outcome {
+            Some(_) => vec![InAppAction {
+                id: "dismiss".to_string(),
+                label: "关闭".to_string(),
+            }],
+            None => Vec::new(),
+        },
+        show_outcome: !(outcome == Some(InAppOutcome::Succeeded) && flag("completion", "hidden")),
+        outcome,
+        error: state.get("error").filter(|e| !e.is_empty()).cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_support::{content, create, state, task};
+    use tauri::test::mock_app;
+
+    fn view_of(pairs: &[(&str, &str)], outcome: Option<InAppOutcome>) -> InAppView {
+        let state = state(pairs);
+        view(&content(task("a")), &state, outcome)
+    }
+
+    #[test]
+    fn progress_shows_as_a_bar_and_a_percentage() {
+        let running = view_of(&[("progress", "0.42")], None);
+        assert_eq!(running.progress, Some(0.42));
+        assert!(!running.indeterminate);
+        assert_eq!(running.progress_text, "42%");
+
+        let percent = view_of(&[("progress", "0.42"), ("percent", "40")], None);
+        assert_eq!(percent.progress_text, "40%");
+
+        let unknown = view_of(&[], None);
+        assert_eq!(unknown.progress, None);
+        assert!(unknown.indeterminate);
+        assert_eq!(unknown.progress_text, "");
+    }
+
+    #[test]
+    fn hint_replaces_the_progress() {
+        let stalled = view_of(&[("progress", "0.42"), ("hint", "Waiting…")], None);
+        assert_eq!(stalled.progress, None);
+        assert!(stalled.indeterminate);
+        assert_eq!(stalled.progress_text, "Waiting…");
+    }
+
+    #[test]
+    fn subtitle_joins_text_and_task_name() {
+        let task = ActivityContentTaskQueue {
+            text: "3 of 5".to_string(),
+            task_name: "Upload".to_string(),
+            ..task("a")
+        };
+        let shown = view(&content(task.clone()), &HashMap::new(), None);
+        assert_eq!(shown.subtitle, "3 of 5 · Upload");
+        let task = ActivityContentTaskQueue {
+            task_name: String::new(),
+            ..task
+        };
+        assert_eq!(
+            view(&content(task), &HashMap::new(), None).subtitle,
+            "3 of 5"
+        );
+    }
+
+    #[test]
+    fn outcome_follows_the_last_state() {
+        assert_eq!(
+            outcome(&state(&[("progress", "1")])),
+            InAppOutcome::Succeeded
+        );
+        assert_eq!(outcome(&state(&[("progress", "0.5")])), InAppOutcome::Ended);
+        assert_eq!(
+            outcome(&state(&[("progress", "1"), ("error", "disk full")])),
+            InAppOutcome::Failed
+        );
+        assert_eq!(outcome(&state(&[("error", "")])), InAppOutcome::Ended);
+
+        let running = view_of(&[("silent", "true")], None);
+        assert!(running.silent);
+        assert!(running.actions.is_empty());
+        let failed = view_of(&[("error", "disk full")], Some(InAppOutcome::Failed));
+        assert_eq!(failed.error.as_deref(), Some("disk full"));
+        assert_eq!(failed.actions.len(), 1);
+        assert!(failed.show_outcome);
+
+        // 用户关掉了完成通知：成功时不展示结果，失败照常展示。
+        let hidden = [("progress", "1"), ("completion", "hidden")];
+        assert!(!view_of(&hidden, Some(InAppOutcome::Succeeded)).show_outcome);
+        assert!(view_of(&hidden, Some(InAppOutcome::Ended)).show_outcome);
+    }
+
+    #[test]
+    fn feed_keeps_only_the_running_activity() {
+        let app = mock_app();
+        let feed = Arc::new(InAppFeed::default());
+        let backend = InAppBackend::new(app.handle().clone(), feed.clone());
+        let latest = || feed.latest.lock().unwrap().clone();
+
+        backend.create(create(task("a"))).unwrap();
+        assert_eq!(latest().map(|v| v.id), Some("a".to_string()));
+        backend
+            .update(UpdateLiveActivityRequest {
+                state: state(&[("progress", "0.5")]),
+                id: None,
+            })
+            .unwrap();
+        assert_eq!(latest().and_then(|v| v.progress), Some(0.5));
+        assert_eq!(backend.visible_ids().unwrap(), ["a"]);
+
+        backend.remove().unwrap();
+        assert_eq!(latest(), None);
+        assert!(backend.visible_ids().unwrap().is_empty());
+        assert!(backend
+            .update(UpdateLiveActivityRequest {
+                state: HashMap::new(),
+                id: None,
+            })
+            .is_err());
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use crate::models::*;
+use crate::sink::ActivitySink;
+
+/// Releases the platform inhibitor when dropped.
+type Guard = Box<dyn Send>;
+
+/// Takes the inhibitor for the app name and the reason shown to the user.
+type Acquire = Box<dyn Fn(&str, &str) -> Result<Guard> + Send + Sync>;
+
+#[derive(Default)]
+struct State {
+    /// Running activities that asked for `inhibit_sleep`.
+    holders: HashSet<String>,
+    guard: Option<Guard>,
+}
+
+/// Keeps the system awake while any activity with `inhibit_sleep` runs.
+///
+/// Fed as a sink: the inhibitor is taken with the first such activity and
+/// released once the last one ends or reports an `error`.
+pub(crate) struct SleepInhibitor {
+    app_name: String,
+    acquire: Acquire,
+    state: Mutex<State>,
+}
+
+impl SleepInhibitor {
+    pub fn new(app_name: String) -> Self {
+        Self::with_acquire(app_name, Box::new(acquire))
+    }
+
+    fn with_acquire(app_name: String, acquire: Acquire) -> Self {
+        Self {
+            app_name,
+            acquire,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Lets the system sleep again, whatever is still running; used on exit.
+    pub fn release_all(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.holders.clear();
+        if state.guard.take().is_some() {
+            tracing::debug!("sleep inhibitor released");
+        }
+    }
+
+    fn hold(&self, id: &str, title: &str) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.holders.insert(id.to_string());
+        if state.guard.is_none() {
+            state.guard = Some((self.acquire)(&self.app_name, title)?);
+            tracing::debug!(id, "sleep inhibitor taken");
+        }
+        Ok(())
+    }
+
+    fn release(&self, id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.holders.remove(id) && state.holders.is_empty() && state.guard.take().is_some() {
+            tracing::debug!(id, "sleep inhibitor released");
+        }
+    }
+}
+
+impl ActivitySink for Arc<SleepInhibitor> {
+    fn on_event(&self, event: &ActivityEvent) -> Result<()> {
+        match event {
+            ActivityEvent::Created { id, content, .. } if content.inhibit_sleep() => {
+                self.hold(id, content.title())?;
+            }
+            ActivityEvent::Updated { id, state, .. }
+                if state.get("error").is_some_and(|e| !e.is_empty()) =>
+            {
+                self.release(id);
+            }
+            ActivityEvent::Ended { id, .. } => self.release(id),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "sleep-inhibitor"
+    }
+}
+
+/// logind first, then the desktop portal, which is what a Flatpak sandbox can reach.
+#[cfg(target_os = "linux")]
+#[allow(unused_variables, unused_mut)]
+fn acquire(app_name: &str, reason: &str) -> Result<Guard> {
+    let mut errors: Vec<String> = Vec::new();
+    #[cfg(feature = "sleep-inhibit")]
+    match linux::login1(app_name, reason) {
+        Ok(guard) => return Ok(guard),
+        Err(err) => errors.push(format!("login1: {err:#}")),
+    }
+    #[cfg(feature = "linux-portal")]
+    match linux::portal(reason) {
+        Ok(guard) => return Ok(guard),
+        Err(err) => errors.push(format!("portal: {err:#}")),
+    }
+    if errors.is_empty() {
+        anyhow::bail!("no sleep inhibitor is compiled in");
+    }
+    anyhow::bail!("cannot inhibit sleep: {}", errors.join("; "))
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(feature = "sleep-inhibit", feature = "linux-portal")
+))]
+mod linux {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use dbus::blocking::Connection;
+
+    use super::Guard;
+
+    const DBUS_TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// `Inhibit` on logind; the lock lasts as long as the returned fd is open.
+    #[cfg(feature = "sleep-inhibit")]
+    pub fn login1(app_name: &str, reason: &str) -> Result<Guard> {
+        let conn = Connection::new_system()?;
+        let proxy = conn.with_proxy(
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            DBUS_TIMEOUT,
+        );
+        let (fd,): (dbus::arg::OwnedFd,) = proxy.method_call(
+            "org.freedesktop.login1.Manager",
+            "Inhibit",
+            ("sleep:idle", app_name, reason, "block"),
+        )?;
+        Ok(Box::new(fd))
+    }
+
+    /// `Inhibit` on the desktop portal; released by closing the returned request.
+    #[cfg(feature = "linux-portal")]
+    pub fn portal(reason: &str) -> Result<Guard> {
+        use dbus::arg::{PropMap, Variant};
+
+        // 4 = Suspend，8 = Idle。
+        const SUSPEND_AND_IDLE: u32 = 4 | 8;
+        let conn = Connection::new_session()?;
+        let proxy = conn.with_proxy(
+            "org.freedesktop.portal.Desktop",
+            "/org/freedesktop/portal/desktop",
+            DBUS_TIMEOUT,
+        );
+        let mut options = PropMap::new();
+        options.insert("reason".to_string(), Variant(Box::new(reason.to_string())));
+        let (handle,): (dbus::Path<'static>,) = proxy.method_call(
+            "org.freedesktop.portal.Inhibit",
+            "Inhibit",
+            ("", SUSPEND_AND_IDLE, options),
+        )?;
+        Ok(Box::new(PortalInhibit { conn, handle }))
+    }
+
+    #[cfg(feature = "linux-portal")]
+    struct PortalInhibit {
+        // 抑制跟着这条连接走，连接断了 portal 也会自己撤销。
+        conn: Connection,
+        handle: dbus::Path<'static>,
+    }
+
+    #[cfg(feature = "linux-portal")]
+    impl Drop for PortalInhibit {
+        fn drop(&mut self) {
+            let proxy =
+                self.conn
+                    .with_proxy("org.freedesktop.portal.Desktop", &self.handle, DBUS_TIMEOUT);
+            let result: Result<(), dbus::Error> =
+                proxy.method_call("org.freedesktop.portal.Request", "Close", ());
+            if let Err(err) = result {
+                tracing::debug!(error = %err, "failed to close portal inhibitor");
+            }
+        }
+    }
+}
+
+/// A system-required power request; it goes away with its handle.
+#[cfg(all(windows, feature = "sleep-inhibit"))]
+fn acquire(_app_name: &str, reason: &str) -> Result<Guard> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows::Win32::System::Power::{
+        PowerClearRequest, PowerCreateRequest, PowerRequestSystemRequired, PowerSetRequest,
+    };
+    use windows::Win32::System::Threading::{
+        POWER_REQUEST_CONTEXT_SIMPLE_STRING, POWER_REQUEST_CONTEXT_VERSION, REASON_CONTEXT,
+        REASON_CONTEXT_0,
+    };
+
+    struct PowerRequest(HANDLE);
+
+    // 句柄只在 drop 时用一次，跨线程持有没有问题。
+    unsafe impl Send for PowerRequest {}
+
+    impl Drop for PowerRequest {
+        fn drop(&mut self) {
+            unsafe {
+                let _ = PowerClearRequest(self.0, PowerRequestSystemRequired);
+                let _ = CloseHandle(self.0);
+            }
+        }
+    }
+
+    let mut reason: Vec<u16> = reason.encode_utf16().chain(Some(0)).collect();
+    let context = REASON_CONTEXT {
+        Version: POWER_REQUEST_CONTEXT_VERSION,
+        Flags: POWER_REQUEST_CONTEXT_SIMPLE_STRING,
+        Reason: REASON_CONTEXT_0 {
+            SimpleReasonString: PWSTR(reason.as_mut_ptr()),
+        },
+    };
+    let request = PowerRequest(unsafe { PowerCreateRequest(&context)? });
+    unsafe { PowerSetRequest(request.0, PowerRequestSystemRequired)? };
+    Ok(Box::new(request))
+}
+
+/// `caffeinate` watching this process, so it also ends if the app is killed.
+#[cfg(all(target_os = "macos", feature = "sleep-inhibit"))]
+fn acquire(_app_name: &str, _reason: &str) -> Result<Guard> {
+    use std::process::{Child, Command};
+
+    struct Caffeinate(Child);
+
+    impl Drop for Caffeinate {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    let child = Command::new("/usr/bin/caffeinate")
+        .args(["-i", "-w", &std::process::id().to_string()])
+        .spawn()?;
+    Ok(Box::new(Caffeinate(child)))
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    all(windows, feature = "sleep-inhibit"),
+    all(target_os = "macos", feature = "sleep-inhibit"),
+)))]
+fn acquire(_app_name: &str, _reason: &str) -> Result<Guard> {
+    anyhow::bail!("keeping the system awake is not supported on this platform")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::models::test_support::{content, state, task};
+
+    /// Counts the inhibitors taken and the ones still held.
+    #[derive(Default)]
+    struct Counts {
+        taken: AtomicUsize,
+        held: AtomicUsize,
+    }
+
+    struct Held(Arc<Counts>);
+
+    impl Drop for Held {
+        fn drop(&mut self) {
+            self.0.held.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn inhibitor() -> (Arc<SleepInhibitor>, Arc<Counts>) {
+        let counts = Arc::new(Counts::default());
+        let acquire_counts = counts.clone();
+        let inhibitor = SleepInhibitor::with_acquire(
+            "app".to_string(),
+            Box::new(move |_, _| {
+                acquire_counts.taken.fetch_add(1, Ordering::SeqCst);
+                acquire_counts.held.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(Held(acquire_counts.clone())) as Guard)
+            }),
+        );
+        (Arc::new(inhibitor), counts)
+    }
+
+    fn created(id: &str, inhibit_sleep: bool) -> ActivityEvent {
+        ActivityEvent::Created {
+            id: id.to_string(),
+            content: content(ActivityContentTaskQueue {
+                inhibit_sleep,
+                ..task(id)
+            }),
+            progress: None,
+        }
+    }
+
+    fn ended(id: &str, outcome: EndOutcome) -> ActivityEvent {
+        ActivityEvent::Ended {
+            id: id.to_string(),
+            outcome,
+        }
+    }
+
+    fn held(counts: &Counts) -> (usize, usize) {
+        (
+            counts.taken.load(Ordering::SeqCst),
+            counts.held.load(Ordering::SeqCst),
+        )
+    }
+
+    #[test]
+    fn held_while_any_inhibiting_activity_runs() {
+        let (inhibitor, counts) = inhibitor();
+        inhibitor.on_event(&created("plain", false)).unwrap();
+        assert_eq!(held(&counts), (0, 0));
+
+        inhibitor.on_event(&created("a", true)).unwrap();
+        inhibitor.on_event(&created("b", true)).unwrap();
+        assert_eq!(held(&counts), (1, 1));
+
+        inhibitor
+            .on_event(&ended("plain", EndOutcome::Ended))
+            .unwrap();
+        inhibitor.on_event(&ended("a", EndOutcome::Ended)).unwrap();
+        assert_eq!(held(&counts), (1, 1));
+        // 被中断、被移除的活动同样会放开。
+        inhibitor
+            .on_event(&ended("b", EndOutcome::Interrupted))
+            .unwrap();
+        assert_eq!(held(&counts), (1, 0));
+
+        inhibitor.on_event(&created("c", true)).unwrap();
+        assert_eq!(held(&counts), (2, 1));
+    }
+
+    #[test]
+    fn released_on_error_and_on_exit() {
+        let (inhibitor, counts) = inhibitor();
+        inhibitor.on_event(&created("a", true)).unwrap();
+        inhibitor
+            .on_event(&ActivityEvent::Updated {
+                id: "a".to_string(),
+                state: state(&[("progress", "0.5")]),
+                progress: Some(0.5),
+            })
+            .unwrap();
+        assert_eq!(held(&counts), (1, 1));
+        inhibitor
+            .on_event(&ActivityEvent::Updated {
+                id: "a".to_string(),
+                state: state(&[("error", "disk full")]),
+                progress: None,
+            })
+            .unwrap();
+        assert_eq!(held(&counts), (1, 0));
+
+        inhibitor.on_event(&created("b", true)).unwrap();
+        inhibitor.on_event(&created("c", true)).unwrap();
+        inhibitor.release_all();
+        assert_eq!(held(&counts), (2, 0));
+        // 退出后才到的结束事件不会出错，也不会再拿一次。
+        inhibitor.on_event(&ended("b", EndOutcome::Ended)).unwrap();
+        assert_eq!(held(&counts), (2, 0));
+    }
+}
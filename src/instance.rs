@@ -0,0 +1,813 @@
+//! Single-owner coordination between processes of the same app.
+//!
+//! Processes of one app share its notification identity (the fixed AUMID on
+//! Windows), so two of them presenting at once clobber each other's toasts.
+//! The process holding [`LOCK_FILE`] in the app runtime dir owns the platform
+//! presenter; the others forward their visible activity to it over the IPC
+//! endpoint it advertises in the lock, with the id prefixed by their namespace
+//! so ids never collide in the owner's registry. Each instance serves an
+//! endpoint of its own (see [`crate::ipc::instance_endpoint`]), so the one in
+//! the lock always leads to the owner.
+//!
+//! The lock is taken by creating the file exclusively, so of two instances
+//! racing for it only one wins. The owner rewrites it every [`LOCK_REFRESH`];
+//! a lock left alone for [`LOCK_STALE`] is free to take. Before it goes away,
+//! or once it finds the lock taken over, the owner writes the activities it
+//! shows for others to [`HANDOVER_FILE`], and the next owner adopts them.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::backend::Backend;
+use crate::clock::now_millis;
+use crate::fs::write_atomic;
+use crate::ipc::Client;
+use crate::models::*;
+
+const LOCK_FILE: &str = "live-activity.lock";
+
+const HANDOVER_FILE: &str = "live-activity.handover.json";
+
+/// How often the owner refreshes the lock and the others check whether it is free.
+pub(crate) const LOCK_REFRESH: Duration = Duration::from_secs(5);
+
+/// Age after which a lock counts as left behind by a crashed owner.
+const LOCK_STALE: Duration = Duration::from_secs(15);
+
+/// `owner` recorded for activities forwarded by the instance after the colon.
+const INSTANCE_OWNER: &str = "instance:";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LockInfo {
+    namespace: String,
+    pid: u32,
+    #[serde(default)]
+    endpoint: Option<PathBuf>,
+    /// Milliseconds since the Unix epoch.
+    refreshed_at: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Handover {
+    from: String,
+    activities: Vec<CreateLiveActivityRequest>,
+}
+
+/// Where the activity this instance shows went.
+enum Presented {
+    None,
+    Local(CreateLiveActivityRequest),
+    /// Shown by the owner. Kept without the namespace and with the latest
+    /// state, so it can be shown locally again after a takeover.
+    Forwarded(CreateLiveActivityRequest),
+}
+
+struct State {
+    owner: bool,
+    /// When this instance last gave the lock up; it leaves the lock to the
+    /// others for a while afterwards.
+    released_at: Option<u64>,
+    presented: Presented,
+}
+
+struct Inner {
+    local: Box<dyn Backend>,
+    dir: PathBuf,
+    namespace: String,
+    endpoint: Option<PathBuf>,
+    state: Mutex<State>,
+}
+
+/// Wraps the platform backend so that only the owning instance presents.
+#[derive(Clone)]
+pub(crate) struct Coordinator(Arc<Inner>);
+
+/// Directory shared by every process of the app for the lock and handover files.
+pub(crate) fn lock_dir<R: Runtime>(app: &AppHandle<R>) -> Option<PathBuf> {
+    let path = app.path();
+    path.runtime_dir()
+        .map(|dir| dir.join(&app.config().identifier))
+        .or_else(|_| path.app_local_data_dir())
+        .inspect_err(|err| tracing::warn!(error = %err, "no runtime dir for the instance lock"))
+        .ok()
+}
+
+/// Namespace of the instance that forwarded `content`, if another one did.
+pub(crate) fn origin(content: &ActivityContent) -> Option<&str> {
+    content.owner()?.strip_prefix(INSTANCE_OWNER)
+}
+
+fn namespaced(
+    namespace: &str,
+    mut request: CreateLiveActivityRequest,
+) -> CreateLiveActivityRequest {
+    match &mut request.activity_content {
+        ActivityContent::TaskQueue(t) => {
+            t.id = format!("{namespace}/{}", t.id);
+            // 原来的 owner 是本进程的 webview，到了别的进程就没有意义了。
+            t.owner = Some(format!("{INSTANCE_OWNER}{namespace}"));
+        }
+    }
+    request
+}
+
+fn parse_lock(text: &str) -> Option<LockInfo> {
+    serde_json::from_str(text).ok()
+}
+
+fn elapsed_since(at: u64) -> Duration {
+    Duration::from_millis(now_millis().saturating_sub(at))
+}
+
+impl Coordinator {
+    pub fn new(
+        local: Box<dyn Backend>,
+        dir: PathBuf,
+        namespace: String,
+        endpoint: Option<PathBuf>,
+    ) -> Self {
+        Self(Arc::new(Inner {
+            local,
+            dir,
+            namespace,
+            endpoint,
+            state: Mutex::new(State {
+                owner: false,
+                released_at: None,
+                presented: Presented::None,
+            }),
+        }))
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.0.namespace
+    }
+
+    pub fn is_owner(&self) -> bool {
+        self.state().owner
+    }
+
+    pub fn status(&self) -> InstanceStatus {
+        let owner = self.state().owner;
+        let holder = self.read_lock();
+        InstanceStatus {
+            namespace: self.0.namespace.clone(),
+            owner,
+            owner_namespace: holder.as_ref().map(|h| h.namespace.clone()),
+            owner_pid: holder.map(|h| h.pid),
+        }
+    }
+
+    /// Refreshes the lock when this instance holds it, or takes it when it is
+    /// free or stale. Returns `true` when this instance owned the lock and
+    /// another one took it over; the caller then hands its activities over
+    /// with [`Coordinator::surrender`].
+    pub fn tick(&self) -> bool {
+        let mut state = self.state();
+        let text = self.read_lock_text();
+        let holder = text.as_deref().and_then(parse_lock);
+        if state.owner {
+            // 锁只会是本实例写的完整内容；读不到或换了人都算丢了。
+            if holder.is_some_and(|h| h.namespace == self.0.namespace) {
+                if let Err(err) = self.write_lock() {
+                    tracing::warn!(error = %err, "failed to refresh the instance lock");
+                }
+                return false;
+            }
+            tracing::warn!(
+                namespace = self.0.namespace,
+                "another instance took the lock over"
+            );
+            state.owner = false;
+            return true;
+        }
+        let yielded = state
+            .released_at
+            .is_some_and(|at| elapsed_since(at) < LOCK_STALE);
+        if yielded {
+            return false;
+        }
+        if let Some(text) = text {
+            if !self.is_stale(&text) || !self.clear_stale_lock(&text) {
+                return false;
+            }
+        }
+        match self.create_lock() {
+            Ok(()) => {}
+            // 两个实例同时看到锁空着时，只有一个能建出来。
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => return false,
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to take the instance lock");
+                return false;
+            }
+        }
+        state.owner = true;
+        state.released_at = None;
+        tracing::info!(
+            namespace = self.0.namespace,
+            "this instance presents activities now"
+        );
+        if let Presented::Forwarded(request) =
+            std::mem::replace(&mut state.presented, Presented::None)
+        {
+            match self.0.local.create(request.clone()) {
+                Ok(()) => state.presented = Presented::Local(request),
+                Err(err) => tracing::warn!(error = %err, "failed to take over own activity"),
+            }
+        }
+        false
+    }
+
+    /// Activities the previous owner handed over, minus the one this instance
+    /// forwarded itself; empty unless this instance owns the lock.
+    pub fn take_handover(&self) -> Vec<CreateLiveActivityRequest> {
+        if !self.is_owner() {
+            return Vec::new();
+        }
+        let path = self.0.dir.join(HANDOVER_FILE);
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            return Vec::new();
+        };
+        let _ = std::fs::remove_file(&path);
+        let handover: Handover = match serde_json::from_str(&text) {
+            Ok(handover) => handover,
+            Err(err) => {
+                tracing::warn!(error = %err, "ignoring unreadable handover file");
+                return Vec::new();
+            }
+        };
+        tracing::info!(
+            from = handover.from,
+            count = handover.activities.len(),
+            "adopting handed over activities"
+        );
+        handover
+            .activities
+            .into_iter()
+            .filter(|r| origin(&r.activity_content) != Some(self.0.namespace.as_str()))
+            .collect()
+    }
+
+    /// Gives the lock up and leaves `remote`, the activities shown for other
+    /// instances, to the next owner. With `own`, the activity of this instance
+    /// is handed over as well and its updates are forwarded from then on.
+    pub fn hand_over(&self, remote: Vec<CreateLiveActivityRequest>, own: bool) -> Result<()> {
+        let mut state = self.state();
+        if !state.owner {
+            anyhow::bail!("this instance does not present activities");
+        }
+        self.give_up(&mut state, remote, own)
+    }
+
+    /// After [`Coordinator::tick`] reported the lock taken over: leaves
+    /// `remote` and the activity of this instance to the new owner, as
+    /// [`Coordinator::hand_over`] does.
+    pub fn surrender(&self, remote: Vec<CreateLiveActivityRequest>) -> Result<()> {
+        let mut state = self.state();
+        self.give_up(&mut state, remote, true)
+    }
+
+    fn give_up(
+        &self,
+        state: &mut State,
+        remote: Vec<CreateLiveActivityRequest>,
+        own: bool,
+    ) -> Result<()> {
+        let mut activities = remote;
+        let kept = match std::mem::replace(&mut state.presented, Presented::None) {
+            Presented::Local(request) if origin(&request.activity_content).is_none() => {
+                if own {
+                    activities.push(namespaced(&self.0.namespace, request.clone()));
+                }
+                Some(request)
+            }
+            _ => None,
+        };
+        let handover = Handover {
+            from: self.0.namespace.clone(),
+            activities,
+        };
+        write_atomic(
+            &self.0.dir.join(HANDOVER_FILE),
+            &serde_json::to_vec(&handover)?,
+        )
+        .context("write handover file")?;
+        if let Err(err) = self.0.local.remove() {
+            tracing::debug!(error = %err, "failed to clear handed over activity");
+        }
+        if let Some(request) = kept.filter(|_| own) {
+            state.presented = Presented::Forwarded(request);
+        }
+        if self
+            .read_lock()
+            .is_some_and(|h| h.namespace == self.0.namespace)
+        {
+            let _ = std::fs::remove_file(self.lock_path());
+        }
+        state.owner = false;
+        state.released_at = Some(now_millis());
+        Ok(())
+    }
+
+    /// Called when the app exits: the owner hands `remote` over, any other
+    /// instance ends the activity it forwarded.
+    pub fn leave(&self, remote: Vec<CreateLiveActivityRequest>) {
+        if self.is_owner() {
+            if let Err(err) = self.hand_over(remote, false) {
+                tracing::warn!(error = %err, "failed to hand activities over");
+            }
+        } else if let Err(err) = self.remove() {
+            tracing::debug!(error = %err, "failed to end forwarded activity");
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.0.state.lock().unwrap()
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.0.dir.join(LOCK_FILE)
+    }
+
+    fn read_lock_text(&self) -> Option<String> {
+        std::fs::read_to_string(self.lock_path()).ok()
+    }
+
+    fn read_lock(&self) -> Option<LockInfo> {
+        parse_lock(&self.read_lock_text()?)
+    }
+
+    fn lock_info(&self) -> LockInfo {
+        LockInfo {
+            namespace: self.0.namespace.clone(),
+            pid: std::process::id(),
+            endpoint: self.0.endpoint.clone(),
+            refreshed_at: now_millis(),
+        }
+    }
+
+    /// Rewrites the lock this instance holds.
+    fn write_lock(&self) -> Result<()> {
+        write_atomic(&self.lock_path(), &serde_json::to_vec(&self.lock_info())?)?;
+        Ok(())
+    }
+
+    /// Creates the lock; fails with `AlreadyExists` when another instance holds it.
+    fn create_lock(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.0.dir)?;
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.lock_path())?;
+        let info = serde_json::to_vec(&self.lock_info())?;
+        file.write_all(&info).inspect_err(|_| {
+            let _ = std::fs::remove_file(self.lock_path());
+        })
+    }
+
+    /// Whether the lock reading `text` was left behind: by an earlier process
+    /// with this namespace, or by an owner that stopped refreshing it.
+    fn is_stale(&self, text: &str) -> bool {
+        match parse_lock(text) {
+            Some(h) => {
+                h.namespace == self.0.namespace || elapsed_since(h.refreshed_at) > LOCK_STALE
+            }
+            // 刚建出来还没写完的锁是空的，只有放久了才算残留。
+            None => std::fs::metadata(self.lock_path())
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|at| at.elapsed().ok())
+                .is_some_and(|age| age > LOCK_STALE),
+        }
+    }
+
+    /// Removes the stale lock that read `text`. Moves it aside first, so that
+    /// a lock another instance created in the meantime is put back instead of
+    /// deleted; returns whether the lock is gone.
+    fn clear_stale_lock(&self, text: &str) -> bool {
+        let aside = self
+            .lock_path()
+            .with_extension(format!("{}.stale", std::process::id()));
+        if std::fs::rename(self.lock_path(), &aside).is_err() {
+            // 别的实例已经先挪走了。
+            return !self.lock_path().exists();
+        }
+        let moved = std::fs::read_to_string(&aside).ok();
+        if moved.as_deref() != Some(text) {
+            // 挪走的是别人刚建的锁；硬链接回去，已经又有锁时不会覆盖。
+            let _ = std::fs::hard_link(&aside, self.lock_path());
+            let _ = std::fs::remove_file(&aside);
+            return false;
+        }
+        let _ = std::fs::remove_file(&aside);
+        true
+    }
+
+    /// Endpoint of the owner when another instance holds the lock and serves IPC.
+    fn owner_endpoint(&self) -> Option<PathBuf> {
+        self.read_lock()
+            .filter(|h| h.namespace != self.0.namespace)
+            .and_then(|h| h.endpoint)
+            // 发给自己的端点会绕回本实例，永远等不到回复。
+            .filter(|endpoint| self.0.endpoint.as_ref() != Some(endpoint))
+    }
+
+    /// Where a new activity goes; `None` means the local backend.
+    fn route(&self, state: &State) -> Result<Option<PathBuf>> {
+        if state.owner {
+            return Ok(None);
+        }
+        match self.owner_endpoint() {
+            Some(endpoint) => Ok(Some(endpoint)),
+            None if state.released_at.is_some() => {
+                anyhow::bail!("activities were handed over and no instance took them yet")
+            }
+            // 锁的主人没开 IPC，或者锁目录不可写，只能自己展示。
+            None => Ok(None),
+        }
+    }
+
+    fn forwarded_id(&self, request: &CreateLiveActivityRequest) -> String {
+        format!("{}/{}", self.0.namespace, request.activity_content.id())
+    }
+}
+
+impl Backend for Coordinator {
+    fn name(&self) -> &'static str {
+        self.0.local.name()
+    }
+
+    // IPC 往返期间不拿着 state：主人处理请求时可能正好要查本实例的状态。
+    fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+        let route = self.route(&self.state())?;
+        match route {
+            Some(endpoint) => {
+                Client::connect_with_token_file(&endpoint)?
+                    .create(namespaced(&self.0.namespace, payload.clone()))?;
+                let mut state = self.state();
+                if matches!(state.presented, Presented::Local(_)) {
+                    self.0.local.remove()?;
+                }
+                state.presented = Presented::Forwarded(payload);
+            }
+            None => {
+                let mut state = self.state();
+                self.0.local.create(payload.clone())?;
+                state.presented = Presented::Local(payload);
+            }
+        }
+        Ok(())
+    }
+
+    fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+        let forwarded = {
+            let mut state = self.state();
+            match &mut state.presented {
+                Presented::Forwarded(request) => self.forwarded_id(request),
+                Presented::Local(request) => {
+                    self.0.local.update(payload.clone())?;
+                    *request.activity_content.state_mut() = payload.state;
+                    return Ok(());
+                }
+                Presented::None => return self.0.local.update(payload),
+            }
+        };
+        let endpoint = self
+            .owner_endpoint()
+            .context("no instance presents the forwarded activity")?;
+        Client::connect_with_token_file(&endpoint)?.update(UpdateLiveActivityRequest {
+            state: payload.state.clone(),
+            id: Some(forwarded.clone()),
+        })?;
+        if let Presented::Forwarded(request) = &mut self.state().presented {
+            // 期间活动可能已经换了，只记到同一个上面。
+            if self.forwarded_id(request) == forwarded {
+                *request.activity_content.state_mut() = payload.state;
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&self) -> Result<()> {
+        let presented = std::mem::replace(&mut self.state().presented, Presented::None);
+        match presented {
+            Presented::Forwarded(request) => match self.owner_endpoint() {
+                Some(endpoint) => Client::connect_with_token_file(&endpoint)?
+                    .end_activity(&self.forwarded_id(&request)),
+                // 主人已经不在了，没有东西要收。
+                None => Ok(()),
+            },
+            _ => self.0.local.remove(),
+        }
+    }
+
+    fn visible_ids(&self) -> Result<Vec<String>> {
+        match &self.state().presented {
+            Presented::Forwarded(request) => Ok(vec![request.activity_content.id().to_string()]),
+            _ => self.0.local.visible_ids(),
+        }
+    }
+
+    fn capabilities(&self) -> Result<Capabilities> {
+        self.0.local.capabilities()
+    }
+
+    fn permission_state(&self) -> Result<PermissionState> {
+        self.0.local.permission_state()
+    }
+
+    fn request_permission(&self) -> Result<PermissionState> {
+        self.0.local.request_permission()
+    }
+
+    fn alert(&self, request: AlertRequest) -> Result<()> {
+        self.0.local.alert(request)
+    }
+
+    fn system_dnd(&self) -> Option<bool> {
+        self.0.local.system_dnd()
+    }
+
+    fn diagnostics(&self, app: &AppIdentity) -> Vec<DiagnosticCheck> {
+        let mut checks = self.0.local.diagnostics(app);
+        let status = self.status();
+        checks.push(match status.owner_namespace {
+            _ if status.owner => DiagnosticCheck::new(
+                "instance",
+                CheckStatus::Ok,
+                format!("{} presents activities", status.namespace),
+            ),
+            Some(owner) if self.owner_endpoint().is_some() => DiagnosticCheck::new(
+                "instance",
+                CheckStatus::Ok,
+                format!("{} forwards activities to {owner}", status.namespace),
+            ),
+            Some(owner) => DiagnosticCheck::new(
+                "instance",
+                CheckStatus::Warning,
+                format!(
+                    "{owner} holds the lock but serves no IPC endpoint; both instances present"
+                ),
+            ),
+            None => DiagnosticCheck::new(
+                "instance",
+                CheckStatus::Warning,
+                "no instance holds the lock",
+            ),
+        });
+        checks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockBackend;
+    use crate::models::test_support::{create, task};
+
+    fn request(id: &str) -> CreateLiveActivityRequest {
+        create(task(id))
+    }
+
+    /// A fresh lock directory per test, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("live-activity-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn instance(
+        dir: &TempDir,
+        namespace: &str,
+        endpoint: Option<PathBuf>,
+    ) -> (Coordinator, Arc<MockBackend>) {
+        let backend = Arc::new(MockBackend::new());
+        let coordinator = Coordinator::new(
+            Box::new(backend.clone()),
+            dir.0.clone(),
+            namespace.to_string(),
+            endpoint,
+        );
+        (coordinator, backend)
+    }
+
+    fn write_lock(dir: &TempDir, namespace: &str, endpoint: Option<PathBuf>, refreshed_at: u64) {
+        let info = LockInfo {
+            namespace: namespace.to_string(),
+            pid: 0,
+            endpoint,
+            refreshed_at,
+        };
+        write_atomic(&dir.0.join(LOCK_FILE), &serde_json::to_vec(&info).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn stale_lock_is_taken_over() {
+        let dir = TempDir::new("takeover");
+        let (a, _) = instance(&dir, "a", None);
+        let (b, _) = instance(&dir, "b", None);
+        a.tick();
+        b.tick();
+        assert!(a.is_owner());
+        assert!(!b.is_owner());
+        assert_eq!(b.status().owner_namespace.as_deref(), Some("a"));
+
+        // a 崩了，锁停在很久以前。
+        write_lock(&dir, "a", None, 0);
+        b.tick();
+        assert!(b.is_owner());
+        assert!(a.tick(), "a learns it lost the lock");
+        assert!(!a.is_owner());
+        assert_eq!(a.status().owner_namespace.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn lock_left_by_this_namespace_is_taken_right_away() {
+        let dir = TempDir::new("own-stale");
+        // 同一命名空间的上一个进程没来得及删锁，锁还很新。
+        write_lock(&dir, "a", None, now_millis());
+        let (a, _) = instance(&dir, "a", None);
+        assert!(!a.tick());
+        assert!(a.is_owner());
+        assert_eq!(a.read_lock().unwrap().pid, std::process::id());
+    }
+
+    #[test]
+    fn clearing_a_stale_lock_puts_back_one_created_meanwhile() {
+        let dir = TempDir::new("clear-stale");
+        let (b, _) = instance(&dir, "b", None);
+        write_lock(&dir, "a", None, 0);
+        let stale = b.read_lock_text().unwrap();
+        assert!(b.is_stale(&stale));
+
+        // b 读完残留的锁之后，c 抢先删掉它建了新锁。
+        write_lock(&dir, "c", None, now_millis());
+        assert!(!b.clear_stale_lock(&stale));
+        assert_eq!(b.read_lock().unwrap().namespace, "c");
+        assert!(!b.is_stale(&b.read_lock_text().unwrap()));
+
+        write_lock(&dir, "a", None, 0);
+        assert!(b.clear_stale_lock(&stale));
+        assert!(b.read_lock_text().is_none());
+        let left: Vec<_> = std::fs::read_dir(&dir.0).unwrap().collect();
+        assert!(left.is_empty(), "no lock moved aside is left behind");
+    }
+
+    #[test]
+    fn owner_refreshes_the_lock_on_tick() {
+        let dir = TempDir::new("refresh");
+        let (a, _) = instance(&dir, "a", None);
+        a.tick();
+        write_lock(&dir, "a", None, 1);
+        assert!(!a.tick());
+        assert!(a.is_owner());
+        assert!(a.read_lock().unwrap().refreshed_at > 1);
+
+        // 锁被换成读不懂的内容也算丢了。
+        std::fs::write(a.lock_path(), b"").unwrap();
+        assert!(a.tick());
+        assert!(!a.is_owner());
+    }
+
+    #[test]
+    fn released_lock_is_left_to_the_others_for_a_while() {
+        let dir = TempDir::new("yield");
+        let (a, _) = instance(&dir, "a", None);
+        a.tick();
+        a.hand_over(Vec::new(), false).unwrap();
+        assert!(a.read_lock_text().is_none());
+
+        // 锁空着，但刚交出去的实例不去拿。
+        a.tick();
+        assert!(!a.is_owner());
+
+        let long_ago = now_millis() - LOCK_STALE.as_millis() as u64 - 1;
+        a.state().released_at = Some(long_ago);
+        a.tick();
+        assert!(a.is_owner());
+        assert_eq!(a.state().released_at, None);
+    }
+
+    #[test]
+    fn handover_moves_activities_to_the_next_owner() {
+        let dir = TempDir::new("handover");
+        let (a, backend_a) = instance(&dir, "a", None);
+        let (b, _) = instance(&dir, "b", None);
+        a.tick();
+        a.create(request("mine")).unwrap();
+        assert_eq!(backend_a.current_id().as_deref(), Some("mine"));
+
+        let remote = namespaced("c", request("theirs"));
+        a.hand_over(vec![remote], true).unwrap();
+        assert!(!a.is_owner());
+        assert_eq!(backend_a.current_id(), None);
+        // 交出去之后本实例的活动记为转发，仍然算可见。
+        assert_eq!(a.visible_ids().unwrap(), ["mine"]);
+
+        b.tick();
+        assert!(b.is_owner());
+        let adopted: Vec<_> = b
+            .take_handover()
+            .into_iter()
+            .map(|r| r.activity_content.id().to_string())
+            .collect();
+        assert_eq!(adopted, ["c/theirs", "a/mine"]);
+        assert!(b.take_handover().is_empty());
+
+        // 刚交出去的实例先不抢回来。
+        a.tick();
+        assert!(!a.is_owner());
+    }
+
+    #[test]
+    fn owner_that_lost_the_lock_hands_over() {
+        let dir = TempDir::new("surrender");
+        let (a, backend_a) = instance(&dir, "a", None);
+        let (b, _) = instance(&dir, "b", None);
+        a.tick();
+        a.create(request("mine")).unwrap();
+
+        // a 卡住太久，b 把锁当成残留接走了。
+        write_lock(&dir, "a", None, 0);
+        b.tick();
+        assert!(b.is_owner());
+        assert!(a.tick());
+        a.surrender(Vec::new()).unwrap();
+        assert_eq!(backend_a.current_id(), None);
+        // 新主人的锁还在。
+        assert_eq!(a.status().owner_namespace.as_deref(), Some("b"));
+        let adopted: Vec<_> = b
+            .take_handover()
+            .into_iter()
+            .map(|r| r.activity_content.id().to_string())
+            .collect();
+        assert_eq!(adopted, ["a/mine"]);
+    }
+
+    #[test]
+    fn racing_instances_take_a_free_lock_once() {
+        let dir = TempDir::new("race");
+        let (a, _) = instance(&dir, "a", None);
+        let (b, _) = instance(&dir, "b", None);
+        // 两个都看到锁空着；后建的那个拿不到。
+        a.create_lock().unwrap();
+        let err = b.create_lock().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(b.status().owner_namespace.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn owner_without_endpoint_leaves_presenting_local() {
+        let dir = TempDir::new("routing-local");
+        let (a, _) = instance(&dir, "a", None);
+        let (b, backend_b) = instance(&dir, "b", Some(dir.0.join("b.sock")));
+        a.tick();
+        b.tick();
+        b.create(request("job")).unwrap();
+        assert_eq!(backend_b.current_id().as_deref(), Some("job"));
+    }
+
+    #[test]
+    fn never_forwards_to_its_own_endpoint() {
+        let dir = TempDir::new("routing-self");
+        let own = dir.0.join("b.sock");
+        let (b, backend_b) = instance(&dir, "b", Some(own.clone()));
+        // 另一个实例的锁里写着本实例的端点，比如共用端点时留下的。
+        write_lock(&dir, "a", Some(own), now_millis());
+        b.tick();
+        assert!(!b.is_owner());
+        b.create(request("job")).unwrap();
+        assert_eq!(backend_b.current_id().as_deref(), Some("job"));
+    }
+
+    #[test]
+    fn forwards_to_the_owner_endpoint() {
+        let dir = TempDir::new("routing-owner");
+        let (b, backend_b) = instance(&dir, "b", Some(dir.0.join("b.sock")));
+        write_lock(&dir, "a", Some(dir.0.join("a.sock")), now_millis());
+        b.tick();
+        // 没人在 a 的端点上听，转发失败，也不会退回本地展示。
+        assert!(b.create(request("job")).is_err());
+        assert_eq!(backend_b.current_id(), None);
+        assert!(backend_b.calls().is_empty());
+    }
+}
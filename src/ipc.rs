@@ -0,0 +1,481 @@
+//! Local endpoint that lets other processes drive the live activity.
+//!
+//! The protocol is line-delimited JSON: every [`IpcRequest`] line is answered
+//! with one [`IpcResponse`] line. The endpoint is a Unix domain socket, or a
+//! named pipe on Windows; requests must carry the token the server writes to
+//! [`token_path`] when it starts, so only processes of the same user that can
+//! read that file get through.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::models::*;
+
+#[cfg(unix)]
+type Stream = std::os::unix::net::UnixStream;
+#[cfg(windows)]
+type Stream = std::fs::File;
+
+/// Endpoint used when the app does not pick one.
+pub fn default_endpoint() -> PathBuf {
+    #[cfg(windows)]
+    {
+        PathBuf::from(r"\\.\pipe\live-activity")
+    }
+    #[cfg(not(windows))]
+    {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir)
+            .join("live-activity.sock")
+    }
+}
+
+/// Endpoint of the instance `namespace` of an app serving `endpoint`.
+///
+/// Every coordinated instance serves one of its own, so that the owner is
+/// reached by its endpoint rather than by whichever process bound the shared one.
+pub fn instance_endpoint(endpoint: &Path, namespace: &str) -> PathBuf {
+    // 命名空间是调用方给的，可能带路径分隔符。
+    let namespace: String = namespace
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let stem = endpoint
+        .file_stem()
+        .and_then(|n| n.to_str())
+        .unwrap_or("live-activity");
+    let name = match endpoint.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{stem}.{namespace}.{ext}"),
+        None => format!("{stem}.{namespace}"),
+    };
+    endpoint.with_file_name(name)
+}
+
+/// File holding the token clients have to present to `endpoint`.
+pub fn token_path(endpoint: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        // 命名管道不在文件系统里，令牌放到用户自己的临时目录。
+        let name = endpoint
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("live-activity");
+        std::env::temp_dir().join(format!("{name}.token"))
+    }
+    #[cfg(not(windows))]
+    {
+        endpoint.with_extension("token")
+    }
+}
+
+/// Blocking client for the IPC endpoint, used by the `live-activity` CLI.
+pub struct Client {
+    reader: BufReader<Stream>,
+    writer: Stream,
+    token: String,
+}
+
+impl Client {
+    pub fn connect(endpoint: &Path, token: impl Into<String>) -> anyhow::Result<Self> {
+        #[cfg(unix)]
+        let stream = Stream::connect(endpoint);
+        #[cfg(windows)]
+        let stream = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(endpoint);
+        let stream = stream.with_context(|| format!("connect to {}", endpoint.display()))?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            token: token.into(),
+        })
+    }
+
+    /// Connects with the token the server wrote next to `endpoint`.
+    pub fn connect_with_token_file(endpoint: &Path) -> anyhow::Result<Self> {
+        let path = token_path(endpoint);
+        let token = std::fs::read_to_string(&path)
+            .with_context(|| format!("read token from {}", path.display()))?;
+        Self::connect(endpoint, token.trim())
+    }
+
+    pub fn send(&mut self, call: RecordedCall) -> anyhow::Result<()> {
+        let request = IpcRequest {
+            token: self.token.clone(),
+            call,
+        };
+        let mut line = serde_json::to_string(&request)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.flush()?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            anyhow::bail!("connection closed by the server");
+        }
+        let response: IpcResponse = serde_json::from_str(&line)?;
+        match response.error {
+            Some(err) if !response.ok => anyhow::bail!(err),
+            _ if !response.ok => anyhow::bail!("request failed"),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn create(&mut self, payload: CreateLiveActivityRequest) -> anyhow::Result<()> {
+        self.send(RecordedCall::Create(payload))
+    }
+
+    pub fn update(&mut self, payload: UpdateLiveActivityRequest) -> anyhow::Result<()> {
+        self.send(RecordedCall::Update(payload))
+    }
+
+    pub fn end(&mut self) -> anyhow::Result<()> {
+        self.send(RecordedCall::Remove)
+    }
+
+    pub fn end_activity(&mut self, id: &str) -> anyhow::Result<()> {
+        self.send(RecordedCall::End { id: id.to_string() })
+    }
+}
+
+#[cfg(desktop)]
+pub(crate) use server::spawn_server;
+
+#[cfg(desktop)]
+mod server {
+    use std::path::{Path, PathBuf};
+
+    use anyhow::Context;
+    use tauri::{AppHandle, Runtime};
+    use tokio::io::{
+        AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+        BufReader,
+    };
+
+    use super::token_path;
+    use crate::models::*;
+    use crate::LiveActivityExt;
+
+    /// Longest request line the server reads; longer ones close the connection.
+    pub(super) const MAX_REQUEST_LINE: usize = 64 * 1024;
+
+    /// Serves `endpoint` on the async runtime for as long as the app runs.
+    pub(crate) fn spawn_server<R: Runtime>(app: AppHandle<R>, endpoint: PathBuf) {
+        tauri::async_runtime::spawn(async move {
+            if let Err(err) = serve(app, &endpoint).await {
+                tracing::warn!(error = %err, endpoint = %endpoint.display(), "ipc server stopped");
+            }
+        });
+    }
+
+    pub(super) fn write_token(endpoint: &Path) -> anyhow::Result<String> {
+        let mut bytes = [0u8; 32];
+        getrandom::fill(&mut bytes).map_err(|err| anyhow::anyhow!("generate token: {err}"))?;
+        let token: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+
+        let path = token_path(endpoint);
+        // 旧文件可能是别人放的、权限不对，或者是指向别处的符号链接：删掉重建。
+        // create_new 在路径已存在（包括符号链接）时直接失败，不会顺着链接写。
+        match std::fs::remove_file(&path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                return Err(err).with_context(|| format!("remove {}", path.display()));
+            }
+            _ => {}
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(&path)
+            .with_context(|| format!("create {}", path.display()))?;
+        std::io::Write::write_all(&mut file, token.as_bytes())?;
+        Ok(token)
+    }
+
+    /// Binds `endpoint`, replacing a socket file only when nobody listens on it.
+    #[cfg(unix)]
+    pub(super) fn bind(endpoint: &Path) -> anyhow::Result<std::os::unix::net::UnixListener> {
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::net::{UnixListener, UnixStream};
+
+        if UnixStream::connect(endpoint).is_ok() {
+            anyhow::bail!("another process serves {}", endpoint.display());
+        }
+        // 上次异常退出会留下 socket 文件，不删掉 bind 会失败。
+        let _ = std::fs::remove_file(endpoint);
+        let listener = UnixListener::bind(endpoint)?;
+        std::fs::set_permissions(endpoint, std::fs::Permissions::from_mode(0o600))?;
+        listener.set_nonblocking(true)?;
+        Ok(listener)
+    }
+
+    #[cfg(unix)]
+    async fn serve<R: Runtime>(app: AppHandle<R>, endpoint: &Path) -> anyhow::Result<()> {
+        let listener = tokio::net::UnixListener::from_std(bind(endpoint)?)?;
+        let token = write_token(endpoint)?;
+        tracing::info!(endpoint = %endpoint.display(), "ipc server listening");
+        loop {
+            let (stream, _) = listener.accept().await?;
+            tauri::async_runtime::spawn(handle(app.clone(), token.clone(), stream));
+        }
+    }
+
+    #[cfg(windows)]
+    async fn serve<R: Runtime>(app: AppHandle<R>, endpoint: &Path) -> anyhow::Result<()> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let mut server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .reject_remote_clients(true)
+            .create(endpoint)?;
+        let token = write_token(endpoint)?;
+        tracing::info!(endpoint = %endpoint.display(), "ipc server listening");
+        loop {
+            server.connect().await?;
+            // 先开好下一个实例再处理当前连接，避免客户端撞上 ERROR_PIPE_BUSY。
+            let connected = server;
+            server = ServerOptions::new()
+                .reject_remote_clients(true)
+                .create(endpoint)?;
+            tauri::async_runtime::spawn(handle(app.clone(), token.clone(), connected));
+        }
+    }
+
+    async fn handle<R: Runtime, S: AsyncRead + AsyncWrite + Unpin>(
+        app: AppHandle<R>,
+        token: String,
+        stream: S,
+    ) {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(reader);
+        loop {
+            let (response, open) = match read_request(&mut reader).await {
+                Ok(Some(line)) if line.trim().is_empty() => continue,
+                Ok(Some(line)) => {
+                    let response = match dispatch(&app, &token, &line).await {
+                        Ok(()) => IpcResponse {
+                            ok: true,
+                            error: None,
+                        },
+                        Err(err) => IpcResponse {
+                            ok: false,
+                            error: Some(format!("{err:#}")),
+                        },
+                    };
+                    (response, true)
+                }
+                Ok(None) => return,
+                Err(err) if err.kind() == std::io::ErrorKind::InvalidData => {
+                    tracing::warn!(error = %err, "ipc request rejected");
+                    let response = IpcResponse {
+                        ok: false,
+                        error: Some(err.to_string()),
+                    };
+                    (response, false)
+                }
+                Err(err) => {
+                    tracing::debug!(error = %err, "ipc connection failed");
+                    return;
+                }
+            };
+            let mut out = serde_json::to_string(&response).unwrap_or_default();
+            out.push('\n');
+            if writer.write_all(out.as_bytes()).await.is_err() {
+                return;
+            }
+            // 超长的请求后面剩下的内容没法再按行对齐，直接断开。
+            if !open {
+                return;
+            }
+        }
+    }
+
+    /// Reads the next request line; `None` once the client hung up. A line
+    /// over [`MAX_REQUEST_LINE`] bytes fails with `InvalidData`.
+    pub(super) async fn read_request<B: AsyncBufRead + Unpin>(
+        reader: &mut B,
+    ) -> std::io::Result<Option<String>> {
+        let mut line = Vec::new();
+        let read = reader
+            .take(MAX_REQUEST_LINE as u64 + 1)
+            .read_until(b'\n', &mut line)
+            .await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if line.len() > MAX_REQUEST_LINE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("request longer than {MAX_REQUEST_LINE} bytes"),
+            ));
+        }
+        String::from_utf8(line)
+            .map(Some)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    async fn dispatch<R: Runtime>(
+        app: &AppHandle<R>,
+        token: &str,
+        line: &str,
+    ) -> anyhow::Result<()> {
+        let call = parse_request(token, line)?;
+        // 后端调用是阻塞的（比如 macOS 申请权限会等用户点），不能占着 IO 线程。
+        let app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || app.live_activity().apply(call)).await?
+    }
+
+    /// The call in a request line, provided it carries `token`.
+    pub(super) fn parse_request(token: &str, line: &str) -> anyhow::Result<RecordedCall> {
+        let request: IpcRequest = serde_json::from_str(line).context("malformed request")?;
+        if !token_matches(&request.token, token) {
+            tracing::warn!("ipc request rejected: bad token");
+            anyhow::bail!("invalid token");
+        }
+        Ok(request.call)
+    }
+
+    fn token_matches(given: &str, expected: &str) -> bool {
+        given.len() == expected.len()
+            && given
+                .bytes()
+                .zip(expected.bytes())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_endpoints_are_distinct() {
+        let endpoint = Path::new("/run/user/1000/live-activity.sock");
+        let a = instance_endpoint(endpoint, "1234");
+        assert_eq!(a, Path::new("/run/user/1000/live-activity.1234.sock"));
+        assert_ne!(a, instance_endpoint(endpoint, "5678"));
+        assert_eq!(
+            instance_endpoint(endpoint, "../editor"),
+            Path::new("/run/user/1000/live-activity.___editor.sock")
+        );
+    }
+
+    #[cfg(all(unix, desktop))]
+    #[test]
+    fn bind_leaves_a_live_socket_alone() {
+        let dir = std::env::temp_dir().join(format!("live-activity-ipc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let endpoint = dir.join("live-activity.sock");
+        let _ = std::fs::remove_file(&endpoint);
+
+        let first = server::bind(&endpoint).unwrap();
+        assert!(server::bind(&endpoint).is_err());
+        assert!(Stream::connect(&endpoint).is_ok());
+
+        // 主人没了，留下的 socket 文件可以接手。
+        drop(first);
+        assert!(server::bind(&endpoint).is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(desktop)]
+    fn request_line(token: &str, call: RecordedCall) -> String {
+        serde_json::to_string(&IpcRequest {
+            token: token.to_string(),
+            call,
+        })
+        .unwrap()
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn requests_need_the_token() {
+        let line = request_line("secret", RecordedCall::End { id: "a".into() });
+        assert!(matches!(
+            server::parse_request("secret", &line),
+            Ok(RecordedCall::End { id }) if id == "a"
+        ));
+        let err = server::parse_request("other!", &line).unwrap_err();
+        assert_eq!(err.to_string(), "invalid token");
+        // 长度不同也只是拒绝，不会越界比较。
+        assert!(server::parse_request("secret-but-longer", &line).is_err());
+        assert!(server::parse_request("secret", "{\"token\":").is_err());
+    }
+
+    #[cfg(desktop)]
+    #[test]
+    fn request_lines_are_read_one_by_one_and_capped() {
+        let mut input = request_line("t", RecordedCall::Remove);
+        input.push('\n');
+        input.push_str(&request_line("t", RecordedCall::End { id: "a".into() }));
+        let mut reader = tokio::io::BufReader::new(input.as_bytes());
+        tauri::async_runtime::block_on(async {
+            let first = server::read_request(&mut reader).await.unwrap().unwrap();
+            assert!(matches!(
+                server::parse_request("t", &first),
+                Ok(RecordedCall::Remove)
+            ));
+            // 最后一行没有换行符也照样读出来。
+            let second = server::read_request(&mut reader).await.unwrap().unwrap();
+            assert!(matches!(
+                server::parse_request("t", &second),
+                Ok(RecordedCall::End { .. })
+            ));
+            assert!(server::read_request(&mut reader).await.unwrap().is_none());
+
+            let long = "x".repeat(server::MAX_REQUEST_LINE + 10);
+            let mut reader = tokio::io::BufReader::new(long.as_bytes());
+            let err = server::read_request(&mut reader).await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        });
+    }
+
+    #[cfg(all(unix, desktop))]
+    #[test]
+    fn token_file_is_private_and_replaced() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("live-activity-token-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let endpoint = dir.join("live-activity.sock");
+        let path = token_path(&endpoint);
+
+        // 别人事先放好一个谁都能读的文件。
+        std::fs::write(&path, "planted").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let token = server::write_token(&endpoint).unwrap();
+        assert_eq!(token.len(), 64);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), token);
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // 符号链接只被删掉，指向的文件不受影响。
+        let target = dir.join("target");
+        std::fs::write(&target, "untouched").unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+        server::write_token(&endpoint).unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "untouched");
+        assert!(!std::fs::symlink_metadata(&path)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,6 +1,11 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
 use tauri::{
-    plugin::{Builder, TauriPlugin},
-    Manager, Runtime,
+    plugin::{Builder as PluginBuilder, TauriPlugin},
+    webview::PageLoadEvent,
+    Manager, RunEvent, Runtime, WindowEvent,
 };
 
 pub use models::*;
@@ -10,15 +15,53 @@ mod desktop;
 #[cfg(mobile)]
 mod mobile;
 
+mod activity;
+mod backend;
+mod batch;
+mod chain;
+mod clock;
+mod commands;
+#[cfg(any(test, feature = "conformance"))]
+pub mod conformance;
 mod error;
+mod fs;
+#[cfg(feature = "history")]
+mod history;
+mod in_app;
+mod inhibit;
+#[cfg(feature = "ipc")]
+mod instance;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+mod metrics;
+#[cfg(any(test, feature = "mock"))]
+#[cfg_attr(not(feature = "mock"), allow(dead_code))]
+mod mock;
 mod models;
+mod owner;
+mod power;
+mod preferences;
+mod quiet;
+mod recorder;
+mod registry;
+mod retry;
+mod schedule;
+mod sink;
+#[cfg(desktop)]
+mod taskbar;
+mod telemetry;
+mod throttle;
 
+pub use backend::Backend;
+pub use clock::{Clock, ManualClock, SystemClock};
 pub use error::{Error, Result};
+#[cfg(feature = "mock")]
+pub use mock::MockBackend;
+pub use owner::ActivityGuard;
+pub use power::{ManualPower, PowerSource, SystemPower};
+pub use sink::ActivitySink;
 
-#[cfg(desktop)]
-use desktop::LiveActivity;
-#[cfg(mobile)]
-use mobile::LiveActivity;
+use activity::LiveActivity;
 
 pub trait LiveActivityExt<R: Runtime> {
     fn live_activity(&self) -> &LiveActivity<R>;
@@ -30,16 +73,275 @@ impl<R: Runtime, T: Manager<R>> crate::LiveActivityExt<R> for T {
     }
 }
 
+/// Options fixed at setup time, collected by [`Builder`].
+#[derive(Default)]
+pub(crate) struct PluginOptions {
+    prometheus: Option<metrics::PrometheusExport>,
+    record_sessions: Option<PathBuf>,
+    backend: Option<Box<dyn Backend>>,
+    fallbacks: Vec<Box<dyn Backend>>,
+    in_app: Option<Arc<in_app::InAppFeed>>,
+    #[cfg(desktop)]
+    taskbar_fallback: bool,
+    sinks: Vec<Box<dyn ActivitySink>>,
+    quiet_hours: Option<QuietHours>,
+    batch_window: Option<Duration>,
+    stalled_hint: Option<String>,
+    retry: RetryPolicies,
+    clock: Option<Arc<dyn Clock>>,
+    update_throttle: UpdateThrottle,
+    power: Option<Arc<dyn PowerSource>>,
+    #[cfg(feature = "ipc")]
+    instances: Option<InstanceOptions>,
+    #[cfg(feature = "history")]
+    history: Option<usize>,
+    #[cfg(all(desktop, feature = "ipc"))]
+    ipc_endpoint: Option<PathBuf>,
+}
+
+#[cfg(feature = "ipc")]
+#[derive(Default)]
+pub(crate) struct InstanceOptions {
+    namespace: Option<String>,
+    /// IPC endpoint advertised to the other instances.
+    endpoint: Option<PathBuf>,
+}
+
+/// Configures the plugin before it is registered with the app.
+#[derive(Default)]
+pub struct Builder {
+    options: PluginOptions,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Periodically writes the plugin metrics to `path` in the Prometheus text format.
+    pub fn prometheus_export(mut self, path: impl Into<PathBuf>, interval: Duration) -> Self {
+        self.options.prometheus = Some(metrics::PrometheusExport {
+            path: path.into(),
+            interval,
+        });
+        self
+    }
+
+    /// Appends every create/update/remove call with its outcome to a JSONL file at `path`.
+    ///
+    /// The file can be fed back with `LiveActivity::replay_session`.
+    pub fn record_sessions(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.record_sessions = Some(path.into());
+        self
+    }
+
+    /// Presents activities through `backend` instead of the platform one, e.g. [`MockBackend`].
+    pub fn backend(mut self, backend: impl Backend + 'static) -> Self {
+        self.options.backend = Some(Box::new(backend));
+        self
+    }
+
+    /// Appends `presenter` to the fallback chain.
+    ///
+    /// When the platform backend is denied or fails, the activity moves to the
+    /// next presenter in the order they were added.
+    pub fn fallback(mut self, presenter: impl Backend + 'static) -> Self {
+        self.options.fallbacks.push(Box::new(presenter));
+        self
+    }
+
+    /// Appends a presenter that draws nothing itself but streams each activity
+    /// to the frontend as an [`InAppView`].
+    ///
+    /// Views are emitted as the `live-activity://in-app` event and sent to the
+    /// channels passed to the `watch_in_app` command, so the app can show its
+    /// own banner when OS notifications are denied or unavailable. Comes after
+    /// the other fallbacks and before [`Builder::taskbar_fallback`].
+    pub fn in_app_fallback(mut self) -> Self {
+        self.options.in_app.get_or_insert_with(Default::default);
+        self
+    }
+
+    /// Ends the fallback chain with the window's taskbar/dock progress bar.
+    #[cfg(desktop)]
+    pub fn taskbar_fallback(mut self) -> Self {
+        self.options.taskbar_fallback = true;
+        self
+    }
+
+    /// Starts with `policy` as the quiet-hours policy; it can be changed at runtime.
+    pub fn quiet_hours(mut self, policy: QuietHours) -> Self {
+        self.options.quiet_hours = Some(policy);
+        self
+    }
+
+    /// How long a `group` waits for more finished activities before its
+    /// completion summary is posted; five seconds by default.
+    pub fn completion_batch_window(mut self, window: Duration) -> Self {
+        self.options.batch_window = Some(window);
+        self
+    }
+
+    /// Text shown as the `hint` of an activity that went its `stall_after`
+    /// without an update; "Waiting…" by default.
+    pub fn stalled_hint(mut self, hint: impl Into<String>) -> Self {
+        self.options.stalled_hint = Some(hint.into());
+        self
+    }
+
+    /// Retries transient backend failures per operation; by default up to three
+    /// attempts on timeouts, busy services and lost connections.
+    pub fn retry_policies(mut self, policies: RetryPolicies) -> Self {
+        self.options.retry = policies;
+        self
+    }
+
+    /// Replaces the wall clock for everything the plugin schedules.
+    ///
+    /// The clock is process-wide. Pass an `Arc<ManualClock>` and keep a clone
+    /// to move time forward from tests.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.options.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// How far apart updates reach the OS on AC, on battery and in power-saver
+    /// mode; see [`UpdateThrottle`] for the defaults.
+    pub fn update_throttle(mut self, policy: UpdateThrottle) -> Self {
+        self.options.update_throttle = policy;
+        self
+    }
+
+    /// Replaces [`SystemPower`] as the source of the power state, e.g. with a
+    /// [`ManualPower`] in tests.
+    pub fn power_source(mut self, source: impl PowerSource + 'static) -> Self {
+        self.options.power = Some(Arc::new(source));
+        self
+    }
+
+    /// Lets only one process of the app present activities at a time.
+    ///
+    /// The process holding the lock in the app runtime dir presents; the others
+    /// forward their activities to it, which needs [`Builder::ipc_server`] on
+    /// the owner. When the owner exits, the activities it shows for the others
+    /// move to the next one.
+    #[cfg(feature = "ipc")]
+    pub fn coordinate_instances(mut self) -> Self {
+        self.options.instances.get_or_insert_with(Default::default);
+        self
+    }
+
+    /// Prefix for this process's activity ids in the owning instance; the
+    /// process id by default. Turns on [`Builder::coordinate_instances`].
+    #[cfg(feature = "ipc")]
+    pub fn instance_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.options
+            .instances
+            .get_or_insert_with(Default::default)
+            .namespace = Some(namespace.into());
+        self
+    }
+
+    /// Keeps the last `limit` ended activities with their outcome, duration and
+    /// peak progress in the app data dir, for `LiveActivity::history`.
+    #[cfg(feature = "history")]
+    pub fn history(mut self, limit: usize) -> Self {
+        self.options.history = Some(limit);
+        self
+    }
+
+    /// Mirrors every create/update/end event to `sink`; can be called repeatedly.
+    pub fn sink(mut self, sink: impl ActivitySink + 'static) -> Self {
+        self.options.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Accepts create/update/end requests from other local processes on `endpoint`.
+    ///
+    /// See [`ipc`] for the protocol; [`ipc::default_endpoint`] is where the
+    /// `live-activity` CLI looks by default. Only one process can serve an
+    /// endpoint; with [`Builder::coordinate_instances`] every instance also
+    /// serves its own [`ipc::instance_endpoint`] for the others to forward to.
+    #[cfg(all(desktop, feature = "ipc"))]
+    pub fn ipc_server(mut self, endpoint: impl Into<PathBuf>) -> Self {
+        self.options.ipc_endpoint = Some(endpoint.into());
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        #[allow(unused_mut)]
+        let mut options = self.options;
+        #[cfg(all(desktop, feature = "ipc"))]
+        let ipc_endpoint = options.ipc_endpoint.take();
+        #[cfg(all(desktop, feature = "ipc"))]
+        if let Some(instances) = options.instances.as_mut() {
+            instances.endpoint = ipc_endpoint.clone();
+        }
+        PluginBuilder::new("live-activity")
+            .invoke_handler(tauri::generate_handler![
+                commands::list_activities,
+                commands::get_activity,
+                commands::capabilities,
+                commands::permission_state,
+                commands::request_permission,
+                commands::metrics,
+                commands::replay_session,
+                commands::diagnose,
+                commands::quiet_status,
+                commands::set_quiet_hours,
+                commands::get_preferences,
+                commands::set_preference,
+                commands::reset_preference,
+                commands::heartbeat,
+                commands::instance_status,
+                commands::hand_over,
+                commands::history,
+                commands::history_stats,
+                commands::clear_history,
+                commands::watch_in_app
+            ])
+            .on_page_load(|webview, payload| {
+                // 页面重新加载后前端状态全丢了，它名下的活动也就没人管了。
+                if payload.event() == PageLoadEvent::Started {
+                    if let Some(live_activity) = webview.try_state::<LiveActivity<R>>() {
+                        live_activity.owner_gone(webview.label());
+                    }
+                }
+            })
+            .on_event(|app, event| {
+                let Some(live_activity) = app.try_state::<LiveActivity<R>>() else {
+                    return;
+                };
+                match event {
+                    RunEvent::WindowEvent {
+                        label,
+                        event: WindowEvent::Destroyed,
+                        ..
+                    } => live_activity.owner_gone(label),
+                    RunEvent::Exit => {
+                        live_activity.leave_instances();
+                        live_activity.release_sleep();
+                    }
+                    _ => {}
+                }
+            })
+            .setup(move |app, api| {
+                #[cfg(mobile)]
+                let live_activity = mobile::init(app, api, options)?;
+                #[cfg(desktop)]
+                let live_activity = desktop::init(app, api, options)?;
+                app.manage(live_activity);
+                #[cfg(all(desktop, feature = "ipc"))]
+                if let Some(endpoint) = ipc_endpoint {
+                    ipc::spawn_server(app.clone(), endpoint);
+                }
+                Ok(())
+            })
+            .build()
+    }
+}
+
 /// Initializes the plugin.
 pub fn init<R: Runtime>() -> TauriPlugin<R> {
-    Builder::new("live-activity")
-        .setup(|app, api| {
-            #[cfg(mobile)]
-            let live_activity = mobile::init(app, api)?;
-            #[cfg(desktop)]
-            let live_activity = desktop::init(app, api)?;
-            app.manage(live_activity);
-            Ok(())
-        })
-        .build()
+    Builder::new().build()
 }
@@ -1,11 +1,39 @@
 pub mod core {
+    use crate::backend::Backend;
     use crate::models::*;
     use anyhow::Result;
+    #[cfg(feature = "linux-dbus")]
+    use dbus::arg::{PropMap, Variant};
+    #[cfg(feature = "linux-dbus")]
+    use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+    #[cfg(feature = "linux-dbus")]
+    use dbus::blocking::Connection;
+    #[cfg(feature = "linux-dbus")]
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::{Mutex, OnceLock};
+    #[cfg(feature = "linux-dbus")]
+    use std::time::Duration;
+
+    pub const BACKEND: &str = "linux-stub";
+
+    // 没有任务追踪器时暂无展示通道，只记住当前活动，让调用语义和其他平台保持一致。
+    static CURRENT_TAG: OnceLock<Mutex<Option<String>>> = OnceLock::new();
+
+    fn current_tag() -> &'static Mutex<Option<String>> {
+        CURRENT_TAG.get_or_init(|| Mutex::new(None))
+    }
+
     //返回ok防止爆炸
     pub fn create_live_activity(
         _self: &impl Sized,
-        _payload: CreateLiveActivityRequest,
+        payload: CreateLiveActivityRequest,
     ) -> Result<()> {
+        #[cfg(feature = "kde-jobs")]
+        if kde::available() {
+            kde::show(&payload.activity_content)?;
+        }
+        *current_tag().lock().unwrap() = Some(payload.activity_content.id().to_string());
         Ok(())
     }
 
@@ -13,10 +41,373 @@ pub mod core {
         _self: &impl Sized,
         _payload: UpdateLiveActivityRequest,
     ) -> Result<()> {
+        if current_tag().lock().unwrap().is_none() {
+            corelib::bail_site!("No active live activity to update");
+        }
+        #[cfg(feature = "kde-jobs")]
+        kde::update(&_payload.state)?;
         Ok(())
     }
 
     pub fn remove_live_activity(_self: &impl Sized) -> Result<()> {
+        #[cfg(feature = "kde-jobs")]
+        kde::finish();
+        *current_tag().lock().unwrap() = None;
+        Ok(())
+    }
+
+    pub fn permission_state(_self: &impl Sized) -> PermissionState {
+        PermissionState::Granted
+    }
+
+    pub fn request_permission(_self: &impl Sized) -> PermissionState {
+        PermissionState::Granted
+    }
+
+    pub fn capabilities(_self: &impl Sized) -> Capabilities {
+        #[cfg(feature = "kde-jobs")]
+        if kde::available() {
+            return Capabilities {
+                backend: BACKEND.to_string(),
+                presents: true,
+                progress_bar: true,
+                updates_in_place: true,
+                max_concurrent: 1,
+                ..Default::default()
+            };
+        }
+        Capabilities {
+            backend: BACKEND.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn visible_ids(_self: &impl Sized) -> Vec<String> {
+        #[cfg(feature = "kde-jobs")]
+        if kde::showing() {
+            return current_tag().lock().unwrap().iter().cloned().collect();
+        }
+        Vec::new()
+    }
+
+    /// The current activity as a job in the Plasma job tracker.
+    #[cfg(feature = "kde-jobs")]
+    mod kde {
+        use std::collections::HashMap;
+        use std::sync::{Mutex, OnceLock};
+        use std::time::Duration;
+
+        use anyhow::Result;
+        use dbus::arg::AppendAll;
+        use dbus::blocking::Connection;
+
+        use crate::models::*;
+
+        const SERVICE: &str = "org.kde.kuiserver";
+        const SERVER_PATH: &str = "/JobViewServer";
+        const SERVER: &str = "org.kde.JobViewServer";
+        const VIEW: &str = "org.kde.JobViewV2";
+        const TIMEOUT: Duration = Duration::from_secs(2);
+
+        /// A job view and the connection that requested it; the tracker drops
+        /// the job once that connection closes.
+        struct Job {
+            conn: Connection,
+            path: dbus::Path<'static>,
+        }
+
+        impl Job {
+            fn call<A: AppendAll>(&self, method: &str, args: A) -> Result<()> {
+                self.conn
+                    .with_proxy(SERVICE, &self.path, TIMEOUT)
+                    .method_call::<(), _, _, _>(VIEW, method, args)?;
+                Ok(())
+            }
+
+            fn set_progress(&self, state: &HashMap<String, String>) -> Result<()> {
+                if let Some(progress) = parse_progress(state) {
+                    self.call("setPercent", ((progress * 100.0).round() as u32,))?;
+                }
+                Ok(())
+            }
+        }
+
+        static JOB: OnceLock<Mutex<Option<Job>>> = OnceLock::new();
+
+        fn job() -> &'static Mutex<Option<Job>> {
+            JOB.get_or_init(|| Mutex::new(None))
+        }
+
+        /// Whether a job tracker runs on the session bus.
+        pub fn available() -> bool {
+            let Ok(conn) = Connection::new_session() else {
+                return false;
+            };
+            conn.with_proxy("org.freedesktop.DBus", "/org/freedesktop/DBus", TIMEOUT)
+                .method_call("org.freedesktop.DBus", "NameHasOwner", (SERVICE,))
+                .is_ok_and(|(owned,): (bool,)| owned)
+        }
+
+        pub fn showing() -> bool {
+            job().lock().unwrap().is_some()
+        }
+
+        pub fn show(content: &ActivityContent) -> Result<()> {
+            finish();
+            let ActivityContent::TaskQueue(task) = content;
+            let conn = Connection::new_session()?;
+            let (path,): (dbus::Path<'static>,) =
+                conn.with_proxy(SERVICE, SERVER_PATH, TIMEOUT).method_call(
+                    SERVER,
+                    "requestView",
+                    (task.task_name.as_str(), task.task_icon.as_str(), 0i32),
+                )?;
+            let job_view = Job { conn, path };
+            job_view.call("setInfoMessage", (task.title.as_str(),))?;
+            if !task.text.is_empty() {
+                job_view.call(
+                    "setDescriptionField",
+                    (0u32, task.task_type.as_str(), task.text.as_str()),
+                )?;
+            }
+            job_view.set_progress(&task.state)?;
+            *job().lock().unwrap() = Some(job_view);
+            Ok(())
+        }
+
+        pub fn update(state: &HashMap<String, String>) -> Result<()> {
+            match job().lock().unwrap().as_ref() {
+                Some(job_view) => job_view.set_progress(state),
+                None => Ok(()),
+            }
+        }
+
+        pub fn finish() {
+            let Some(job_view) = job().lock().unwrap().take() else {
+                return;
+            };
+            if let Err(err) = job_view.call("terminate", ("",)) {
+                tracing::debug!(error = %err, "failed to end the job view");
+            }
+        }
+    }
+
+    #[cfg(feature = "linux-dbus")]
+    const NOTIFICATIONS_NAME: &str = "org.freedesktop.Notifications";
+    #[cfg(feature = "linux-dbus")]
+    const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+    fn data_dirs() -> Vec<PathBuf> {
+        let home = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")));
+        let system = std::env::var("XDG_DATA_DIRS")
+            .ok()
+            .filter(|dirs| !dirs.is_empty())
+            .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+        home.into_iter()
+            .chain(system.split(':').map(PathBuf::from))
+            .collect()
+    }
+
+    fn find_desktop_entry(app: &AppIdentity) -> Option<PathBuf> {
+        let names = [
+            format!("{}.desktop", app.identifier),
+            format!("{}.desktop", app.name),
+        ];
+        data_dirs().into_iter().find_map(|dir| {
+            names
+                .iter()
+                .map(|name| dir.join("applications").join(name))
+                .find(|path| path.is_file())
+        })
+    }
+
+    /// Name, vendor and version of the running notification server, plus its capabilities.
+    #[cfg(feature = "linux-dbus")]
+    fn notification_server() -> Result<(String, Vec<String>)> {
+        let conn = Connection::new_session()?;
+        let proxy = conn.with_proxy(
+            NOTIFICATIONS_NAME,
+            NOTIFICATIONS_PATH,
+            Duration::from_secs(2),
+        );
+        let (name, vendor, version, _spec): (String, String, String, String) =
+            proxy.method_call(NOTIFICATIONS_NAME, "GetServerInformation", ())?;
+        let (caps,): (Vec<String>,) =
+            proxy.method_call(NOTIFICATIONS_NAME, "GetCapabilities", ())?;
+        Ok((format!("{name} {version} ({vendor})"), caps))
+    }
+
+    /// `Inhibited` from the notification spec 1.3, set by servers such as Plasma in DND mode.
+    #[cfg(feature = "linux-dbus")]
+    fn notifications_inhibited() -> Result<bool> {
+        let conn = Connection::new_session()?;
+        let proxy = conn.with_proxy(
+            NOTIFICATIONS_NAME,
+            NOTIFICATIONS_PATH,
+            Duration::from_secs(2),
+        );
+        Ok(proxy.get(NOTIFICATIONS_NAME, "Inhibited")?)
+    }
+
+    /// Notification ids handed out by the server, per alert group, so a new
+    /// summary replaces the previous one through `replaces_id`.
+    #[cfg(feature = "linux-dbus")]
+    static GROUP_IDS: OnceLock<Mutex<HashMap<String, u32>>> = OnceLock::new();
+
+    #[cfg(feature = "linux-dbus")]
+    fn group_ids() -> &'static Mutex<HashMap<String, u32>> {
+        GROUP_IDS.get_or_init(|| Mutex::new(HashMap::new()))
+    }
+
+    #[cfg(feature = "linux-dbus")]
+    pub fn alert(_self: &impl Sized, alert: AlertRequest) -> Result<()> {
+        let conn = Connection::new_session()?;
+        let proxy = conn.with_proxy(
+            NOTIFICATIONS_NAME,
+            NOTIFICATIONS_PATH,
+            Duration::from_secs(2),
+        );
+        let replaces_id = alert
+            .group
+            .as_ref()
+            .and_then(|g| group_ids().lock().unwrap().get(g).copied())
+            .unwrap_or(0);
+        let mut hints = PropMap::new();
+        if alert.silent {
+            hints.insert("suppress-sound".to_string(), Variant(Box::new(true)));
+            hints.insert("urgency".to_string(), Variant(Box::new(0u8)));
+        }
+        let (id,): (u32,) = proxy.method_call(
+            NOTIFICATIONS_NAME,
+            "Notify",
+            (
+                "",
+                replaces_id,
+                "",
+                alert.title.as_str(),
+                alert.body.as_str(),
+                Vec::<String>::new(),
+                hints,
+                -1i32,
+            ),
+        )?;
+        if let Some(group) = alert.group {
+            group_ids().lock().unwrap().insert(group, id);
+        }
         Ok(())
     }
+
+    /// GNOME keeps DND in gsettings rather than on the notification server.
+    fn gnome_banners_hidden() -> Option<bool> {
+        let output = std::process::Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.notifications", "show-banners"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        match String::from_utf8_lossy(&output.stdout).trim() {
+            "false" => Some(true),
+            "true" => Some(false),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "linux-dbus")]
+    pub fn system_dnd(_self: &impl Sized) -> Option<bool> {
+        match notifications_inhibited() {
+            Ok(true) => Some(true),
+            Ok(false) => gnome_banners_hidden().or(Some(false)),
+            Err(err) => {
+                tracing::debug!(error = %err, "Inhibited property not available");
+                gnome_banners_hidden()
+            }
+        }
+    }
+
+    #[cfg(not(feature = "linux-dbus"))]
+    pub fn system_dnd(_self: &impl Sized) -> Option<bool> {
+        gnome_banners_hidden()
+    }
+
+    pub fn diagnostics(_self: &impl Sized, app: &AppIdentity) -> Vec<DiagnosticCheck> {
+        let mut checks = Vec::new();
+        checks.push(match find_desktop_entry(app) {
+            Some(path) => {
+                DiagnosticCheck::new("desktop-entry", CheckStatus::Ok, path.display().to_string())
+            }
+            // 没有 .desktop 文件时，通知服务器拿不到应用名和图标。
+            None => DiagnosticCheck::new(
+                "desktop-entry",
+                CheckStatus::Warning,
+                format!("no {}.desktop in the XDG data dirs", app.identifier),
+            ),
+        });
+        #[cfg(feature = "linux-dbus")]
+        checks.push(match notification_server() {
+            Ok((server, caps)) => DiagnosticCheck::new(
+                "notification-server",
+                CheckStatus::Ok,
+                format!("{server}; capabilities: {}", caps.join(", ")),
+            ),
+            Err(err) => DiagnosticCheck::new(
+                "notification-server",
+                CheckStatus::Error,
+                format!("{err:#}"),
+            ),
+        });
+        checks
+    }
+
+    /// Adapter exposing this module to the shared plugin layer.
+    pub struct LinuxBackend;
+
+    impl Backend for LinuxBackend {
+        fn name(&self) -> &'static str {
+            BACKEND
+        }
+
+        fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+            create_live_activity(self, payload)
+        }
+
+        fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+            update_live_activity(self, payload)
+        }
+
+        fn remove(&self) -> Result<()> {
+            remove_live_activity(self)
+        }
+
+        fn visible_ids(&self) -> Result<Vec<String>> {
+            Ok(visible_ids(self))
+        }
+
+        fn capabilities(&self) -> Result<Capabilities> {
+            Ok(capabilities(self))
+        }
+
+        fn permission_state(&self) -> Result<PermissionState> {
+            Ok(permission_state(self))
+        }
+
+        fn request_permission(&self) -> Result<PermissionState> {
+            Ok(request_permission(self))
+        }
+
+        #[cfg(feature = "linux-dbus")]
+        fn alert(&self, request: AlertRequest) -> Result<()> {
+            alert(self, request)
+        }
+
+        fn system_dnd(&self) -> Option<bool> {
+            system_dnd(self)
+        }
+
+        fn diagnostics(&self, app: &AppIdentity) -> Vec<DiagnosticCheck> {
+            diagnostics(self, app)
+        }
+    }
 }
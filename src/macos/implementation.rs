@@ -1,4 +1,5 @@
 pub mod core {
+    use crate::backend::Backend;
     use crate::models::*;
     use anyhow::{Context, Result};
     use block2::RcBlock;
@@ -9,14 +10,13 @@ pub mod core {
         UNAuthorizationOptions, UNAuthorizationStatus, UNNotificationSettings,
         UNUserNotificationCenter,
     };
-    use std::process::Command;
     use std::sync::{Mutex, OnceLock};
     use std::time::Duration;
 
+    pub const BACKEND: &str = "macos-notification";
+
     static CURRENT_TAG: OnceLock<Mutex<Option<String>>> = OnceLock::new();
     static CURRENT_META: OnceLock<Mutex<Option<Meta>>> = OnceLock::new();
-    static NOTIFY_SETTINGS_HINT_SHOWN: OnceLock<()> = OnceLock::new();
-    static NOTIFY_PERMISSION_CHECKED: OnceLock<()> = OnceLock::new();
 
     #[derive(Clone)]
     struct Meta {
@@ -46,23 +46,6 @@ pub mod core {
         }
     }
 
-    fn open_notification_settings_once() {
-        if NOTIFY_SETTINGS_HINT_SHOWN.get().is_some() {
-            return;
-        }
-        let _ = NOTIFY_SETTINGS_HINT_SHOWN.set(());
-
-        // 优先新设置页 URI，失败后回退到旧 URI。
-        let _ = Command::new("open")
-            .arg("x-apple.systempreferences:com.apple.Notifications-Settings.extension")
-            .status()
-            .or_else(|_| {
-                Command::new("open")
-                    .arg("x-apple.systempreferences:com.apple.preference.notifications")
-                    .status()
-            });
-    }
-
     fn current_notification_auth_status(timeout: Duration) -> Option<UNAuthorizationStatus> {
         let center = UNUserNotificationCenter::currentNotificationCenter();
         let (tx, rx) = std::sync::mpsc::channel();
@@ -88,26 +71,38 @@ pub mod core {
         rx.recv_timeout(timeout).ok()
     }
 
-    fn ensure_notification_permission_once() {
-        if NOTIFY_PERMISSION_CHECKED.get().is_some() {
-            return;
-        }
-        let _ = NOTIFY_PERMISSION_CHECKED.set(());
-
+    fn auth_state() -> PermissionState {
         match current_notification_auth_status(Duration::from_secs(2)) {
-            Some(UNAuthorizationStatus::Authorized)
-            | Some(UNAuthorizationStatus::Provisional)
-            | Some(UNAuthorizationStatus::Ephemeral) => {}
-            Some(UNAuthorizationStatus::NotDetermined) => {
-                // 主动触发系统授权弹窗；若用户拒绝或系统未返回结果，则引导到设置页。
-                if !request_notification_auth(Duration::from_secs(4)).unwrap_or(false) {
-                    open_notification_settings_once();
-                }
-            }
-            Some(UNAuthorizationStatus::Denied) | None => {
-                open_notification_settings_once();
+            Some(UNAuthorizationStatus::Authorized) | Some(UNAuthorizationStatus::Ephemeral) => {
+                PermissionState::Granted
             }
-            Some(_) => {}
+            Some(UNAuthorizationStatus::Provisional) => PermissionState::Provisional,
+            Some(UNAuthorizationStatus::NotDetermined) => PermissionState::NotDetermined,
+            // 通知中心 2 秒内没回话，不能当成用户还没选。
+            None => PermissionState::Unknown,
+            Some(_) => PermissionState::Denied,
+        }
+    }
+
+    pub fn permission_state(_self: &impl Sized) -> PermissionState {
+        auth_state()
+    }
+
+    pub fn request_permission(_self: &impl Sized) -> PermissionState {
+        if matches!(
+            auth_state(),
+            PermissionState::NotDetermined | PermissionState::Unknown
+        ) {
+            // 等待用户点选系统弹窗，给足时间；超时按未决定处理。
+            let _ = request_notification_auth(Duration::from_secs(60));
+        }
+        auth_state()
+    }
+
+    fn apply_bundle_id(bundle_id: &str) {
+        if let Err(err) = set_application(bundle_id) {
+            // 重复设置同一个 bundle id 也会报错，这里只记录不中断。
+            tracing::debug!(error = %err, bundle_id, "set_application failed");
         }
     }
 
@@ -118,11 +113,9 @@ pub mod core {
         opts: Option<&Notification>,
         err_ctx: &str,
     ) -> Result<()> {
-        ensure_notification_permission_once();
         match send_notification(title, subtitle, message, opts) {
             Ok(_) => Ok(()),
             Err(err) => {
-                open_notification_settings_once();
                 corelib::bail_site!("{}: {} (请在系统设置>通知中开启 AstroBox)", err_ctx, err);
             }
         }
@@ -140,36 +133,16 @@ pub mod core {
 
         let bundle_id = state.remove("bundle_id");
         if let Some(ref bid) = bundle_id {
-            set_application(bid).ok();
+            apply_bundle_id(bid);
         }
         let icon = state.remove("logo");
-        let progress = state
-            .remove("progress")
-            .and_then(|s| s.parse::<f32>().ok())
-            .or_else(|| {
-                state
-                    .get("percent")
-                    .and_then(|s| s.parse::<f32>().ok())
-                    .map(|x| x / 100.0)
-            })
-            .unwrap_or(0.0)
-            .clamp(0.0, 1.0);
-        let progress_text = state
-            .remove("percent")
-            .map(|p| format!("{}%", p))
-            .unwrap_or_else(|| format!("{:.1}%", progress * 100.0));
-
-        {
-            *current_tag().lock().unwrap() = Some(id);
-        }
-        {
-            *current_meta().lock().unwrap() = Some(Meta {
-                title: title.clone(),
-                text: text.clone(),
-                icon: icon.clone(),
-                bundle_id: bundle_id.clone(),
-            });
-        }
+        let progress = parse_progress(&state).unwrap_or(0.0);
+        let progress_text = state.remove("hint").unwrap_or_else(|| {
+            state
+                .remove("percent")
+                .map(|p| format!("{}%", p))
+                .unwrap_or_else(|| format!("{:.1}%", progress * 100.0))
+        });
 
         let subtitle_opt = Some(task_type);
         let subtitle = subtitle_opt.as_deref();
@@ -187,6 +160,15 @@ pub mod core {
             "Failed to send macOS notification",
         )?;
 
+        // 发送成功后才记下，否则失败的创建会让后续更新以为活动还在。
+        *current_tag().lock().unwrap() = Some(id);
+        *current_meta().lock().unwrap() = Some(Meta {
+            title,
+            text,
+            icon,
+            bundle_id,
+        });
+
         Ok(())
     }
 
@@ -205,29 +187,27 @@ pub mod core {
             m.clone().context("No meta to update")?
         };
         if let Some(ref bid) = meta.bundle_id {
-            set_application(bid).ok();
+            apply_bundle_id(bid);
         }
 
-        let mut p = payload
-            .state
-            .get("progress")
-            .and_then(|s| s.parse::<f32>().ok())
-            .or_else(|| {
-                payload
-                    .state
-                    .get("percent")
-                    .and_then(|s| s.parse::<f32>().ok())
-                    .map(|x| x / 100.0)
-            })
-            .unwrap_or(0.0);
-        p = p.clamp(0.0, 1.0);
+        let p = parse_progress(&payload.state).unwrap_or(0.0);
 
         let mut opts = Notification::new();
         if let Some(ref path) = meta.icon {
             opts.app_icon(path);
         }
 
-        if (p - 1.0).abs() < f32::EPSILON {
+        if (p - 1.0).abs() < f32::EPSILON
+            && payload
+                .state
+                .get("completion")
+                .is_some_and(|v| v == "hidden")
+        {
+            // 用户不要完成通知，静默收尾。
+            clear_all_notifications();
+            *current_tag().lock().unwrap() = None;
+            *current_meta().lock().unwrap() = None;
+        } else if (p - 1.0).abs() < f32::EPSILON {
             let subtitle = Some("传输完成");
             let message = format!("{} — 100%", meta.text);
             send_notification_checked(
@@ -240,11 +220,19 @@ pub mod core {
 
             // 给系统一点时间展示完成通知，然后再清空通知中心，避免“看起来完全没通知”。
             std::thread::spawn(|| {
-                std::thread::sleep(Duration::from_secs(3));
+                crate::clock::sleep(Duration::from_secs(3));
                 clear_all_notifications();
             });
             *current_tag().lock().unwrap() = None;
             *current_meta().lock().unwrap() = None;
+        } else if let Some(hint) = payload.state.get("hint") {
+            send_notification_checked(
+                &meta.title,
+                Some(hint),
+                &meta.text,
+                Some(&opts),
+                "Failed to send hint notification",
+            )?;
         } else {
             let pct_text = if let Some(s) = payload.state.get("percent") {
                 format!("{}%", s)
@@ -273,7 +261,7 @@ pub mod core {
 
         if let Some(meta) = { current_meta().lock().unwrap().clone() } {
             if let Some(ref bid) = meta.bundle_id {
-                set_application(bid).ok();
+                apply_bundle_id(bid);
             }
             let mut opts = Notification::new();
             if let Some(ref path) = meta.icon {
@@ -281,11 +269,150 @@ pub mod core {
             }
             let subtitle = Some("已结束");
             let message = meta.text.clone();
-            send_notification(&meta.title, subtitle, &message, Some(&opts)).ok();
+            if let Err(err) = send_notification(&meta.title, subtitle, &message, Some(&opts)) {
+                tracing::warn!(error = %err, "failed to send removal notification");
+            }
         }
 
         *current_tag().lock().unwrap() = None;
         *current_meta().lock().unwrap() = None;
         Ok(())
     }
+
+    pub fn capabilities(_self: &impl Sized) -> Capabilities {
+        // mac-notification-sys 只能投递普通通知：进度只能写进正文，每次更新都是一条新通知。
+        Capabilities {
+            backend: BACKEND.to_string(),
+            presents: true,
+            progress_bar: false,
+            indeterminate_progress: false,
+            buttons: false,
+            images: true,
+            updates_in_place: false,
+            max_concurrent: 1,
+        }
+    }
+
+    pub fn visible_ids(_self: &impl Sized) -> Vec<String> {
+        current_tag().lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn alert(_self: &impl Sized, alert: AlertRequest) -> Result<()> {
+        // 这里不设置提示音，静默与否对 macOS 没有区别。
+        let bundle_id = current_meta()
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|m| m.bundle_id.clone());
+        if let Some(ref bid) = bundle_id {
+            apply_bundle_id(bid);
+        }
+        send_notification_checked(
+            &alert.title,
+            None,
+            &alert.body,
+            None,
+            "Failed to send alert notification",
+        )
+    }
+
+    fn main_bundle_id() -> Option<String> {
+        unsafe {
+            let bundle: *mut AnyObject = msg_send![class!(NSBundle), mainBundle];
+            if bundle.is_null() {
+                return None;
+            }
+            let id: *mut AnyObject = msg_send![bundle, bundleIdentifier];
+            if id.is_null() {
+                return None;
+            }
+            let utf8: *const std::ffi::c_char = msg_send![id, UTF8String];
+            if utf8.is_null() {
+                return None;
+            }
+            Some(
+                std::ffi::CStr::from_ptr(utf8)
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        }
+    }
+
+    pub fn diagnostics(_self: &impl Sized, app: &AppIdentity) -> Vec<DiagnosticCheck> {
+        let mut checks = Vec::new();
+        checks.push(match main_bundle_id() {
+            Some(id) if id == app.identifier => {
+                DiagnosticCheck::new("bundle-id", CheckStatus::Ok, id)
+            }
+            Some(id) => DiagnosticCheck::new(
+                "bundle-id",
+                CheckStatus::Warning,
+                format!("running as {id}, app identifier is {}", app.identifier),
+            ),
+            // 没有 bundle（比如直接跑可执行文件）时通知中心不认这个进程。
+            None => DiagnosticCheck::new(
+                "bundle-id",
+                CheckStatus::Error,
+                "process is not running from an app bundle",
+            ),
+        });
+        let configured = current_meta()
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|m| m.bundle_id.clone());
+        if let Some(bundle_id) = configured {
+            checks.push(DiagnosticCheck::new(
+                "notification-sender",
+                CheckStatus::Ok,
+                format!("sent as {bundle_id} (bundle_id state)"),
+            ));
+        }
+        checks
+    }
+
+    /// Adapter exposing this module to the shared plugin layer.
+    pub struct MacosBackend;
+
+    impl Backend for MacosBackend {
+        fn name(&self) -> &'static str {
+            BACKEND
+        }
+
+        fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+            create_live_activity(self, payload)
+        }
+
+        fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+            update_live_activity(self, payload)
+        }
+
+        fn remove(&self) -> Result<()> {
+            remove_live_activity(self)
+        }
+
+        fn visible_ids(&self) -> Result<Vec<String>> {
+            Ok(visible_ids(self))
+        }
+
+        fn capabilities(&self) -> Result<Capabilities> {
+            Ok(capabilities(self))
+        }
+
+        fn permission_state(&self) -> Result<PermissionState> {
+            Ok(permission_state(self))
+        }
+
+        fn request_permission(&self) -> Result<PermissionState> {
+            Ok(request_permission(self))
+        }
+
+        fn alert(&self, request: AlertRequest) -> Result<()> {
+            alert(self, request)
+        }
+
+        fn diagnostics(&self, app: &AppIdentity) -> Vec<DiagnosticCheck> {
+            diagnostics(self, app)
+        }
+    }
 }
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::models::*;
+
+/// Upper bounds (seconds) of the latency histogram buckets.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+#[derive(Default)]
+struct Histogram {
+    buckets: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; LATENCY_BUCKETS.len()];
+        }
+        for (bucket, le) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= *le {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    operations: BTreeMap<(String, String), u64>,
+    latencies: BTreeMap<(String, String), Histogram>,
+    active: u64,
+}
+
+/// In-process counters, latency histograms and gauges for the activity pipeline.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    inner: Mutex<Inner>,
+}
+
+impl Metrics {
+    pub fn count(&self, operation: &str, outcome: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner
+            .operations
+            .entry((operation.to_string(), outcome.to_string()))
+            .or_default() += 1;
+    }
+
+    /// Records how long one call to the presenter `backend` took.
+    pub fn observe(&self, operation: &str, backend: &str, elapsed: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .latencies
+            .entry((backend.to_string(), operation.to_string()))
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn set_active(&self, active: usize) {
+        self.inner.lock().unwrap().active = active as u64;
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let inner = self.inner.lock().unwrap();
+        MetricsSnapshot {
+            operations: inner
+                .operations
+                .iter()
+                .map(|((operation, outcome), count)| OperationCount {
+                    operation: operation.clone(),
+                    outcome: outcome.clone(),
+                    count: *count,
+                })
+                .collect(),
+            latencies: inner
+                .latencies
+                .iter()
+                .map(|((backend, operation), h)| LatencyHistogram {
+                    backend: backend.clone(),
+                    operation: operation.clone(),
+                    buckets: LATENCY_BUCKETS
+                        .iter()
+                        .zip(&h.buckets)
+                        .map(|(le, count)| LatencyBucket {
+                            le: *le,
+                            count: *count,
+                        })
+                        .collect(),
+                    count: h.count,
+                    sum_seconds: h.sum,
+                })
+                .collect(),
+            active_activities: inner.active,
+        }
+    }
+
+    /// Renders the current values in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let snapshot = self.snapshot();
+        let mut out = String::new();
+
+        out.push_str("# HELP live_activity_operations_total Plugin operations by outcome.\n");
+        out.push_str("# TYPE live_activity_operations_total counter\n");
+        for op in &snapshot.operations {
+            let _ = writeln!(
+                out,
+                "live_activity_operations_total{{operation=\"{}\",outcome=\"{}\"}} {}",
+                op.operation, op.outcome, op.count
+            );
+        }
+
+        out.push_str("# HELP live_activity_latency_seconds Time spent per presenter call.\n");
+        out.push_str("# TYPE live_activity_latency_seconds histogram\n");
+        for h in &snapshot.latencies {
+            let labels = format!("backend=\"{}\",operation=\"{}\"", h.backend, h.operation);
+            for bucket in &h.buckets {
+                let _ = writeln!(
+                    out,
+                    "live_activity_latency_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bucket.le, bucket.count
+                );
+            }
+            let _ = writeln!(
+                out,
+                "live_activity_latency_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, h.count
+            );
+            let _ = writeln!(
+                out,
+                "live_activity_latency_seconds_sum{{{}}} {}",
+                labels, h.sum_seconds
+            );
+            let _ = writeln!(
+                out,
+                "live_activity_latency_seconds_count{{{}}} {}",
+                labels, h.count
+            );
+        }
+
+        out.push_str("# HELP live_activity_active Activities currently tracked by the plugin.\n");
+        out.push_str("# TYPE live_activity_active gauge\n");
+        let _ = writeln!(out, "live_activity_active {}", snapshot.active_activities);
+        out
+    }
+}
+
+/// Where and how often the Prometheus text dump is written.
+#[derive(Debug, Clone)]
+pub(crate) struct PrometheusExport {
+    pub path: PathBuf,
+    pub interval: Duration,
+}
+
+/// Periodically rewrites the export file for as long as the app runs.
+pub(crate) fn spawn_prometheus_export(metrics: Arc<Metrics>, export: PrometheusExport) {
+    std::thread::spawn(move || loop {
+        crate::clock::sleep(export.interval);
+        let result = crate::fs::write_atomic(&export.path, metrics.to_prometheus().as_bytes());
+        if let Err(err) = result {
+            tracing::warn!(error = %err, path = %export.path.display(), "failed to export metrics");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latencies_fill_cumulative_buckets_per_presenter() {
+        let metrics = Metrics::default();
+        metrics.observe("create", "toast", Duration::from_millis(3));
+        metrics.observe("create", "toast", Duration::from_millis(200));
+        metrics.observe("create", "in-app", Duration::from_millis(1));
+        metrics.count("create", "ok");
+        metrics.count("create", "ok");
+        metrics.count("create", "error");
+        metrics.set_active(2);
+
+        let snapshot = metrics.snapshot();
+        let counts: Vec<_> = snapshot
+            .operations
+            .iter()
+            .map(|op| (op.outcome.as_str(), op.count))
+            .collect();
+        assert_eq!(counts, [("error", 1), ("ok", 2)]);
+        let backends: Vec<_> = snapshot
+            .latencies
+            .iter()
+            .map(|h| (h.backend.as_str(), h.count))
+            .collect();
+        assert_eq!(backends, [("in-app", 1), ("toast", 2)]);
+
+        let toast = &snapshot.latencies[1];
+        let le = |bound: f64| {
+            toast
+                .buckets
+                .iter()
+                .find(|b| b.le == bound)
+                .map(|b| b.count)
+                .unwrap()
+        };
+        assert_eq!(
+            (le(0.001), le(0.005), le(0.1), le(0.25), le(5.0)),
+            (0, 1, 1, 2, 2)
+        );
+        assert!((toast.sum_seconds - 0.203).abs() < 1e-9);
+        assert_eq!(snapshot.active_activities, 2);
+    }
+
+    #[test]
+    fn renders_prometheus_text() {
+        let metrics = Metrics::default();
+        metrics.count("update", "ok");
+        metrics.observe("update", "mock", Duration::from_millis(20));
+        metrics.set_active(1);
+
+        let text = metrics.to_prometheus();
+        let lines: Vec<_> = text.lines().collect();
+        for expected in [
+            "# TYPE live_activity_operations_total counter",
+            "live_activity_operations_total{operation=\"update\",outcome=\"ok\"} 1",
+            "# TYPE live_activity_latency_seconds histogram",
+            "live_activity_latency_seconds_bucket{backend=\"mock\",operation=\"update\",le=\"0.01\"} 0",
+            "live_activity_latency_seconds_bucket{backend=\"mock\",operation=\"update\",le=\"0.025\"} 1",
+            "live_activity_latency_seconds_bucket{backend=\"mock\",operation=\"update\",le=\"+Inf\"} 1",
+            "live_activity_latency_seconds_sum{backend=\"mock\",operation=\"update\"} 0.02",
+            "live_activity_latency_seconds_count{backend=\"mock\",operation=\"update\"} 1",
+            "# TYPE live_activity_active gauge",
+            "live_activity_active 1",
+        ] {
+            assert!(lines.contains(&expected), "missing `{expected}` in\n{text}");
+        }
+        // 每个直方图都是 11 个桶加上 +Inf、sum、count。
+        let series = lines
+            .iter()
+            .filter(|l| l.starts_with("live_activity_latency_seconds"))
+            .count();
+        assert_eq!(series, LATENCY_BUCKETS.len() + 3);
+    }
+}
@@ -1,19 +1,30 @@
 use anyhow::Result;
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use tauri::{
+    ipc::{Channel, InvokeResponseBody},
     plugin::{PluginApi, PluginHandle},
     AppHandle, Runtime,
 };
 
+use crate::backend::Backend;
+use crate::in_app::InAppBackend;
 use crate::models::*;
+use crate::LiveActivity;
+use crate::PluginOptions;
 
 #[cfg(target_os = "ios")]
 tauri::ios_plugin_binding!(init_plugin_live_activity);
 
+#[cfg(target_os = "android")]
+const BACKEND: &str = "android-notification";
+#[cfg(target_os = "ios")]
+const BACKEND: &str = "ios-activitykit";
+
 // initializes the Kotlin or Swift plugin classes
 pub fn init<R: Runtime, C: DeserializeOwned>(
-    _app: &AppHandle<R>,
+    app: &AppHandle<R>,
     api: PluginApi<R, C>,
+    mut options: PluginOptions,
 ) -> crate::Result<LiveActivity<R>> {
     #[cfg(target_os = "android")]
     let handle = api.register_android_plugin(
@@ -22,28 +33,129 @@ pub fn init<R: Runtime, C: DeserializeOwned>(
     )?;
     #[cfg(target_os = "ios")]
     let handle = api.register_ios_plugin(init_plugin_live_activity)?;
-    Ok(LiveActivity(handle))
+    forward_native_logs(&handle);
+    let backend = options
+        .backend
+        .take()
+        .unwrap_or_else(|| Box::new(MobileBackend(handle)));
+    if let Some(feed) = options.in_app.clone() {
+        options
+            .fallbacks
+            .push(Box::new(InAppBackend::new(app.clone(), feed)));
+    }
+    Ok(LiveActivity::new(app, backend, options))
+}
+
+#[derive(Deserialize)]
+struct NativeLog {
+    level: String,
+    message: String,
 }
 
-/// Access to the live-activity APIs.
-pub struct LiveActivity<R: Runtime>(PluginHandle<R>);
+#[derive(Serialize)]
+struct LogChannelArgs {
+    channel: Channel,
+}
+
+/// Routes the Kotlin/Swift side's log lines into the same `tracing` stream as the Rust layer.
+fn forward_native_logs<R: Runtime>(handle: &PluginHandle<R>) {
+    let channel = Channel::new(|body: InvokeResponseBody| {
+        let Ok(log) = body.deserialize::<NativeLog>() else {
+            return Ok(());
+        };
+        let message = log.message;
+        match log.level.as_str() {
+            "error" => {
+                tracing::error!(target: "live_activity::native", backend = BACKEND, "{message}")
+            }
+            "warn" => {
+                tracing::warn!(target: "live_activity::native", backend = BACKEND, "{message}")
+            }
+            "debug" => {
+                tracing::debug!(target: "live_activity::native", backend = BACKEND, "{message}")
+            }
+            _ => tracing::info!(target: "live_activity::native", backend = BACKEND, "{message}"),
+        }
+        Ok(())
+    });
+    if let Err(err) = handle.run_mobile_plugin::<()>("setLogChannel", LogChannelArgs { channel }) {
+        tracing::warn!(error = %err, "failed to attach native log channel");
+    }
+}
 
-impl<R: Runtime> LiveActivity<R> {
-    pub fn create_live_activity(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+#[derive(Deserialize)]
+struct PermissionResponse {
+    state: PermissionState,
+}
+
+#[derive(Deserialize)]
+struct DoNotDisturb {
+    enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct VisibleActivities {
+    ids: Vec<String>,
+}
+
+/// Forwards to the Kotlin or Swift plugin.
+struct MobileBackend<R: Runtime>(PluginHandle<R>);
+
+impl<R: Runtime> Backend for MobileBackend<R> {
+    fn name(&self) -> &'static str {
+        BACKEND
+    }
+
+    fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
         self.0
             .run_mobile_plugin("createLiveActivity", payload)
             .map_err(Into::into)
     }
 
-    pub fn update_live_activity(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+    fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
         self.0
             .run_mobile_plugin("updateLiveActivity", payload)
             .map_err(Into::into)
     }
 
-    pub fn remove_live_activity(&self) -> Result<()> {
+    fn remove(&self) -> Result<()> {
         self.0
             .run_mobile_plugin("removeLiveActivity", ())
             .map_err(Into::into)
     }
+
+    fn visible_ids(&self) -> Result<Vec<String>> {
+        let res: VisibleActivities = self.0.run_mobile_plugin("getVisibleActivities", ())?;
+        Ok(res.ids)
+    }
+
+    fn capabilities(&self) -> Result<Capabilities> {
+        let mut caps: Capabilities = self.0.run_mobile_plugin("getCapabilities", ())?;
+        caps.backend = BACKEND.to_string();
+        Ok(caps)
+    }
+
+    fn permission_state(&self) -> Result<PermissionState> {
+        let res: PermissionResponse = self.0.run_mobile_plugin("getPermissionState", ())?;
+        Ok(res.state)
+    }
+
+    fn request_permission(&self) -> Result<PermissionState> {
+        let res: PermissionResponse = self.0.run_mobile_plugin("requestPermission", ())?;
+        Ok(res.state)
+    }
+
+    fn alert(&self, alert: AlertRequest) -> Result<()> {
+        self.0
+            .run_mobile_plugin("postAlert", alert)
+            .map_err(Into::into)
+    }
+
+    fn system_dnd(&self) -> Option<bool> {
+        self.0
+            .run_mobile_plugin::<DoNotDisturb>("getDoNotDisturb", ())
+            .inspect_err(|err| tracing::debug!(error = %err, "getDoNotDisturb failed"))
+            .ok()
+            .and_then(|res| res.enabled)
+    }
 }
@@ -0,0 +1,154 @@
+use std::sync::Mutex;
+
+use anyhow::Result;
+
+use crate::backend::Backend;
+use crate::models::*;
+
+#[derive(Debug)]
+struct MockState {
+    current: Option<String>,
+    progress: Option<f32>,
+    permission: PermissionState,
+    calls: Vec<RecordedCall>,
+    alerts: Vec<AlertRequest>,
+    dnd: Option<bool>,
+}
+
+/// In-memory backend that presents nothing and remembers every call.
+///
+/// It is the reference for the backend contract and stands in for the OS in
+/// tests. Like the mobile backends it has a single slot: a create fails while
+/// another activity is shown.
+#[derive(Debug)]
+pub struct MockBackend {
+    state: Mutex<MockState>,
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(MockState {
+                current: None,
+                progress: None,
+                permission: PermissionState::Granted,
+                calls: Vec::new(),
+                alerts: Vec::new(),
+                dnd: None,
+            }),
+        }
+    }
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Permission reported from now on; a request grants `NotDetermined`, as
+    /// if the user agreed to the prompt.
+    pub fn set_permission(&self, permission: PermissionState) {
+        self.state.lock().unwrap().permission = permission;
+    }
+
+    /// Every create/update/remove received so far, in order.
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    pub fn set_system_dnd(&self, dnd: Option<bool>) {
+        self.state.lock().unwrap().dnd = dnd;
+    }
+
+    /// Every alert posted so far, in order.
+    pub fn alerts(&self) -> Vec<AlertRequest> {
+        self.state.lock().unwrap().alerts.clone()
+    }
+
+    pub fn current_id(&self) -> Option<String> {
+        self.state.lock().unwrap().current.clone()
+    }
+
+    /// Last progress shown, already clamped to `0.0..=1.0`.
+    pub fn progress(&self) -> Option<f32> {
+        self.state.lock().unwrap().progress
+    }
+}
+
+impl Backend for MockBackend {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        // 和 Android、iOS 一样只有一个位置，不会替换正在展示的活动。
+        if let Some(current) = &state.current {
+            anyhow::bail!("Live activity `{current}` already exists; skip create");
+        }
+        state.current = Some(payload.activity_content.id().to_string());
+        state.progress = parse_progress(payload.activity_content.state());
+        state.calls.push(RecordedCall::Create(payload));
+        Ok(())
+    }
+
+    fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.current.is_none() {
+            anyhow::bail!("No active live activity to update");
+        }
+        state.progress = parse_progress(&payload.state).or(state.progress);
+        state.calls.push(RecordedCall::Update(payload));
+        Ok(())
+    }
+
+    fn remove(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.current = None;
+        state.progress = None;
+        state.calls.push(RecordedCall::Remove);
+        Ok(())
+    }
+
+    fn visible_ids(&self) -> Result<Vec<String>> {
+        Ok(self.state.lock().unwrap().current.iter().cloned().collect())
+    }
+
+    fn shown_progress(&self) -> Option<f32> {
+        self.progress()
+    }
+
+    fn capabilities(&self) -> Result<Capabilities> {
+        Ok(Capabilities {
+            backend: self.name().to_string(),
+            presents: true,
+            progress_bar: true,
+            indeterminate_progress: true,
+            buttons: false,
+            images: false,
+            updates_in_place: true,
+            max_concurrent: 1,
+        })
+    }
+
+    fn permission_state(&self) -> Result<PermissionState> {
+        Ok(self.state.lock().unwrap().permission)
+    }
+
+    fn request_permission(&self) -> Result<PermissionState> {
+        let mut state = self.state.lock().unwrap();
+        if state.permission == PermissionState::NotDetermined {
+            state.permission = PermissionState::Granted;
+        }
+        Ok(state.permission)
+    }
+
+    fn alert(&self, alert: AlertRequest) -> Result<()> {
+        self.state.lock().unwrap().alerts.push(alert);
+        Ok(())
+    }
+
+    fn system_dnd(&self) -> Option<bool> {
+        self.state.lock().unwrap().dnd
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ActivityContentTaskQueue {
     pub id: String,
     pub title: String,
@@ -13,6 +13,63 @@ pub struct ActivityContentTaskQueue {
     #[serde(rename = "taskIcon")]
     pub task_icon: String,
     pub state: HashMap<String, String>,
+    #[serde(default)]
+    pub priority: ActivityPriority,
+    /// Activities sharing a group get one completion summary instead of a
+    /// notification each.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Shows the activity as stalled once no update arrived for this long.
+    #[serde(
+        rename = "stallAfterMs",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stall_after_ms: Option<u64>,
+    /// Ends the activity as interrupted when no heartbeat or update arrived
+    /// for this long.
+    #[serde(
+        rename = "heartbeatMs",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub heartbeat_ms: Option<u64>,
+    /// Label of the webview that owns the activity; it ends as interrupted
+    /// when that webview reloads or is destroyed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Milliseconds since the Unix epoch. Until then the activity is shown as
+    /// announced, with a countdown instead of its progress.
+    #[serde(rename = "startAt", default, skip_serializing_if = "Option::is_none")]
+    pub start_at: Option<u64>,
+    /// Milliseconds since the Unix epoch at which the activity ends by itself.
+    #[serde(rename = "endAt", default, skip_serializing_if = "Option::is_none")]
+    pub end_at: Option<u64>,
+    /// Keeps the system from sleeping or idling while the activity runs, e.g.
+    /// during a firmware transfer.
+    #[serde(
+        rename = "inhibitSleep",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub inhibit_sleep: bool,
+}
+
+/// Which activity gets the visible slot when several are running.
+///
+/// A new activity takes the slot from one of lower priority, which is kept and
+/// shown again once the slot frees up. At equal priority the new activity
+/// replaces the current one.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum ActivityPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Critical,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -21,19 +78,755 @@ pub struct ActivityContentTaskQueueBasicState {
     pub progress: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "type", content = "data")]
 pub enum ActivityContent {
     TaskQueue(ActivityContentTaskQueue),
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl ActivityContent {
+    pub fn id(&self) -> &str {
+        match self {
+            ActivityContent::TaskQueue(t) => &t.id,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        match self {
+            ActivityContent::TaskQueue(t) => &t.title,
+        }
+    }
+
+    pub fn task_type(&self) -> &str {
+        match self {
+            ActivityContent::TaskQueue(t) => &t.task_type,
+        }
+    }
+
+    pub fn priority(&self) -> ActivityPriority {
+        match self {
+            ActivityContent::TaskQueue(t) => t.priority,
+        }
+    }
+
+    pub fn group(&self) -> Option<&str> {
+        match self {
+            ActivityContent::TaskQueue(t) => t.group.as_deref(),
+        }
+    }
+
+    pub fn heartbeat(&self) -> Option<std::time::Duration> {
+        match self {
+            ActivityContent::TaskQueue(t) => t.heartbeat_ms.map(std::time::Duration::from_millis),
+        }
+    }
+
+    pub fn owner(&self) -> Option<&str> {
+        match self {
+            ActivityContent::TaskQueue(t) => t.owner.as_deref(),
+        }
+    }
+
+    pub fn start_at(&self) -> Option<u64> {
+        match self {
+            ActivityContent::TaskQueue(t) => t.start_at,
+        }
+    }
+
+    pub fn end_at(&self) -> Option<u64> {
+        match self {
+            ActivityContent::TaskQueue(t) => t.end_at,
+        }
+    }
+
+    pub fn inhibit_sleep(&self) -> bool {
+        match self {
+            ActivityContent::TaskQueue(t) => t.inhibit_sleep,
+        }
+    }
+
+    pub fn stall_after(&self) -> Option<std::time::Duration> {
+        match self {
+            ActivityContent::TaskQueue(t) => t.stall_after_ms.map(std::time::Duration::from_millis),
+        }
+    }
+
+    pub fn state(&self) -> &HashMap<String, String> {
+        match self {
+            ActivityContent::TaskQueue(t) => &t.state,
+        }
+    }
+
+    pub fn state_mut(&mut self) -> &mut HashMap<String, String> {
+        match self {
+            ActivityContent::TaskQueue(t) => &mut t.state,
+        }
+    }
+}
+
+/// Reads the activity progress from a state map.
+///
+/// `progress` (0..1) wins over `percent` (0..100, optional `%` suffix); the
+/// result is clamped to `0.0..=1.0`. `None` when neither key parses.
+pub fn parse_progress(state: &HashMap<String, String>) -> Option<f32> {
+    state
+        .get("progress")
+        .and_then(|s| s.trim().parse::<f32>().ok())
+        .or_else(|| {
+            state
+                .get("percent")
+                .and_then(|s| s.trim().trim_end_matches('%').parse::<f32>().ok())
+                .map(|x| x / 100.0)
+        })
+        .filter(|p| p.is_finite())
+        .map(|p| p.clamp(0.0, 1.0))
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CreateLiveActivityRequest {
     pub activity_content_v: u32,
     pub activity_content: ActivityContent,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct UpdateLiveActivityRequest {
     pub state: HashMap<String, String>,
+    /// Activity to update; the one holding the visible slot when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+/// Point-in-time view of an activity created through the plugin.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivitySnapshot {
+    pub id: String,
+    pub content: ActivityContent,
+    /// Last state pushed through create or update.
+    pub state: HashMap<String, String>,
+    /// Milliseconds since the Unix epoch.
+    pub created_at: u64,
+    /// Milliseconds since the Unix epoch.
+    pub updated_at: u64,
+    /// Presenter currently showing the activity; after a fallback this is not
+    /// the platform backend any more.
+    pub backend: String,
+    /// Whether the OS still presents the activity.
+    pub visible: bool,
+    /// No update arrived within the activity's `stall_after`.
+    pub stalled: bool,
+}
+
+/// What the active backend can present, so the UI can fill in the gaps itself.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    #[serde(default)]
+    pub backend: String,
+    /// Whether anything is shown by the OS at all.
+    pub presents: bool,
+    /// A real progress bar rather than a percentage in the text.
+    pub progress_bar: bool,
+    pub indeterminate_progress: bool,
+    pub buttons: bool,
+    pub images: bool,
+    /// Updates replace the shown activity instead of posting a new notification.
+    pub updates_in_place: bool,
+    /// How many activities can be shown at the same time.
+    pub max_concurrent: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionState {
+    Granted,
+    Denied,
+    /// The user was not asked yet; the first create asks.
+    NotDetermined,
+    /// Delivered quietly until the user decides (macOS provisional authorization).
+    Provisional,
+    /// The platform did not answer in time; presenting is attempted anyway.
+    Unknown,
+}
+
+impl PermissionState {
+    pub fn allows_presenting(self) -> bool {
+        matches!(
+            self,
+            PermissionState::Granted | PermissionState::Provisional
+        )
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationCount {
+    pub operation: String,
+    pub outcome: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyBucket {
+    /// Upper bound in seconds.
+    pub le: f64,
+    /// Observations at or below `le` (cumulative, Prometheus style).
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyHistogram {
+    pub backend: String,
+    pub operation: String,
+    pub buckets: Vec<LatencyBucket>,
+    pub count: u64,
+    pub sum_seconds: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsSnapshot {
+    pub operations: Vec<OperationCount>,
+    pub latencies: Vec<LatencyHistogram>,
+    pub active_activities: u64,
+}
+
+/// One plugin call as written by the session recorder.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "op", content = "request", rename_all = "camelCase")]
+#[allow(clippy::large_enum_variant)]
+pub enum RecordedCall {
+    Create(CreateLiveActivityRequest),
+    Update(UpdateLiveActivityRequest),
+    Remove,
+    End { id: String },
+}
+
+/// A line of a recorded session (JSONL).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedEntry {
+    /// Milliseconds since the Unix epoch when the call was made.
+    pub at: u64,
+    pub call: RecordedCall,
+    pub elapsed_ms: f64,
+    /// `None` when the call succeeded.
+    pub error: Option<String>,
+}
+
+/// Normalized activity lifecycle event delivered to every `ActivitySink`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+#[allow(clippy::large_enum_variant)]
+pub enum ActivityEvent {
+    #[serde(rename_all = "camelCase")]
+    Created {
+        id: String,
+        content: ActivityContent,
+        /// Parsed with [`parse_progress`], `None` when the state carries none.
+        progress: Option<f32>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Updated {
+        id: String,
+        state: HashMap<String, String>,
+        progress: Option<f32>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Ended {
+        id: String,
+        #[serde(default)]
+        outcome: EndOutcome,
+    },
+    /// The announced activity reached its `start_at`.
+    #[serde(rename_all = "camelCase")]
+    Started { id: String },
+    /// No update arrived within the activity's `stall_after`.
+    #[serde(rename_all = "camelCase")]
+    Stalled { id: String },
+    /// Updates arrived again after [`ActivityEvent::Stalled`].
+    #[serde(rename_all = "camelCase")]
+    Resumed { id: String },
+}
+
+impl ActivityEvent {
+    pub fn id(&self) -> &str {
+        match self {
+            ActivityEvent::Created { id, .. }
+            | ActivityEvent::Updated { id, .. }
+            | ActivityEvent::Ended { id, .. }
+            | ActivityEvent::Started { id }
+            | ActivityEvent::Stalled { id }
+            | ActivityEvent::Resumed { id } => id,
+        }
+    }
+}
+
+/// Why an activity ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EndOutcome {
+    /// Ended through the API.
+    #[default]
+    Ended,
+    /// Its owner went away or stopped sending heartbeats.
+    Interrupted,
+    /// Its `end_at` came.
+    Expired,
+}
+
+/// An ended activity, as kept by the history store.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: String,
+    pub task_type: String,
+    /// Content with the last state pushed.
+    pub content: ActivityContent,
+    pub outcome: EndOutcome,
+    /// Reached 100% without an `error` in its state.
+    pub succeeded: bool,
+    /// Milliseconds since the Unix epoch.
+    pub started_at: u64,
+    /// Milliseconds since the Unix epoch.
+    pub ended_at: u64,
+    /// Highest progress it reported, 0.0 to 1.0.
+    pub peak_progress: Option<f32>,
+    /// The `error` state value it ended with.
+    pub failure: Option<String>,
+}
+
+/// Which history entries to return; every field narrows the result.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HistoryQuery {
+    /// Milliseconds since the Unix epoch; entries that ended before are left out.
+    pub from: Option<u64>,
+    /// Milliseconds since the Unix epoch; entries that started at or after are left out.
+    pub to: Option<u64>,
+    pub task_type: Option<String>,
+    /// At most this many entries, newest first.
+    pub limit: Option<usize>,
+}
+
+/// Aggregates over the history entries matching a [`HistoryQuery`].
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStats {
+    pub count: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// `succeeded / count`; `None` without entries.
+    pub success_rate: Option<f64>,
+    /// `None` without entries.
+    pub mean_duration_ms: Option<u64>,
+}
+
+/// A one-off notification outside any activity, e.g. a batched summary.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRequest {
+    pub title: String,
+    pub body: String,
+    /// Deliver without sound or banner where the platform allows it.
+    #[serde(default)]
+    pub silent: bool,
+    /// Alerts of the same group replace each other where the platform can.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+/// A daily quiet period in local time; `end` before `start` spans midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietWindow {
+    /// Minutes since local midnight.
+    pub start: u16,
+    /// Minutes since local midnight, exclusive.
+    pub end: u16,
+}
+
+impl QuietWindow {
+    pub fn new(start: (u8, u8), end: (u8, u8)) -> Self {
+        Self {
+            start: start.0 as u16 * 60 + start.1 as u16,
+            end: end.0 as u16 * 60 + end.1 as u16,
+        }
+    }
+
+    pub fn contains(&self, minute: u16) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QuietMode {
+    /// Present as usual, but without sound or banner.
+    #[default]
+    Silent,
+    /// Hold new activities back until the quiet period ends.
+    Defer,
+}
+
+/// Plugin-level quiet hours, applied before any backend call.
+///
+/// While quiet, backends receive `silent = "true"` in the activity state and
+/// completion alerts are collected into one summary posted afterwards.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    #[serde(default)]
+    pub windows: Vec<QuietWindow>,
+    /// Also treat the OS do-not-disturb state as quiet, where it can be read.
+    #[serde(default)]
+    pub honor_system_dnd: bool,
+    #[serde(default)]
+    pub mode: QuietMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QuietReason {
+    Schedule,
+    SystemDnd,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietStatus {
+    pub policy: Option<QuietHours>,
+    /// `None` when not quiet.
+    pub reason: Option<QuietReason>,
+    /// Activity held back by [`QuietMode::Defer`].
+    pub deferred: Option<String>,
+    /// Completions waiting for the summary alert.
+    pub pending_completions: usize,
+}
+
+/// How this process shares the presenter with other instances of the app.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceStatus {
+    /// Prefix of this instance's activity ids in the owning instance.
+    pub namespace: String,
+    /// Whether this instance holds the lock and presents activities itself.
+    pub owner: bool,
+    /// Namespace of the instance holding the lock, if any does.
+    pub owner_namespace: Option<String>,
+    pub owner_pid: Option<u32>,
+}
+
+/// What the user wants to see for one `task_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TaskPreference {
+    /// `false` keeps activities of this type off the OS presentation entirely.
+    pub enabled: bool,
+    /// Present without sound or banner.
+    pub silent: bool,
+    /// Post the completion notification when the activity reaches 100%.
+    pub show_completion: bool,
+}
+
+impl Default for TaskPreference {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            silent: false,
+            show_completion: true,
+        }
+    }
+}
+
+/// How the app identifies itself to the OS, for identity checks in diagnostics.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppIdentity {
+    /// Product name, e.g. the `.desktop` file or shortcut name.
+    pub name: String,
+    /// Reverse-DNS app identifier from the Tauri config.
+    pub identifier: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CheckStatus {
+    Ok,
+    /// Works, but something is likely to degrade the presentation.
+    Warning,
+    Error,
+    /// Not applicable or not checkable on this platform.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl DiagnosticCheck {
+    pub fn new(name: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Facts about the notification setup, meant to be attached to bug reports.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticReport {
+    /// Milliseconds since the Unix epoch.
+    pub generated_at: u64,
+    pub plugin_version: String,
+    pub os: String,
+    pub arch: String,
+    pub app: AppIdentity,
+    /// Platform backend followed by the fallback presenters, in order.
+    pub presenters: Vec<String>,
+    /// `None` when the query itself failed; see `checks`.
+    pub permission: Option<PermissionState>,
+    pub capabilities: Option<Capabilities>,
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+/// A line sent to the local IPC endpoint.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpcRequest {
+    /// Contents of the token file written next to the endpoint.
+    pub token: String,
+    pub call: RecordedCall,
+}
+
+/// The line the IPC endpoint answers every request with.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpcResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaySummary {
+    pub total: usize,
+    pub failed: usize,
+}
+
+/// What went wrong in a backend call, as far as retrying is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FailureKind {
+    /// The notification service did not answer in time.
+    Timeout,
+    /// The service or COM apartment asked to be called again later.
+    Busy,
+    /// The connection to the service was lost or it is not running (yet).
+    Disconnected,
+    PermissionDenied,
+    Other,
+}
+
+/// How often and how patiently a backend call is retried.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one; `1` disables retrying.
+    pub max_attempts: u32,
+    /// Pause before the second attempt; it doubles with every further one.
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Failures worth another attempt; anything else fails right away.
+    pub retry_on: Vec<FailureKind>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 2_000,
+            retry_on: vec![
+                FailureKind::Timeout,
+                FailureKind::Busy,
+                FailureKind::Disconnected,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt.
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+}
+
+/// Retry policy for each backend operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicies {
+    pub create: RetryPolicy,
+    pub update: RetryPolicy,
+    pub remove: RetryPolicy,
+    pub alert: RetryPolicy,
+}
+
+/// Render-ready form of an activity, sent to the frontend by the in-app presenter.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InAppView {
+    pub id: String,
+    pub task_type: String,
+    pub icon: String,
+    pub title: String,
+    pub subtitle: String,
+    /// 0.0–1.0; `None` with `indeterminate` set while there is no progress to show.
+    pub progress: Option<f32>,
+    pub indeterminate: bool,
+    /// Text next to the progress: the percentage, a countdown or a waiting hint.
+    pub progress_text: String,
+    /// Show without sound or animation.
+    pub silent: bool,
+    pub actions: Vec<InAppAction>,
+    /// Set once the activity ended; the banner should go away after showing it.
+    pub outcome: Option<InAppOutcome>,
+    /// Reason of a failed activity.
+    pub error: Option<String>,
+    /// `false` when the user turned completion notifications off for this task
+    /// type; drop the banner without showing the outcome.
+    pub show_outcome: bool,
+}
+
+/// A button of the in-app banner; handled entirely by the frontend.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InAppAction {
+    pub id: String,
+    pub label: String,
+}
+
+/// How an activity shown in the app ended, judged by its last state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InAppOutcome {
+    /// Reached 100% without an `error`.
+    Succeeded,
+    /// Its state carried an `error`.
+    Failed,
+    /// Ended before reaching 100%.
+    Ended,
+}
+
+/// What the device currently runs on, as far as update throttling cares.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PowerState {
+    /// On external power, or the state is unknown.
+    #[default]
+    Ac,
+    Battery,
+    /// A power-saver profile or battery saver is on.
+    PowerSaver,
+}
+
+/// Minimum time between two updates of an activity that reach the OS, per
+/// power state.
+///
+/// Updates arriving faster are held back and only the latest one is shown
+/// once the interval has passed; sinks and snapshots still see every update.
+/// Reaching 100% or reporting an `error` always goes through.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UpdateThrottle {
+    pub ac_interval_ms: u64,
+    pub battery_interval_ms: u64,
+    pub power_saver_interval_ms: u64,
+    /// Never hold back updates of `critical` activities.
+    pub exempt_critical: bool,
+}
+
+impl Default for UpdateThrottle {
+    fn default() -> Self {
+        Self {
+            ac_interval_ms: 0,
+            battery_interval_ms: 2_000,
+            power_saver_interval_ms: 5_000,
+            exempt_critical: true,
+        }
+    }
+}
+
+impl UpdateThrottle {
+    pub fn interval(&self, power: PowerState) -> std::time::Duration {
+        std::time::Duration::from_millis(match power {
+            PowerState::Ac => self.ac_interval_ms,
+            PowerState::Battery => self.battery_interval_ms,
+            PowerState::PowerSaver => self.power_saver_interval_ms,
+        })
+    }
+}
+
+/// Fixtures shared by the unit tests and the `conformance` scenarios.
+#[cfg(any(test, feature = "conformance"))]
+#[cfg_attr(not(test), allow(dead_code))]
+pub(crate) mod test_support {
+    use super::*;
+
+    /// A normal-priority activity with only its id and title set; override the
+    /// rest with struct update syntax.
+    pub fn task(id: &str) -> ActivityContentTaskQueue {
+        ActivityContentTaskQueue {
+            id: id.to_string(),
+            title: id.to_string(),
+            text: String::new(),
+            task_name: String::new(),
+            task_type: String::new(),
+            task_icon: String::new(),
+            state: HashMap::new(),
+            priority: ActivityPriority::Normal,
+            group: None,
+            stall_after_ms: None,
+            heartbeat_ms: None,
+            owner: None,
+            start_at: None,
+            end_at: None,
+            inhibit_sleep: false,
+        }
+    }
+
+    pub fn content(task: ActivityContentTaskQueue) -> ActivityContent {
+        ActivityContent::TaskQueue(task)
+    }
+
+    pub fn create(task: ActivityContentTaskQueue) -> CreateLiveActivityRequest {
+        CreateLiveActivityRequest {
+            activity_content_v: 1,
+            activity_content: content(task),
+        }
+    }
+
+    pub fn state(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
 }
@@ -0,0 +1,47 @@
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::LiveActivity;
+
+/// Keeps an activity bound to a Rust task; see [`LiveActivity::guard`].
+///
+/// Dropping the guard while the activity still runs, e.g. because the task
+/// returned early or panicked, ends it as interrupted. Ending the activity
+/// through the API first makes the drop a no-op.
+#[must_use = "the activity is interrupted as soon as the guard is dropped"]
+pub struct ActivityGuard<R: Runtime> {
+    app: AppHandle<R>,
+    id: String,
+    created_at: u64,
+    armed: bool,
+}
+
+impl<R: Runtime> ActivityGuard<R> {
+    pub(crate) fn new(app: AppHandle<R>, id: String, created_at: u64) -> Self {
+        Self {
+            app,
+            id,
+            created_at,
+            armed: true,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Lets the activity outlive the guard.
+    pub fn release(mut self) {
+        self.armed = false;
+    }
+}
+
+impl<R: Runtime> Drop for ActivityGuard<R> {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        if let Some(live_activity) = self.app.try_state::<LiveActivity<R>>() {
+            live_activity.interrupt(&self.id, self.created_at);
+        }
+    }
+}
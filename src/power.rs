@@ -0,0 +1,160 @@
+use std::sync::{Arc, Mutex};
+
+use crate::clock::now_millis;
+use crate::models::PowerState;
+
+/// Where the update throttle learns whether the device runs on battery.
+///
+/// The plugin uses [`SystemPower`] unless `Builder::power_source` installs
+/// another one, e.g. a [`ManualPower`] in tests.
+pub trait PowerSource: Send + Sync {
+    fn power_state(&self) -> PowerState;
+}
+
+/// Reads the power state from the OS: UPower and power-profiles-daemon on
+/// Linux, `GetSystemPowerStatus` on Windows, `pmset` and its Low Power Mode
+/// setting on macOS.
+///
+/// Reports [`PowerState::Ac`] where it cannot tell. The answer is cached for
+/// [`SystemPower::CACHE_MS`], as every update of an activity asks for it.
+#[derive(Default)]
+pub struct SystemPower {
+    cached: Mutex<Option<(u64, PowerState)>>,
+}
+
+impl SystemPower {
+    pub const CACHE_MS: u64 = 30_000;
+}
+
+impl PowerSource for SystemPower {
+    fn power_state(&self) -> PowerState {
+        let now = now_millis();
+        let mut cached = self.cached.lock().unwrap();
+        match *cached {
+            Some((at, state)) if now.saturating_sub(at) < Self::CACHE_MS => state,
+            _ => {
+                let state = read().unwrap_or_default();
+                *cached = Some((now, state));
+                state
+            }
+        }
+    }
+}
+
+/// A power state that only changes when told to.
+#[derive(Default)]
+pub struct ManualPower {
+    state: Mutex<PowerState>,
+}
+
+impl ManualPower {
+    pub fn new(state: PowerState) -> Self {
+        Self {
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn set(&self, state: PowerState) {
+        *self.state.lock().unwrap() = state;
+    }
+}
+
+impl PowerSource for ManualPower {
+    fn power_state(&self) -> PowerState {
+        *self.state.lock().unwrap()
+    }
+}
+
+impl<P: PowerSource + ?Sized> PowerSource for Arc<P> {
+    fn power_state(&self) -> PowerState {
+        (**self).power_state()
+    }
+}
+
+/// The power-saver profile wins over the battery, since it is the stricter one.
+#[cfg(all(target_os = "linux", feature = "power-aware"))]
+fn read() -> Option<PowerState> {
+    use std::time::Duration;
+
+    use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+    use dbus::blocking::Connection;
+
+    const DBUS_TIMEOUT: Duration = Duration::from_secs(1);
+    let conn = Connection::new_system().ok()?;
+    // 老版本叫 net.hadess.PowerProfiles，新版本两个名字都注册。
+    let profile: Option<String> = conn
+        .with_proxy(
+            "net.hadess.PowerProfiles",
+            "/net/hadess/PowerProfiles",
+            DBUS_TIMEOUT,
+        )
+        .get("net.hadess.PowerProfiles", "ActiveProfile")
+        .ok();
+    if profile.as_deref() == Some("power-saver") {
+        return Some(PowerState::PowerSaver);
+    }
+    let on_battery: bool = conn
+        .with_proxy(
+            "org.freedesktop.UPower",
+            "/org/freedesktop/UPower",
+            DBUS_TIMEOUT,
+        )
+        .get("org.freedesktop.UPower", "OnBattery")
+        .ok()?;
+    Some(if on_battery {
+        PowerState::Battery
+    } else {
+        PowerState::Ac
+    })
+}
+
+#[cfg(all(windows, feature = "power-aware"))]
+fn read() -> Option<PowerState> {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status = SYSTEM_POWER_STATUS::default();
+    unsafe { GetSystemPowerStatus(&mut status) }.ok()?;
+    // SystemStatusFlag 为 1 表示节电模式开着。
+    Some(match (status.SystemStatusFlag, status.ACLineStatus) {
+        (1, _) => PowerState::PowerSaver,
+        (_, 0) => PowerState::Battery,
+        _ => PowerState::Ac,
+    })
+}
+
+/// Low Power Mode wins over the battery, like the power-saver profile on Linux.
+#[cfg(all(target_os = "macos", feature = "power-aware"))]
+fn read() -> Option<PowerState> {
+    let pmset = |args: &[&str]| {
+        let output = std::process::Command::new("/usr/bin/pmset")
+            .args(args)
+            .output()
+            .ok()?;
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    // `pmset -g` 列出当前电源的设置；低电量模式在老系统上是 `lowpowermode 1`，
+    // 新系统上是 `powermode 1`。
+    let low_power = pmset(&["-g"]).is_some_and(|text| {
+        text.lines().any(|line| {
+            let setting: Vec<&str> = line.split_whitespace().collect();
+            matches!(setting[..], ["lowpowermode" | "powermode", "1"])
+        })
+    });
+    if low_power {
+        return Some(PowerState::PowerSaver);
+    }
+    let text = pmset(&["-g", "batt"])?;
+    Some(if text.contains("'Battery Power'") {
+        PowerState::Battery
+    } else {
+        PowerState::Ac
+    })
+}
+
+#[cfg(not(all(
+    any(target_os = "linux", windows, target_os = "macos"),
+    feature = "power-aware"
+)))]
+fn read() -> Option<PowerState> {
+    None
+}
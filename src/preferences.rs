@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Context;
+
+use crate::models::*;
+
+/// Per-`task_type` preferences, persisted as JSON in the app config dir.
+///
+/// Task types without an entry use [`TaskPreference::default`].
+pub(crate) struct PreferenceStore {
+    path: Option<PathBuf>,
+    prefs: Mutex<BTreeMap<String, TaskPreference>>,
+}
+
+impl PreferenceStore {
+    /// Loads `path`; a missing or unreadable file starts empty. Without a path
+    /// changes only last for this run.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let prefs = path
+            .as_deref()
+            .filter(|p| p.exists())
+            .map(read)
+            .transpose()
+            .unwrap_or_else(|err| {
+                tracing::warn!(error = %err, "failed to load activity preferences");
+                None
+            })
+            .unwrap_or_default();
+        Self {
+            path,
+            prefs: Mutex::new(prefs),
+        }
+    }
+
+    pub fn get(&self, task_type: &str) -> TaskPreference {
+        self.prefs
+            .lock()
+            .unwrap()
+            .get(task_type)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn all(&self) -> BTreeMap<String, TaskPreference> {
+        self.prefs.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, task_type: &str, preference: TaskPreference) -> anyhow::Result<()> {
+        let mut prefs = self.prefs.lock().unwrap();
+        prefs.insert(task_type.to_string(), preference);
+        self.save(&prefs)
+    }
+
+    pub fn reset(&self, task_type: &str) -> anyhow::Result<()> {
+        let mut prefs = self.prefs.lock().unwrap();
+        prefs.remove(task_type);
+        self.save(&prefs)
+    }
+
+    fn save(&self, prefs: &BTreeMap<String, TaskPreference>) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        crate::fs::write_atomic(path, &serde_json::to_vec_pretty(prefs)?)
+            .with_context(|| format!("write {}", path.display()))
+    }
+}
+
+/// Asks backends not to post a completion notification when progress reaches 100%.
+pub(crate) fn hide_completion(state: &mut HashMap<String, String>) {
+    state.insert("completion".to_string(), "hidden".to_string());
+}
+
+fn read(path: &Path) -> anyhow::Result<BTreeMap<String, TaskPreference>> {
+    let data = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!(
+                "live-activity-preferences-{name}-{}",
+                std::process::id()
+            ))
+            .join("preferences.json")
+    }
+
+    fn muted() -> TaskPreference {
+        TaskPreference {
+            enabled: false,
+            silent: true,
+            show_completion: false,
+        }
+    }
+
+    #[test]
+    fn unknown_task_types_use_the_defaults() {
+        let store = PreferenceStore::load(None);
+        assert_eq!(store.get("sync"), TaskPreference::default());
+        assert!(store.all().is_empty());
+
+        store.set("sync", muted()).unwrap();
+        assert_eq!(store.get("sync"), muted());
+        store.reset("sync").unwrap();
+        assert_eq!(store.get("sync"), TaskPreference::default());
+    }
+
+    #[test]
+    fn changes_persist_across_loads() {
+        let path = temp_path("persist");
+        let store = PreferenceStore::load(Some(path.clone()));
+        store.set("sync", muted()).unwrap();
+        store.set("upload", TaskPreference::default()).unwrap();
+        store.reset("upload").unwrap();
+
+        let loaded = PreferenceStore::load(Some(path.clone()));
+        assert_eq!(
+            loaded.all(),
+            BTreeMap::from([("sync".to_string(), muted())])
+        );
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn invalid_json_starts_empty_and_is_replaced_on_save() {
+        let path = temp_path("invalid");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"{not json").unwrap();
+
+        let store = PreferenceStore::load(Some(path.clone()));
+        assert!(store.all().is_empty());
+        store.set("sync", muted()).unwrap();
+        assert_eq!(
+            PreferenceStore::load(Some(path.clone())).get("sync"),
+            muted()
+        );
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use chrono::Timelike;
+
+use crate::batch;
+use crate::clock;
+use crate::models::*;
+
+/// How long a system do-not-disturb reading is trusted before asking again.
+const DND_CACHE: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct Inner {
+    policy: Option<QuietHours>,
+    dnd: Option<(SystemTime, Option<bool>)>,
+    deferred: Option<CreateLiveActivityRequest>,
+    completed: Vec<String>,
+}
+
+/// Quiet-hours state shared by every activity call.
+#[derive(Default)]
+pub(crate) struct QuietGate {
+    inner: Mutex<Inner>,
+}
+
+/// Minutes since local midnight.
+pub(crate) fn local_minute() -> u16 {
+    let now = chrono::DateTime::<chrono::Local>::from(clock::now());
+    (now.hour() * 60 + now.minute()) as u16
+}
+
+impl QuietGate {
+    pub fn new(policy: Option<QuietHours>) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                policy,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn policy(&self) -> Option<QuietHours> {
+        self.inner.lock().unwrap().policy.clone()
+    }
+
+    pub fn set_policy(&self, policy: Option<QuietHours>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.policy = policy;
+        inner.dnd = None;
+    }
+
+    pub fn mode(&self) -> QuietMode {
+        let inner = self.inner.lock().unwrap();
+        inner.policy.as_ref().map(|p| p.mode).unwrap_or_default()
+    }
+
+    /// Why the plugin is quiet at `minute`; `dnd` is only asked when the policy honors it.
+    pub fn reason(&self, minute: u16, dnd: impl FnOnce() -> Option<bool>) -> Option<QuietReason> {
+        let mut inner = self.inner.lock().unwrap();
+        let policy = inner.policy.as_ref()?;
+        if policy.windows.iter().any(|w| w.contains(minute)) {
+            return Some(QuietReason::Schedule);
+        }
+        if !policy.honor_system_dnd {
+            return None;
+        }
+        let cached = inner
+            .dnd
+            .filter(|(at, _)| elapsed(*at) < DND_CACHE)
+            .map(|(_, dnd)| dnd);
+        let dnd = match cached {
+            Some(dnd) => dnd,
+            None => {
+                let dnd = dnd();
+                inner.dnd = Some((clock::now(), dnd));
+                dnd
+            }
+        };
+        dnd.unwrap_or(false).then_some(QuietReason::SystemDnd)
+    }
+
+    pub fn defer(&self, payload: CreateLiveActivityRequest) {
+        self.inner.lock().unwrap().deferred = Some(payload);
+    }
+
+    /// Replaces the state of the deferred activity, as an update replaces the
+    /// state of a shown one; `false` when nothing is deferred.
+    pub fn update_deferred(&self, state: HashMap<String, String>) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(deferred) = inner.deferred.as_mut() else {
+            return false;
+        };
+        *deferred.activity_content.state_mut() = state;
+        true
+    }
+
+    pub fn take_deferred(&self) -> Option<CreateLiveActivityRequest> {
+        self.inner.lock().unwrap().deferred.take()
+    }
+
+    pub fn completed(&self, title: String) {
+        self.inner.lock().unwrap().completed.push(title);
+    }
+
+    pub fn take_completed(&self) -> Vec<String> {
+        std::mem::take(&mut self.inner.lock().unwrap().completed)
+    }
+
+    /// Whether there is anything to release once the quiet period ends.
+    pub fn has_pending(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        inner.deferred.is_some() || !inner.completed.is_empty()
+    }
+
+    pub fn status(&self, reason: Option<QuietReason>) -> QuietStatus {
+        let inner = self.inner.lock().unwrap();
+        QuietStatus {
+            policy: inner.policy.clone(),
+            reason,
+            deferred: inner
+                .deferred
+                .as_ref()
+                .map(|d| d.activity_content.id().to_string()),
+            pending_completions: inner.completed.len(),
+        }
+    }
+}
+
+fn elapsed(since: SystemTime) -> Duration {
+    clock::now().duration_since(since).unwrap_or_default()
+}
+
+/// Asks backends to deliver without sound or banner.
+pub(crate) fn mark_silent(state: &mut HashMap<String, String>) {
+    state.insert("silent".to_string(), "true".to_string());
+}
+
+pub(crate) fn summary(titles: &[String]) -> AlertRequest {
+    AlertRequest {
+        title: format!("勿扰期间完成了 {} 项任务", titles.len()),
+        body: batch::list(titles),
+        silent: false,
+        group: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_contain_their_start_but_not_their_end() {
+        let day = QuietWindow::new((9, 0), (17, 30));
+        assert!(!day.contains(8 * 60 + 59));
+        assert!(day.contains(9 * 60));
+        assert!(day.contains(17 * 60 + 29));
+        assert!(!day.contains(17 * 60 + 30));
+        assert!(!QuietWindow::new((9, 0), (9, 0)).contains(9 * 60));
+    }
+
+    #[test]
+    fn windows_ending_before_they_start_span_midnight() {
+        let night = QuietWindow::new((22, 0), (7, 0));
+        assert!(!night.contains(21 * 60 + 59));
+        assert!(night.contains(22 * 60));
+        assert!(night.contains(23 * 60 + 59));
+        assert!(night.contains(0));
+        assert!(night.contains(6 * 60 + 59));
+        assert!(!night.contains(7 * 60));
+        assert!(!night.contains(12 * 60));
+    }
+
+    #[test]
+    fn summary_lists_only_the_first_titles() {
+        let titles: Vec<_> = (1..=7).map(|n| format!("t{n}")).collect();
+        let alert = summary(&titles);
+        assert_eq!(alert.title, "勿扰期间完成了 7 项任务");
+        assert_eq!(alert.body, "t1、t2、t3、t4、t5 等 7 项");
+        assert_eq!(summary(&titles[..2]).body, "t1、t2");
+    }
+}
@@ -0,0 +1,230 @@
+use std::cell::Cell;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+
+use crate::clock::{self, now_millis};
+use crate::models::*;
+
+thread_local! {
+    /// Set while this thread replays a session; the replayed calls are not
+    /// recorded a second time.
+    static REPLAYING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Appends every create/update/remove call to a JSONL file so a session can be replayed later.
+pub(crate) struct Recorder {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Runs `f` and writes the call together with its outcome.
+    pub fn record(
+        &self,
+        call: RecordedCall,
+        f: impl FnOnce() -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        if REPLAYING.with(Cell::get) {
+            return f();
+        }
+        let at = now_millis();
+        let started = Instant::now();
+        let result = f();
+        let entry = RecordedEntry {
+            at,
+            call,
+            elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+            error: result.as_ref().err().map(|err| format!("{err:#}")),
+        };
+        if let Err(err) = self.write(&entry) {
+            tracing::warn!(error = %err, path = %self.path.display(), "failed to record call");
+        }
+        result
+    }
+
+    fn write(&self, entry: &RecordedEntry) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+pub(crate) fn read_session(path: &Path) -> anyhow::Result<Vec<RecordedEntry>> {
+    let file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut entries = Vec::new();
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .with_context(|| format!("{}:{}: invalid entry", path.display(), n + 1))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Feeds recorded calls to `apply`, keeping the original gaps divided by `speed`.
+///
+/// `speed` of `1.0` replays in real time; `0.0` or less replays back to back.
+/// Fails before the first call whose gap `speed` stretches past what a
+/// [`Duration`] holds.
+pub(crate) fn replay(
+    entries: Vec<RecordedEntry>,
+    speed: f64,
+    apply: impl FnMut(RecordedCall) -> anyhow::Result<()>,
+) -> anyhow::Result<ReplaySummary> {
+    replay_with(entries, speed, clock::sleep, apply)
+}
+
+/// [`replay`] waiting through `sleep`.
+fn replay_with(
+    entries: Vec<RecordedEntry>,
+    speed: f64,
+    mut sleep: impl FnMut(Duration),
+    mut apply: impl FnMut(RecordedCall) -> anyhow::Result<()>,
+) -> anyhow::Result<ReplaySummary> {
+    let _replaying = Replaying::start();
+    let mut summary = ReplaySummary::default();
+    let mut last_at = None;
+    for entry in entries {
+        if let Some(prev) = last_at {
+            let gap = entry.at.saturating_sub(prev);
+            if speed > 0.0 && gap > 0 {
+                let wait = Duration::try_from_secs_f64(gap as f64 / 1000.0 / speed)
+                    .map_err(|_| anyhow::anyhow!("replay speed {speed} is too slow"))?;
+                sleep(wait);
+            }
+        }
+        last_at = Some(entry.at);
+        summary.total += 1;
+        if let Err(err) = apply(entry.call) {
+            summary.failed += 1;
+            tracing::warn!(error = %err, "replayed call failed");
+        }
+    }
+    Ok(summary)
+}
+
+/// Marks the thread as replaying until dropped, also when `apply` panics.
+struct Replaying(bool);
+
+impl Replaying {
+    fn start() -> Self {
+        Self(REPLAYING.with(|r| r.replace(true)))
+    }
+}
+
+impl Drop for Replaying {
+    fn drop(&mut self) {
+        REPLAYING.with(|r| r.set(self.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::clock::{Clock, ManualClock};
+
+    fn entry(at: u64, call: RecordedCall) -> RecordedEntry {
+        RecordedEntry {
+            at,
+            call,
+            elapsed_ms: 1.0,
+            error: None,
+        }
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("live-activity-{name}-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn recorded_session_replays_with_scaled_gaps() {
+        let path = temp_file("replay");
+        let recorder = Recorder::open(&path).unwrap();
+        recorder
+            .write(&entry(1_000, RecordedCall::End { id: "a".into() }))
+            .unwrap();
+        recorder.write(&entry(1_500, RecordedCall::Remove)).unwrap();
+        recorder
+            .write(&entry(4_000, RecordedCall::End { id: "b".into() }))
+            .unwrap();
+        let entries = read_session(&path).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let mut applied = Vec::new();
+        let summary = replay_with(
+            entries,
+            2.0,
+            |wait| clock.advance(wait),
+            |call| {
+                let at = clock.now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+                // 回放时经过的调用不会再写进记录里。
+                recorder.record(call.clone(), || Ok(()))?;
+                let failed = matches!(&call, RecordedCall::Remove);
+                applied.push((at, call));
+                if failed {
+                    anyhow::bail!("nothing to remove");
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!((summary.total, summary.failed), (3, 1));
+        let times: Vec<_> = applied.iter().map(|(at, _)| *at).collect();
+        assert_eq!(times, [0, 250, 1_500]);
+        assert!(matches!(&applied[2].1, RecordedCall::End { id } if id == "b"));
+        assert_eq!(read_session(&path).unwrap().len(), 3);
+
+        // 回放结束后照常记录。
+        recorder.record(RecordedCall::Remove, || Ok(())).unwrap();
+        assert_eq!(read_session(&path).unwrap().len(), 4);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn too_slow_a_replay_fails_instead_of_panicking() {
+        let entries = vec![
+            entry(0, RecordedCall::Remove),
+            entry(u64::MAX / 2, RecordedCall::Remove),
+        ];
+        let mut applied = 0;
+        let result = replay_with(
+            entries,
+            1e-300,
+            |_| panic!("must not wait"),
+            |_| {
+                applied += 1;
+                Ok(())
+            },
+        );
+        assert!(result.is_err());
+        assert_eq!(applied, 1);
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::clock::now_millis;
+use crate::models::*;
+use crate::schedule::Timing;
+
+struct Record {
+    content: ActivityContent,
+    state: HashMap<String, String>,
+    created_at: u64,
+    updated_at: u64,
+    presenter: &'static str,
+    stalled: bool,
+    /// Last create, update or heartbeat.
+    alive_at: u64,
+    timing: Timing,
+}
+
+#[derive(Default)]
+struct Inner {
+    current: Option<String>,
+    records: HashMap<String, Record>,
+}
+
+/// Last known state of every activity created through the plugin.
+///
+/// The backends only keep what they need to drive the OS presentation, so this
+/// is the source of truth for the query API. One activity holds the visible
+/// slot (`current`); preempted and lower-priority ones wait here as queued.
+#[derive(Default)]
+pub(crate) struct ActivityRegistry {
+    inner: Mutex<Inner>,
+}
+
+impl ActivityRegistry {
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().records.len()
+    }
+
+    pub fn current_id(&self) -> Option<String> {
+        self.inner.lock().unwrap().current.clone()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.inner.lock().unwrap().records.contains_key(id)
+    }
+
+    pub fn current_priority(&self) -> Option<ActivityPriority> {
+        let inner = self.inner.lock().unwrap();
+        Some(
+            inner
+                .records
+                .get(inner.current.as_ref()?)?
+                .content
+                .priority(),
+        )
+    }
+
+    /// Presenter showing the current activity.
+    pub fn current_presenter(&self) -> Option<&'static str> {
+        let inner = self.inner.lock().unwrap();
+        Some(inner.records.get(inner.current.as_ref()?)?.presenter)
+    }
+
+    /// Content of the current activity with its latest state.
+    pub fn current_content(&self) -> Option<ActivityContent> {
+        let id = self.current_id()?;
+        self.content(&id)
+    }
+
+    /// Content of the activity `id` with its latest state.
+    pub fn content(&self, id: &str) -> Option<ActivityContent> {
+        let inner = self.inner.lock().unwrap();
+        let record = inner.records.get(id)?;
+        let mut content = record.content.clone();
+        *content.state_mut() = record.state.clone();
+        Some(content)
+    }
+
+    pub fn created(&self, content: ActivityContent, presenter: &'static str) {
+        let now = now_millis();
+        let id = content.id().to_string();
+        let mut inner = self.inner.lock().unwrap();
+        // 后端同一时间只展示一个活动，同优先级的新建会顶掉旧的。
+        if let Some(prev) = inner.current.take() {
+            inner.records.remove(&prev);
+        }
+        inner.records.insert(
+            id.clone(),
+            Record {
+                state: content.state().clone(),
+                timing: Timing::new(content.start_at(), content.end_at(), now),
+                content,
+                created_at: now,
+                updated_at: now,
+                presenter,
+                stalled: false,
+                alive_at: now,
+            },
+        );
+        inner.current = Some(id);
+    }
+
+    /// Tracks an activity that does not get the visible slot yet.
+    pub fn queued(&self, content: ActivityContent, presenter: &'static str) {
+        let now = now_millis();
+        let mut inner = self.inner.lock().unwrap();
+        inner.records.insert(
+            content.id().to_string(),
+            Record {
+                state: content.state().clone(),
+                timing: Timing::new(content.start_at(), content.end_at(), now),
+                content,
+                created_at: now,
+                updated_at: now,
+                presenter,
+                stalled: false,
+                alive_at: now,
+            },
+        );
+    }
+
+    /// Gives up the visible slot, keeping the current activity as queued.
+    pub fn preempted(&self, presenter: &'static str) -> Option<String> {
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.current.take()?;
+        if let Some(record) = inner.records.get_mut(&id) {
+            record.presenter = presenter;
+        }
+        Some(id)
+    }
+
+    /// The queued activity that should get the slot next: highest priority,
+    /// then the oldest.
+    pub fn next_queued(&self) -> Option<ActivityContent> {
+        let inner = self.inner.lock().unwrap();
+        let (_, record) = inner
+            .records
+            .iter()
+            .filter(|(id, _)| inner.current.as_ref() != Some(*id))
+            .max_by_key(|(_, r)| (r.content.priority(), std::cmp::Reverse(r.created_at)))?;
+        let mut content = record.content.clone();
+        *content.state_mut() = record.state.clone();
+        Some(content)
+    }
+
+    /// Hands the visible slot to the queued activity `id`.
+    pub fn promoted(&self, id: &str, presenter: &'static str) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(record) = inner.records.get_mut(id) {
+            record.presenter = presenter;
+            inner.current = Some(id.to_string());
+        }
+    }
+
+    /// Stores the new state; `true` when this ends a stall.
+    pub fn updated(&self, id: &str, state: &HashMap<String, String>) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(record) = inner.records.get_mut(id) else {
+            return false;
+        };
+        record.state = state.clone();
+        record.updated_at = now_millis();
+        record.alive_at = record.updated_at;
+        std::mem::take(&mut record.stalled)
+    }
+
+    /// The owner of `id` is still there; `false` when there is no such activity.
+    pub fn heartbeat(&self, id: &str) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.records.get_mut(id) {
+            Some(record) => {
+                record.alive_at = now_millis();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn alive_at(&self, id: &str) -> Option<u64> {
+        Some(self.inner.lock().unwrap().records.get(id)?.alive_at)
+    }
+
+    /// Timings of the activities that have a start or end time.
+    pub fn timings(&self) -> Vec<(String, Timing)> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .records
+            .iter()
+            .filter(|(_, record)| record.timing.is_scheduled())
+            .map(|(id, record)| (id.clone(), record.timing.clone()))
+            .collect()
+    }
+
+    /// Countdown shown for `id` while it waits for its `start_at`.
+    pub fn start_hint(&self, id: &str) -> Option<String> {
+        let inner = self.inner.lock().unwrap();
+        let timing = &inner.records.get(id)?.timing;
+        if timing.started {
+            return None;
+        }
+        timing.hint.clone()
+    }
+
+    pub fn set_start_hint(&self, id: &str, hint: String) {
+        if let Some(record) = self.inner.lock().unwrap().records.get_mut(id) {
+            record.timing.hint = Some(hint);
+        }
+    }
+
+    pub fn set_started(&self, id: &str) {
+        if let Some(record) = self.inner.lock().unwrap().records.get_mut(id) {
+            record.timing.started = true;
+            record.timing.hint = None;
+        }
+    }
+
+    /// Activities owned by the webview `label`.
+    pub fn owned_by(&self, label: &str) -> Vec<String> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .records
+            .iter()
+            .filter(|(_, record)| record.content.owner() == Some(label))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// `created_at` and `updated_at` of the activity `id`.
+    pub fn timestamps(&self, id: &str) -> Option<(u64, u64)> {
+        let inner = self.inner.lock().unwrap();
+        let record = inner.records.get(id)?;
+        Some((record.created_at, record.updated_at))
+    }
+
+    /// Marks `id` as stalled; `true` when it was not already.
+    pub fn stalled(&self, id: &str) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.records.get_mut(id) {
+            Some(record) => !std::mem::replace(&mut record.stalled, true),
+            None => false,
+        }
+    }
+
+    /// The current activity fell back to another presenter.
+    pub fn moved(&self, presenter: &'static str) {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(id) = inner.current.clone() else {
+            return;
+        };
+        if let Some(record) = inner.records.get_mut(&id) {
+            record.presenter = presenter;
+        }
+    }
+
+    pub fn removed(&self, id: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.records.remove(id);
+        if inner.current.as_deref() == Some(id) {
+            inner.current = None;
+        }
+    }
+
+    pub fn snapshots(&self, visible: impl Fn(&str) -> bool) -> Vec<ActivitySnapshot> {
+        let inner = self.inner.lock().unwrap();
+        let mut list: Vec<_> = inner
+            .records
+            .iter()
+            .map(|(id, record)| snapshot(id, record, &visible))
+            .collect();
+        list.sort_by_key(|s| s.created_at);
+        list
+    }
+
+    pub fn snapshot(&self, id: &str, visible: impl Fn(&str) -> bool) -> Option<ActivitySnapshot> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .records
+            .get(id)
+            .map(|record| snapshot(id, record, &visible))
+    }
+}
+
+fn snapshot(id: &str, record: &Record, visible: &impl Fn(&str) -> bool) -> ActivitySnapshot {
+    ActivitySnapshot {
+        id: id.to_string(),
+        content: record.content.clone(),
+        state: record.state.clone(),
+        created_at: record.created_at,
+        updated_at: record.updated_at,
+        backend: record.presenter.to_string(),
+        visible: visible(id),
+        stalled: record.stalled,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_support::task;
+
+    fn content(id: &str, priority: ActivityPriority) -> ActivityContent {
+        ActivityContent::TaskQueue(ActivityContentTaskQueue {
+            priority,
+            ..task(id)
+        })
+    }
+
+    #[test]
+    fn preempted_keeps_the_activity_queued() {
+        let registry = ActivityRegistry::default();
+        registry.created(content("a", ActivityPriority::Normal), "mock");
+        assert_eq!(registry.preempted("queued").as_deref(), Some("a"));
+        assert_eq!(registry.current_id(), None);
+        assert_eq!(registry.current_presenter(), None);
+        assert!(registry.contains("a"));
+        assert_eq!(
+            registry
+                .snapshot("a", |_| false)
+                .map(|s| s.backend)
+                .as_deref(),
+            Some("queued")
+        );
+        assert_eq!(registry.preempted("queued"), None);
+    }
+
+    #[test]
+    fn next_queued_prefers_priority_then_age() {
+        let registry = ActivityRegistry::default();
+        assert!(registry.next_queued().is_none());
+        registry.queued(content("low", ActivityPriority::Low), "queued");
+        registry.queued(content("high-old", ActivityPriority::High), "queued");
+        // 创建时间按毫秒记，隔开一点才能分出先后。
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        registry.queued(content("high-new", ActivityPriority::High), "queued");
+        registry.created(content("current", ActivityPriority::Critical), "mock");
+
+        let next = registry.next_queued().unwrap();
+        assert_eq!(next.id(), "high-old");
+        registry.removed("high-old");
+        assert_eq!(registry.next_queued().unwrap().id(), "high-new");
+        registry.removed("high-new");
+        assert_eq!(registry.next_queued().unwrap().id(), "low");
+        registry.removed("low");
+        // 占着位置的活动不算排队。
+        assert!(registry.next_queued().is_none());
+    }
+
+    #[test]
+    fn promoted_takes_the_slot() {
+        let registry = ActivityRegistry::default();
+        registry.created(content("a", ActivityPriority::Normal), "mock");
+        registry.preempted("queued");
+        registry.created(content("b", ActivityPriority::High), "mock");
+        // 新建只顶掉占着位置的活动，排队的留着。
+        assert!(registry.contains("a"));
+        registry.removed("b");
+        registry.promoted("a", "mock");
+        assert_eq!(registry.current_id().as_deref(), Some("a"));
+        assert_eq!(registry.current_presenter(), Some("mock"));
+    }
+}
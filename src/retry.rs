@@ -0,0 +1,266 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::models::*;
+use crate::Error;
+
+/// Runs `f` until it succeeds, fails with an error `policy` does not retry, or
+/// runs out of attempts, doubling the pause between attempts.
+pub(crate) fn retry<T>(
+    policy: &RetryPolicy,
+    op: &'static str,
+    presenter: &str,
+    f: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    retry_with(policy, op, presenter, crate::clock::sleep, f)
+}
+
+/// [`retry`] pausing through `sleep`.
+fn retry_with<T>(
+    policy: &RetryPolicy,
+    op: &'static str,
+    presenter: &str,
+    mut sleep: impl FnMut(Duration),
+    mut f: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let mut backoff = Duration::from_millis(policy.initial_backoff_ms);
+    let max_backoff = Duration::from_millis(policy.max_backoff_ms);
+    let mut attempt = 1;
+    loop {
+        let err = match f() {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        let kind = classify(&err);
+        if attempt >= policy.max_attempts || !policy.retry_on.contains(&kind) {
+            return Err(err);
+        }
+        tracing::debug!(op, presenter, attempt, ?kind, error = %err, "transient failure, retrying");
+        sleep(backoff);
+        backoff = (backoff * 2).min(max_backoff);
+        attempt += 1;
+    }
+}
+
+/// Sorts a backend error into the kinds a [`RetryPolicy`] can name.
+pub(crate) fn classify(err: &anyhow::Error) -> FailureKind {
+    for cause in err.chain() {
+        if let Some(Error::PermissionDenied) = cause.downcast_ref::<Error>() {
+            return FailureKind::PermissionDenied;
+        }
+        if let Some(err) = cause.downcast_ref::<std::io::Error>() {
+            if let Some(kind) = classify_io(err.kind()) {
+                return kind;
+            }
+        }
+        #[cfg(all(target_os = "linux", feature = "linux-dbus"))]
+        if let Some(kind) = cause
+            .downcast_ref::<dbus::Error>()
+            .and_then(|err| classify_dbus(err.name()?))
+        {
+            return kind;
+        }
+        #[cfg(all(windows, feature = "windows-toast"))]
+        if let Some(kind) = cause
+            .downcast_ref::<windows::core::Error>()
+            .and_then(|err| classify_hresult(err.code().0 as u32))
+        {
+            return kind;
+        }
+    }
+    FailureKind::Other
+}
+
+fn classify_io(kind: std::io::ErrorKind) -> Option<FailureKind> {
+    use std::io::ErrorKind::*;
+    match kind {
+        TimedOut | WouldBlock => Some(FailureKind::Timeout),
+        Interrupted => Some(FailureKind::Busy),
+        ConnectionRefused | ConnectionReset | ConnectionAborted | NotConnected | BrokenPipe => {
+            Some(FailureKind::Disconnected)
+        }
+        PermissionDenied => Some(FailureKind::PermissionDenied),
+        _ => None,
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "linux-dbus"))]
+fn classify_dbus(name: &str) -> Option<FailureKind> {
+    match name.strip_prefix("org.freedesktop.DBus.Error.")? {
+        "NoReply" | "Timeout" | "TimedOut" => Some(FailureKind::Timeout),
+        "LimitsExceeded" | "NoMemory" => Some(FailureKind::Busy),
+        "ServiceUnknown" | "NameHasNoOwner" | "Disconnected" | "NoServer" => {
+            Some(FailureKind::Disconnected)
+        }
+        "AccessDenied" | "AuthFailed" => Some(FailureKind::PermissionDenied),
+        _ => None,
+    }
+}
+
+#[cfg(all(windows, feature = "windows-toast"))]
+fn classify_hresult(code: u32) -> Option<FailureKind> {
+    // RPC_E_* 多是通知平台或 COM 套间一时忙不过来，过一会再试通常就好了。
+    match code {
+        0x8001_0001 /* RPC_E_CALL_REJECTED */
+        | 0x8001_010A /* RPC_E_SERVERCALL_RETRYLATER */
+        | 0x8001_0106 /* RPC_E_CHANGED_MODE */ => Some(FailureKind::Busy),
+        0x8001_0108 /* RPC_E_DISCONNECTED */
+        | 0x8007_06BA /* RPC_S_SERVER_UNAVAILABLE */ => Some(FailureKind::Disconnected),
+        0x8007_05B4 /* ERROR_TIMEOUT */ => Some(FailureKind::Timeout),
+        0x8007_0005 /* E_ACCESSDENIED */ => Some(FailureKind::PermissionDenied),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::sync::Mutex;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use anyhow::Context;
+
+    use super::*;
+    use crate::clock::{Clock, ManualClock};
+
+    #[test]
+    fn classifies_io_errors() {
+        let table = [
+            (ErrorKind::TimedOut, FailureKind::Timeout),
+            (ErrorKind::WouldBlock, FailureKind::Timeout),
+            (ErrorKind::Interrupted, FailureKind::Busy),
+            (ErrorKind::ConnectionRefused, FailureKind::Disconnected),
+            (ErrorKind::ConnectionReset, FailureKind::Disconnected),
+            (ErrorKind::BrokenPipe, FailureKind::Disconnected),
+            (ErrorKind::PermissionDenied, FailureKind::PermissionDenied),
+            (ErrorKind::NotFound, FailureKind::Other),
+            (ErrorKind::InvalidData, FailureKind::Other),
+        ];
+        for (kind, expected) in table {
+            let err = anyhow::Error::from(std::io::Error::from(kind));
+            assert_eq!(classify(&err), expected, "{kind:?}");
+            // 包了上下文也要认得出来。
+            let err = Err::<(), _>(std::io::Error::from(kind))
+                .context("send notification")
+                .unwrap_err();
+            assert_eq!(classify(&err), expected, "{kind:?} with context");
+        }
+    }
+
+    #[test]
+    fn classifies_plugin_errors() {
+        let denied = anyhow::Error::from(Error::PermissionDenied).context("create");
+        assert_eq!(classify(&denied), FailureKind::PermissionDenied);
+        assert_eq!(classify(&anyhow::anyhow!("boom")), FailureKind::Other);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "linux-dbus"))]
+    #[test]
+    fn classifies_dbus_errors() {
+        let table = [
+            ("org.freedesktop.DBus.Error.NoReply", FailureKind::Timeout),
+            ("org.freedesktop.DBus.Error.Timeout", FailureKind::Timeout),
+            (
+                "org.freedesktop.DBus.Error.LimitsExceeded",
+                FailureKind::Busy,
+            ),
+            (
+                "org.freedesktop.DBus.Error.ServiceUnknown",
+                FailureKind::Disconnected,
+            ),
+            (
+                "org.freedesktop.DBus.Error.NameHasNoOwner",
+                FailureKind::Disconnected,
+            ),
+            (
+                "org.freedesktop.DBus.Error.AccessDenied",
+                FailureKind::PermissionDenied,
+            ),
+            ("org.freedesktop.DBus.Error.InvalidArgs", FailureKind::Other),
+            ("org.freedesktop.Notifications.Error", FailureKind::Other),
+        ];
+        for (name, expected) in table {
+            let err = anyhow::Error::from(dbus::Error::new_custom(name, "failed"));
+            assert_eq!(classify(&err), expected, "{name}");
+        }
+    }
+
+    #[cfg(all(windows, feature = "windows-toast"))]
+    #[test]
+    fn classifies_hresults() {
+        let table = [
+            (0x8001_0001, FailureKind::Busy),
+            (0x8001_010A, FailureKind::Busy),
+            (0x8001_0108, FailureKind::Disconnected),
+            (0x8007_06BA, FailureKind::Disconnected),
+            (0x8007_05B4, FailureKind::Timeout),
+            (0x8007_0005, FailureKind::PermissionDenied),
+            (0x8000_4005, FailureKind::Other),
+        ];
+        for (code, expected) in table {
+            let err = anyhow::Error::from(windows::core::Error::from(windows::core::HRESULT(
+                code as i32,
+            )));
+            assert_eq!(classify(&err), expected, "{code:#x}");
+        }
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 400,
+            ..Default::default()
+        }
+    }
+
+    /// Runs `retry_with` on a manual clock; returns the result and the time of
+    /// every attempt, in milliseconds from the start.
+    fn run(policy: &RetryPolicy, mut f: impl FnMut(usize) -> Result<()>) -> (Result<()>, Vec<u64>) {
+        let clock = ManualClock::new(UNIX_EPOCH);
+        let attempts = Mutex::new(Vec::new());
+        let since_start =
+            |now: SystemTime| now.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        let result = retry_with(
+            policy,
+            "create",
+            "mock",
+            |pause| clock.advance(pause),
+            || {
+                let mut attempts = attempts.lock().unwrap();
+                attempts.push(since_start(clock.now()));
+                f(attempts.len())
+            },
+        );
+        (result, attempts.into_inner().unwrap())
+    }
+
+    fn timeout() -> anyhow::Error {
+        std::io::Error::from(ErrorKind::TimedOut).into()
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let (result, attempts) = run(&policy(5), |_| Err(timeout()));
+        assert!(result.is_err());
+        assert_eq!(attempts, [0, 100, 300, 700, 1_100]);
+    }
+
+    #[test]
+    fn stops_once_it_succeeds() {
+        let (result, attempts) = run(&policy(5), |n| if n < 3 { Err(timeout()) } else { Ok(()) });
+        assert!(result.is_ok());
+        assert_eq!(attempts, [0, 100, 300]);
+    }
+
+    #[test]
+    fn fails_right_away_on_errors_not_retried() {
+        let (result, attempts) = run(&policy(5), |_| Err(Error::PermissionDenied.into()));
+        assert!(result.is_err());
+        assert_eq!(attempts, [0]);
+
+        let (_, attempts) = run(&RetryPolicy::never(), |_| Err(timeout()));
+        assert_eq!(attempts, [0]);
+    }
+}
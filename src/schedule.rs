@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+/// Start and end times of an activity, in milliseconds since the Unix epoch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Timing {
+    pub start_at: Option<u64>,
+    pub end_at: Option<u64>,
+    /// The pre-announcement is over, or there never was one.
+    pub started: bool,
+    /// Countdown last shown while waiting for `start_at`.
+    pub hint: Option<String>,
+}
+
+/// What the scheduler has to do for an activity right now.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Step {
+    Idle,
+    /// Show the new countdown text.
+    Countdown(String),
+    /// `start_at` has come; switch to the real state.
+    Start,
+    /// `end_at` has come.
+    Expire,
+}
+
+impl Timing {
+    pub fn new(start_at: Option<u64>, end_at: Option<u64>, now: u64) -> Self {
+        let waiting = start_at.filter(|start| *start > now);
+        Self {
+            start_at,
+            end_at,
+            started: waiting.is_none(),
+            hint: waiting.map(|start| countdown(start - now)),
+        }
+    }
+
+    pub fn is_scheduled(&self) -> bool {
+        self.start_at.is_some() || self.end_at.is_some()
+    }
+
+    pub fn step(&self, now: u64) -> Step {
+        if self.end_at.is_some_and(|end| end <= now) {
+            return Step::Expire;
+        }
+        let Some(start) = self.start_at.filter(|_| !self.started) else {
+            return Step::Idle;
+        };
+        if start <= now {
+            return Step::Start;
+        }
+        let hint = countdown(start - now);
+        if self.hint.as_deref() == Some(hint.as_str()) {
+            Step::Idle
+        } else {
+            Step::Countdown(hint)
+        }
+    }
+}
+
+/// "5 分钟后开始" for `remaining` milliseconds, rounded up to the minute.
+pub(crate) fn countdown(remaining: u64) -> String {
+    if remaining < 60_000 {
+        return "即将开始".to_string();
+    }
+    let minutes = remaining.div_ceil(60_000);
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m} 分钟后开始"),
+        (h, 0) => format!("{h} 小时后开始"),
+        (h, m) => format!("{h} 小时 {m} 分钟后开始"),
+    }
+}
+
+/// Replaces the progress with `hint` until the activity really starts.
+pub(crate) fn announce(state: &mut HashMap<String, String>, hint: &str) {
+    state.remove("progress");
+    state.remove("percent");
+    state.insert("hint".to_string(), hint.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+    use crate::clock::{Clock, ManualClock};
+
+    const MINUTE: u64 = 60_000;
+
+    fn millis(clock: &ManualClock) -> u64 {
+        clock.now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+    }
+
+    #[test]
+    fn counts_down_then_starts_then_expires() {
+        let clock = ManualClock::default();
+        let now = millis(&clock);
+        let mut timing = Timing::new(Some(now + 5 * MINUTE), Some(now + 10 * MINUTE), now);
+        assert!(!timing.started);
+        assert_eq!(timing.hint.as_deref(), Some("5 分钟后开始"));
+        assert_eq!(timing.step(now), Step::Idle);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(
+            timing.step(millis(&clock)),
+            Step::Countdown("4 分钟后开始".to_string())
+        );
+        timing.hint = Some("4 分钟后开始".to_string());
+
+        clock.advance(Duration::from_secs(210));
+        assert_eq!(timing.step(millis(&clock)), Step::Start);
+        timing.started = true;
+        assert_eq!(timing.step(millis(&clock)), Step::Idle);
+
+        clock.advance(Duration::from_secs(300));
+        assert_eq!(timing.step(millis(&clock)), Step::Expire);
+    }
+
+    #[test]
+    fn start_in_the_past_is_not_announced() {
+        let timing = Timing::new(Some(0), None, MINUTE);
+        assert!(timing.started);
+        assert_eq!(timing.hint, None);
+        assert_eq!(timing.step(MINUTE), Step::Idle);
+    }
+
+    #[test]
+    fn countdown_text() {
+        assert_eq!(countdown(30_000), "即将开始");
+        assert_eq!(countdown(MINUTE + 1), "2 分钟后开始");
+        assert_eq!(countdown(60 * MINUTE), "1 小时后开始");
+        assert_eq!(countdown(95 * MINUTE), "1 小时 35 分钟后开始");
+    }
+
+    #[test]
+    fn manual_clock_wakes_sleepers_when_advanced() {
+        let clock = Arc::new(ManualClock::default());
+        let sleeper = {
+            let clock = clock.clone();
+            std::thread::spawn(move || {
+                let from = clock.now();
+                clock.sleep(Duration::from_secs(60));
+                clock.now().duration_since(from).unwrap()
+            })
+        };
+        while !sleeper.is_finished() {
+            clock.advance(Duration::from_secs(10));
+            std::thread::yield_now();
+        }
+        assert!(sleeper.join().unwrap() >= Duration::from_secs(60));
+    }
+}
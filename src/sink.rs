@@ -0,0 +1,184 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Mutex;
+
+use crate::metrics::Metrics;
+use crate::models::*;
+
+/// Receives every activity event alongside the OS backend.
+///
+/// Use it to mirror progress somewhere else, e.g. a paired wearable, a log or
+/// a status widget. Sinks run on the calling thread once the operation is
+/// over and the plugin released its lock, so a sink may call back into the
+/// plugin or drop an `ActivityGuard`; the events of that nested call follow
+/// the current one. Errors and panics are logged and never reach the caller,
+/// so a failing sink cannot break the OS presentation.
+pub trait ActivitySink: Send + Sync {
+    fn on_event(&self, event: &ActivityEvent) -> anyhow::Result<()>;
+
+    /// Shown in logs and metrics when the sink fails.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The sinks with the events waiting for them.
+pub(crate) struct SinkQueue {
+    sinks: Vec<Box<dyn ActivitySink>>,
+    pending: Mutex<Vec<ActivityEvent>>,
+    /// Held by the thread handing events out, so they arrive in order.
+    dispatching: Mutex<()>,
+}
+
+impl SinkQueue {
+    pub fn new(sinks: Vec<Box<dyn ActivitySink>>) -> Self {
+        Self {
+            sinks,
+            pending: Mutex::new(Vec::new()),
+            dispatching: Mutex::new(()),
+        }
+    }
+
+    pub fn push(&self, event: ActivityEvent) {
+        self.pending.lock().unwrap().push(event);
+    }
+
+    /// Hands every queued event to the sinks. Returns at once when another
+    /// thread, or an outer call on this one, is already at it; that one
+    /// picks the new events up too.
+    pub fn dispatch_pending(&self, metrics: &Metrics) {
+        loop {
+            {
+                let Ok(_dispatching) = self.dispatching.try_lock() else {
+                    return;
+                };
+                loop {
+                    let events = std::mem::take(&mut *self.pending.lock().unwrap());
+                    if events.is_empty() {
+                        break;
+                    }
+                    for event in events {
+                        dispatch(&self.sinks, metrics, event);
+                    }
+                }
+            }
+            // 放手之前别的线程塞进来的事件，它们以为这里会处理。
+            if self.pending.lock().unwrap().is_empty() {
+                return;
+            }
+        }
+    }
+}
+
+fn dispatch(sinks: &[Box<dyn ActivitySink>], metrics: &Metrics, event: ActivityEvent) {
+    for sink in sinks {
+        let failure = match catch_unwind(AssertUnwindSafe(|| sink.on_event(&event))) {
+            Ok(Ok(())) => None,
+            Ok(Err(err)) => Some(format!("{err:#}")),
+            Err(_) => Some("sink panicked".to_string()),
+        };
+        match failure {
+            None => metrics.count("sink", "ok"),
+            Some(error) => {
+                tracing::warn!(sink = sink.name(), id = event.id(), %error, "activity sink failed");
+                metrics.count("sink", "error");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, OnceLock};
+
+    use super::*;
+
+    /// Keeps the ids of the events it gets.
+    #[derive(Default)]
+    struct Collect(Mutex<Vec<String>>);
+
+    impl ActivitySink for Arc<Collect> {
+        fn on_event(&self, event: &ActivityEvent) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push(event.id().to_string());
+            Ok(())
+        }
+    }
+
+    struct Failing;
+
+    impl ActivitySink for Failing {
+        fn on_event(&self, _event: &ActivityEvent) -> anyhow::Result<()> {
+            anyhow::bail!("wearable offline")
+        }
+    }
+
+    struct Panicking;
+
+    impl ActivitySink for Panicking {
+        fn on_event(&self, _event: &ActivityEvent) -> anyhow::Result<()> {
+            panic!("sink bug")
+        }
+    }
+
+    fn ended(id: &str) -> ActivityEvent {
+        ActivityEvent::Ended {
+            id: id.to_string(),
+            outcome: EndOutcome::Ended,
+        }
+    }
+
+    #[test]
+    fn failing_sinks_do_not_stop_the_others() {
+        let collect = Arc::new(Collect::default());
+        let queue = SinkQueue::new(vec![
+            Box::new(Panicking),
+            Box::new(Failing),
+            Box::new(collect.clone()),
+        ]);
+        let metrics = Metrics::default();
+        queue.push(ended("a"));
+        queue.push(ended("b"));
+        queue.dispatch_pending(&metrics);
+        assert_eq!(*collect.0.lock().unwrap(), ["a", "b"]);
+
+        let count = |outcome: &str| {
+            metrics
+                .snapshot()
+                .operations
+                .iter()
+                .find(|op| op.operation == "sink" && op.outcome == outcome)
+                .map_or(0, |op| op.count)
+        };
+        assert_eq!((count("ok"), count("error")), (2, 4));
+    }
+
+    #[test]
+    fn events_queued_while_dispatching_follow_in_order() {
+        /// Queues another event the first time it is called, as a sink
+        /// calling back into the plugin would.
+        struct Nested(Arc<OnceLock<Arc<SinkQueue>>>, Arc<Collect>);
+
+        impl ActivitySink for Nested {
+            fn on_event(&self, event: &ActivityEvent) -> anyhow::Result<()> {
+                self.1 .0.lock().unwrap().push(event.id().to_string());
+                if event.id() == "a" {
+                    let queue = self.0.get().unwrap();
+                    queue.push(ended("nested"));
+                    queue.dispatch_pending(&Metrics::default());
+                }
+                Ok(())
+            }
+        }
+
+        let collect = Arc::new(Collect::default());
+        let slot = Arc::new(OnceLock::new());
+        let queue = Arc::new(SinkQueue::new(vec![Box::new(Nested(
+            slot.clone(),
+            collect.clone(),
+        ))]));
+        let _ = slot.set(queue.clone());
+        queue.push(ended("a"));
+        queue.push(ended("b"));
+        queue.dispatch_pending(&Metrics::default());
+        assert_eq!(*collect.0.lock().unwrap(), ["a", "b", "nested"]);
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Mutex;
+
+use anyhow::Result;
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::backend::Backend;
+use crate::models::*;
+
+/// Shows the activity progress on the app's taskbar button or dock icon.
+///
+/// Only the progress survives; title and text are dropped. Meant as the last
+/// desktop presenter of the fallback chain, since it needs no permission.
+pub(crate) struct TaskbarBackend<R: Runtime> {
+    app: AppHandle<R>,
+    current: Mutex<Option<String>>,
+}
+
+impl<R: Runtime> TaskbarBackend<R> {
+    pub fn new(app: AppHandle<R>) -> Self {
+        Self {
+            app,
+            current: Mutex::new(None),
+        }
+    }
+
+    fn set(&self, progress: Option<f32>, active: bool) -> Result<()> {
+        let windows = self.app.webview_windows();
+        if windows.is_empty() {
+            anyhow::bail!("no window to show taskbar progress on");
+        }
+        let status = match (active, progress) {
+            (false, _) => ProgressBarStatus::None,
+            (true, Some(_)) => ProgressBarStatus::Normal,
+            (true, None) => ProgressBarStatus::Indeterminate,
+        };
+        for window in windows.values() {
+            window.set_progress_bar(ProgressBarState {
+                status: Some(status),
+                progress: progress.map(|p| (p * 100.0).round() as u64),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Runtime> Backend for TaskbarBackend<R> {
+    fn name(&self) -> &'static str {
+        "taskbar-progress"
+    }
+
+    fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+        self.set(parse_progress(payload.activity_content.state()), true)?;
+        *self.current.lock().unwrap() = Some(payload.activity_content.id().to_string());
+        Ok(())
+    }
+
+    fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+        if self.current.lock().unwrap().is_none() {
+            anyhow::bail!("No active live activity to update");
+        }
+        self.set(parse_progress(&payload.state), true)
+    }
+
+    fn remove(&self) -> Result<()> {
+        if self.current.lock().unwrap().take().is_some() {
+            self.set(None, false)?;
+        }
+        Ok(())
+    }
+
+    fn visible_ids(&self) -> Result<Vec<String>> {
+        Ok(self.current.lock().unwrap().iter().cloned().collect())
+    }
+
+    fn capabilities(&self) -> Result<Capabilities> {
+        Ok(Capabilities {
+            backend: self.name().to_string(),
+            presents: true,
+            progress_bar: true,
+            indeterminate_progress: cfg!(windows),
+            buttons: false,
+            images: false,
+            updates_in_place: true,
+            max_concurrent: 1,
+        })
+    }
+
+    fn permission_state(&self) -> Result<PermissionState> {
+        Ok(PermissionState::Granted)
+    }
+
+    fn request_permission(&self) -> Result<PermissionState> {
+        Ok(PermissionState::Granted)
+    }
+}
@@ -0,0 +1,34 @@
+use std::time::Instant;
+
+use crate::metrics::Metrics;
+
+/// Runs one plugin operation inside a `live_activity` span and counts how it went.
+///
+/// Every public entry point goes through here so a trace always carries the
+/// operation, activity id, duration and outcome, and the metrics registry
+/// counts the same outcomes. Presenter latency is measured per call by the
+/// presenter chain, not here: this duration includes waiting for the
+/// operation lock and retry backoff.
+pub(crate) fn instrument<T>(
+    metrics: &Metrics,
+    op: &'static str,
+    id: Option<&str>,
+    f: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let span = tracing::info_span!("live_activity", op, id = id.unwrap_or_default());
+    let _enter = span.enter();
+    let started = Instant::now();
+    let result = f();
+    let elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+    match &result {
+        Ok(_) => {
+            tracing::debug!(elapsed_ms, outcome = "ok");
+            metrics.count(op, "ok");
+        }
+        Err(err) => {
+            tracing::warn!(elapsed_ms, outcome = "error", error = %err);
+            metrics.count(op, "error");
+        }
+    }
+    result
+}
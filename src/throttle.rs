@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::clock::now_millis;
+use crate::metrics::Metrics;
+use crate::models::*;
+use crate::power::PowerSource;
+
+/// An update held back by the throttle, ready to go to the presenter.
+pub(crate) struct Held {
+    pub id: String,
+    pub payload: UpdateLiveActivityRequest,
+    /// The activity with the held state applied, for falling back.
+    pub content: Option<ActivityContent>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Activity and time of the last update that reached the presenter.
+    last: Option<(String, u64)>,
+    held: Option<Held>,
+}
+
+/// Spaces out the updates that reach the presenter according to the power state.
+///
+/// Counts under the `throttle` operation: `held` for every update kept back,
+/// `coalesced` when a held update is replaced by a newer one before it went
+/// out, and `dropped` when it is discarded with its activity.
+pub(crate) struct Throttler {
+    policy: UpdateThrottle,
+    power: Arc<dyn PowerSource>,
+    metrics: Arc<Metrics>,
+    state: Mutex<State>,
+    held: Condvar,
+}
+
+impl Throttler {
+    pub fn new(policy: UpdateThrottle, power: Arc<dyn PowerSource>, metrics: Arc<Metrics>) -> Self {
+        Self {
+            policy,
+            power,
+            metrics,
+            state: Mutex::new(State::default()),
+            held: Condvar::new(),
+        }
+    }
+
+    pub fn power_state(&self) -> PowerState {
+        self.power.power_state()
+    }
+
+    /// Current minimum time between two updates, in milliseconds.
+    fn interval_ms(&self) -> u64 {
+        self.policy.interval(self.power.power_state()).as_millis() as u64
+    }
+
+    /// Whether an update of `content` to `state` may go to the presenter at
+    /// `now`; when it may, a previously held one is dropped.
+    pub fn admit(
+        &self,
+        content: &ActivityContent,
+        state: &HashMap<String, String>,
+        now: u64,
+    ) -> bool {
+        let id = content.id();
+        let urgent = (self.policy.exempt_critical
+            && content.priority() == ActivityPriority::Critical)
+            || parse_progress(state) == Some(1.0)
+            || state.get("error").is_some_and(|e| !e.is_empty());
+        let mut guard = self.state.lock().unwrap();
+        let due = match &guard.last {
+            Some((last_id, at)) if last_id == id => now.saturating_sub(*at) >= self.interval_ms(),
+            _ => true,
+        };
+        if !urgent && !due {
+            return false;
+        }
+        guard.last = Some((id.to_string(), now));
+        if guard.held.take().is_some() {
+            self.metrics.count("throttle", "coalesced");
+        }
+        true
+    }
+
+    /// Keeps `held` until [`Throttler::take_due`] returns it, replacing any
+    /// update held before.
+    pub fn hold(&self, held: Held) {
+        let replaced = self.state.lock().unwrap().held.replace(held);
+        self.metrics.count("throttle", "held");
+        if replaced.is_some() {
+            self.metrics.count("throttle", "coalesced");
+        }
+        self.held.notify_all();
+    }
+
+    /// Drops the update held for `id`, e.g. because the activity went away.
+    pub fn discard(&self, id: &str) {
+        let mut guard = self.state.lock().unwrap();
+        if guard.held.as_ref().is_some_and(|held| held.id == id) {
+            guard.held = None;
+            self.metrics.count("throttle", "dropped");
+        }
+    }
+
+    /// Blocks until an update is held, then returns how long until it is due.
+    pub fn wait_held(&self) -> Duration {
+        let mut guard = self.state.lock().unwrap();
+        while guard.held.is_none() {
+            guard = self.held.wait(guard).unwrap();
+        }
+        let due_at = match (&guard.last, &guard.held) {
+            (Some((last_id, at)), Some(held)) if *last_id == held.id => at + self.interval_ms(),
+            _ => 0,
+        };
+        Duration::from_millis(due_at.saturating_sub(now_millis()))
+    }
+
+    /// The held update, once its interval has passed at `now`.
+    pub fn take_due(&self, now: u64) -> Option<Held> {
+        let mut guard = self.state.lock().unwrap();
+        let held = guard.held.as_ref()?;
+        if let Some((last_id, at)) = &guard.last {
+            if *last_id == held.id && now.saturating_sub(*at) < self.interval_ms() {
+                return None;
+            }
+        }
+        let held = guard.held.take()?;
+        guard.last = Some((held.id.clone(), now));
+        Some(held)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::test_support::task;
+    use crate::power::ManualPower;
+
+    fn content(priority: ActivityPriority) -> ActivityContent {
+        ActivityContent::TaskQueue(ActivityContentTaskQueue {
+            title: "传输中".to_string(),
+            priority,
+            ..task("transfer")
+        })
+    }
+
+    fn metrics() -> Arc<Metrics> {
+        Arc::new(Metrics::default())
+    }
+
+    fn progress(p: &str) -> HashMap<String, String> {
+        HashMap::from([("progress".to_string(), p.to_string())])
+    }
+
+    fn held(state: HashMap<String, String>) -> Held {
+        Held {
+            id: "transfer".to_string(),
+            payload: UpdateLiveActivityRequest { state, id: None },
+            content: None,
+        }
+    }
+
+    #[test]
+    fn holds_updates_on_battery_only() {
+        let power = Arc::new(ManualPower::new(PowerState::Ac));
+        let throttler = Throttler::new(UpdateThrottle::default(), power.clone(), metrics());
+        let content = content(ActivityPriority::Normal);
+        assert!(throttler.admit(&content, &progress("0.1"), 0));
+        assert!(throttler.admit(&content, &progress("0.2"), 100));
+
+        power.set(PowerState::Battery);
+        assert!(!throttler.admit(&content, &progress("0.3"), 200));
+        throttler.hold(held(progress("0.3")));
+        assert!(throttler.take_due(1_000).is_none());
+        let due = throttler.take_due(2_100).expect("held update is due");
+        assert_eq!(due.payload.state, progress("0.3"));
+        assert!(throttler.take_due(5_000).is_none());
+
+        power.set(PowerState::PowerSaver);
+        assert!(!throttler.admit(&content, &progress("0.4"), 5_000));
+        assert!(throttler.admit(&content, &progress("0.5"), 7_100));
+    }
+
+    #[test]
+    fn discards_the_update_of_an_activity_that_went_away() {
+        let power = Arc::new(ManualPower::new(PowerState::Battery));
+        let throttler = Throttler::new(UpdateThrottle::default(), power, metrics());
+        let content = content(ActivityPriority::Normal);
+        assert!(throttler.admit(&content, &progress("0.1"), 0));
+        throttler.hold(held(progress("0.2")));
+        throttler.discard("another");
+        throttler.discard("transfer");
+        assert!(throttler.take_due(10_000).is_none());
+    }
+
+    #[test]
+    fn waits_only_while_something_is_held() {
+        let power = Arc::new(ManualPower::new(PowerState::Battery));
+        let throttler = Arc::new(Throttler::new(UpdateThrottle::default(), power, metrics()));
+        let waiter = {
+            let throttler = throttler.clone();
+            std::thread::spawn(move || throttler.wait_held())
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!waiter.is_finished());
+        // 别的活动推过的时间不算，攒着的更新马上就能推。
+        throttler.hold(held(progress("0.2")));
+        assert_eq!(waiter.join().unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn lets_completion_errors_and_critical_through() {
+        let power = Arc::new(ManualPower::new(PowerState::PowerSaver));
+        let throttler = Throttler::new(UpdateThrottle::default(), power, metrics());
+        let normal = content(ActivityPriority::Normal);
+        assert!(throttler.admit(&normal, &progress("0.1"), 0));
+        assert!(!throttler.admit(&normal, &progress("0.2"), 10));
+        throttler.hold(held(progress("0.2")));
+        assert!(throttler.admit(&normal, &progress("1"), 20));
+        // 放行的更新比攒着的新，攒着的就不该再推。
+        assert!(throttler.take_due(10_000).is_none());
+
+        let failed = HashMap::from([("error".to_string(), "断开连接".to_string())]);
+        assert!(throttler.admit(&normal, &failed, 30));
+
+        let critical = content(ActivityPriority::Critical);
+        assert!(throttler.admit(&critical, &progress("0.3"), 40));
+        assert!(throttler.admit(&critical, &progress("0.4"), 50));
+    }
+
+    #[test]
+    fn counts_held_coalesced_and_dropped_updates() {
+        let metrics = metrics();
+        let power = Arc::new(ManualPower::new(PowerState::Battery));
+        let throttler = Throttler::new(UpdateThrottle::default(), power, metrics.clone());
+        let normal = content(ActivityPriority::Normal);
+        assert!(throttler.admit(&normal, &progress("0.1"), 0));
+        throttler.hold(held(progress("0.2")));
+        throttler.hold(held(progress("0.3")));
+        // 完成的更新直接放行，攒着的那条就不推了。
+        assert!(throttler.admit(&normal, &progress("1"), 10));
+        throttler.hold(held(progress("0.4")));
+        throttler.discard("transfer");
+
+        let count = |outcome: &str| {
+            metrics
+                .snapshot()
+                .operations
+                .iter()
+                .find(|op| op.operation == "throttle" && op.outcome == outcome)
+                .map_or(0, |op| op.count)
+        };
+        assert_eq!(count("held"), 3);
+        assert_eq!(count("coalesced"), 2);
+        assert_eq!(count("dropped"), 1);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod core {
+    use crate::backend::Backend;
     use crate::models::*;
     use anyhow::{Context, Result};
     use std::ffi::OsStr;
@@ -12,29 +13,24 @@ pub mod core {
         Foundation::{DateTime, IReference, PropertyValue},
         Win32::{
             Foundation::WIN32_ERROR,
-            Graphics::Gdi::{
-                DeleteObject, GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO,
-                BITMAPINFOHEADER, DIB_RGB_COLORS,
-            },
             System::Com::{
                 CoCreateInstance, CoInitializeEx, CoTaskMemAlloc, CoTaskMemFree, IPersistFile,
                 CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED, COINIT_MULTITHREADED,
             },
             System::Registry::{
-                RegCloseKey, RegCreateKeyExW, RegQueryValueExW, RegSetValueExW, HKEY,
-                HKEY_CURRENT_USER, KEY_QUERY_VALUE, KEY_SET_VALUE, KEY_WOW64_64KEY,
+                RegCloseKey, RegCreateKeyExW, RegOpenKeyExW, RegQueryValueExW, RegSetValueExW,
+                HKEY, HKEY_CURRENT_USER, KEY_QUERY_VALUE, KEY_SET_VALUE, KEY_WOW64_64KEY,
                 REG_OPTION_NON_VOLATILE, REG_SZ, REG_VALUE_TYPE,
             },
             UI::Shell::PropertiesSystem::{IPropertyStore, PROPERTYKEY},
             UI::Shell::{
-                ExtractIconExW, FOLDERID_LocalAppData, FOLDERID_Programs, IShellLinkW,
-                SHGetKnownFolderPath, SetCurrentProcessExplicitAppUserModelID, ShellLink,
-                KF_FLAG_DEFAULT,
+                FOLDERID_Programs, IShellLinkW, SHGetKnownFolderPath,
+                SetCurrentProcessExplicitAppUserModelID, ShellLink, KF_FLAG_DEFAULT,
             },
-            UI::WindowsAndMessaging::{DestroyIcon, GetIconInfo, HICON, ICONINFO},
         },
         UI::Notifications::{
-            NotificationData, ToastNotification, ToastNotificationManager, ToastNotifier,
+            NotificationData, NotificationSetting, ToastNotification, ToastNotificationManager,
+            ToastNotifier,
         },
     };
 
@@ -42,6 +38,7 @@ pub mod core {
     static CURRENT_META: OnceLock<Mutex<Option<Meta>>> = OnceLock::new();
     static WINRT_INIT: OnceLock<()> = OnceLock::new();
     const FIXED_AUMID: &str = "moe.astralsight.astrobox";
+    pub const BACKEND: &str = "windows-toast";
     fn current_tag() -> &'static Mutex<Option<String>> {
         CURRENT_TAG.get_or_init(|| Mutex::new(None))
     }
@@ -51,6 +48,7 @@ pub mod core {
 
     #[derive(Clone)]
     struct Meta {
+        id: String,
         title: String,
         text: String,
         task_name: String,
@@ -64,6 +62,7 @@ pub mod core {
             .collect()
     }
 
+    #[cfg(feature = "icons")]
     fn path_to_file_uri(path: &std::path::Path) -> String {
         let mut s = String::from("file:///");
         s.push_str(&path.to_string_lossy().replace('\\', "/"));
@@ -99,10 +98,24 @@ pub mod core {
 
     fn set_process_app_id(app_id: &str) {
         let app_id_w = to_wide(app_id);
-        let _ = unsafe { SetCurrentProcessExplicitAppUserModelID(PCWSTR(app_id_w.as_ptr())) };
+        if let Err(err) =
+            unsafe { SetCurrentProcessExplicitAppUserModelID(PCWSTR(app_id_w.as_ptr())) }
+        {
+            tracing::warn!(error = %err, app_id, "failed to set process AppUserModelID");
+        }
     }
 
+    #[cfg(feature = "icons")]
     fn extract_exe_icon_png(exe_path: &str) -> Result<Option<String>> {
+        use windows::Win32::{
+            Graphics::Gdi::{
+                DeleteObject, GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO,
+                BITMAPINFOHEADER, DIB_RGB_COLORS,
+            },
+            UI::Shell::{ExtractIconExW, FOLDERID_LocalAppData},
+            UI::WindowsAndMessaging::{DestroyIcon, GetIconInfo, HICON, ICONINFO},
+        };
+
         let exe = std::path::Path::new(exe_path);
         if !exe.exists() {
             return Ok(None);
@@ -196,7 +209,15 @@ pub mod core {
         }
         let image = image::RgbaImage::from_raw(width, height, buffer)
             .ok_or_else(|| anyhow::anyhow!("Invalid icon buffer"))?;
-        image.save(&cache_path)?;
+        // 多个实例共用这个缓存，先写到各自的临时文件再改名，避免互相写坏。
+        let tmp_path = cache_dir.join(format!("toast_app_icon.{}.png", std::process::id()));
+        image.save(&tmp_path)?;
+        if let Err(err) = std::fs::rename(&tmp_path, &cache_path) {
+            let _ = std::fs::remove_file(&tmp_path);
+            if !cache_path.exists() {
+                return Err(err.into());
+            }
+        }
         Ok(Some(path_to_file_uri(&cache_path)))
     }
 
@@ -292,6 +313,47 @@ pub mod core {
         result
     }
 
+    /// Reads back what `ensure_app_id_registry` wrote, `None` when the key is missing.
+    fn read_app_id_registry(app_id: &str) -> Result<Option<(String, String)>> {
+        let subkey = format!("Software\\Classes\\AppUserModelId\\{}", app_id);
+        let subkey_w = to_wide(&subkey);
+        let mut hkey = HKEY::default();
+        let status = unsafe {
+            RegOpenKeyExW(
+                HKEY_CURRENT_USER,
+                PCWSTR(subkey_w.as_ptr()),
+                0,
+                KEY_QUERY_VALUE | KEY_WOW64_64KEY,
+                &mut hkey,
+            )
+        };
+        if status != WIN32_ERROR(0) {
+            return Ok(None);
+        }
+        let result = (|| {
+            let display = reg_get_sz(hkey, "DisplayName")?.unwrap_or_default();
+            let icon = reg_get_sz(hkey, "IconUri")?.unwrap_or_default();
+            Ok(Some((display, icon)))
+        })();
+        unsafe {
+            let _ = RegCloseKey(hkey);
+        };
+        result
+    }
+
+    fn shortcut_path() -> Result<PathBuf> {
+        let folder_ptr =
+            unsafe { SHGetKnownFolderPath(&FOLDERID_Programs, KF_FLAG_DEFAULT, None)? };
+        let programs_dir = pwstr_to_string(folder_ptr);
+        unsafe { CoTaskMemFree(Some(folder_ptr.0 as _)) };
+        if programs_dir.is_empty() {
+            corelib::bail_site!("Failed to resolve Start Menu programs path");
+        }
+
+        let shortcut_name = "AstroBox".to_string();
+        Ok(PathBuf::from(programs_dir).join(format!("{}.lnk", shortcut_name)))
+    }
+
     fn ensure_shortcut_inner(app_id: &str) -> Result<()> {
         let exe = std::env::current_exe()?;
         let exe_dir = exe
@@ -303,16 +365,7 @@ pub mod core {
         let exe_w = to_wide(&exe_str);
         let exe_dir_w = to_wide(&exe_dir_str);
 
-        let folder_ptr =
-            unsafe { SHGetKnownFolderPath(&FOLDERID_Programs, KF_FLAG_DEFAULT, None)? };
-        let programs_dir = pwstr_to_string(folder_ptr);
-        unsafe { CoTaskMemFree(Some(folder_ptr.0 as _)) };
-        if programs_dir.is_empty() {
-            corelib::bail_site!("Failed to resolve Start Menu programs path");
-        }
-
-        let shortcut_name = "AstroBox".to_string();
-        let shortcut_path = PathBuf::from(programs_dir).join(format!("{}.lnk", shortcut_name));
+        let shortcut_path = shortcut_path()?;
         let shortcut_w = to_wide(&shortcut_path.to_string_lossy());
 
         let shell_link: IShellLinkW =
@@ -386,17 +439,27 @@ pub mod core {
             set_process_app_id(&app_id);
             if let Ok(exe) = std::env::current_exe() {
                 let exe_str = exe.to_string_lossy().to_string();
+                #[cfg(feature = "icons")]
                 let icon_value = extract_exe_icon_png(&exe_str)
-                    .unwrap_or(None)
+                    .unwrap_or_else(|err| {
+                        tracing::warn!(error = %err, "failed to extract toast icon, using exe icon");
+                        None
+                    })
                     .unwrap_or_else(|| format!("{},0", exe_str));
+                #[cfg(not(feature = "icons"))]
+                let icon_value = format!("{},0", exe_str);
                 ensure_app_id_registry(&app_id, "AstroBox", &icon_value)?;
             }
-            let _ = ensure_shortcut(&app_id);
+            if let Err(err) = ensure_shortcut(&app_id) {
+                tracing::warn!(error = %err, "failed to ensure Start Menu shortcut");
+            }
             let id_h = HSTRING::from(&app_id);
-            if let Ok(notifier) = ToastNotificationManager::CreateToastNotifierWithId(&id_h) {
-                Ok(notifier)
-            } else {
-                Ok(ToastNotificationManager::CreateToastNotifier()?)
+            match ToastNotificationManager::CreateToastNotifierWithId(&id_h) {
+                Ok(notifier) => Ok(notifier),
+                Err(err) => {
+                    tracing::warn!(error = %err, app_id, "CreateToastNotifierWithId failed, falling back");
+                    Ok(ToastNotificationManager::CreateToastNotifier()?)
+                }
             }
         } else {
             Ok(ToastNotificationManager::CreateToastNotifier()?)
@@ -409,24 +472,46 @@ pub mod core {
         if let Some(id) = app_id {
             let id_h = HSTRING::from(id);
             let group_h = HSTRING::from("");
-            let _ = history.RemoveGroupedTagWithId(&tag_h, &group_h, &id_h);
-            let _ = history.Remove(&tag_h);
-        } else {
-            let _ = history.Remove(&tag_h);
+            if let Err(err) = history.RemoveGroupedTagWithId(&tag_h, &group_h, &id_h) {
+                tracing::debug!(error = %err, tag, "RemoveGroupedTagWithId failed");
+            }
+        }
+        if let Err(err) = history.Remove(&tag_h) {
+            tracing::debug!(error = %err, tag, "history Remove failed");
         }
         Ok(())
     }
 
     fn schedule_remove_history(tag: String) {
         let app_id = resolve_app_id();
+        let span = tracing::Span::current();
         std::thread::spawn(move || {
-            std::thread::sleep(Duration::from_secs(2));
-            let _ = remove_history(&tag, app_id);
+            let _enter = span.enter();
+            crate::clock::sleep(Duration::from_secs(2));
+            if let Err(err) = remove_history(&tag, app_id) {
+                tracing::warn!(error = %err, tag, "failed to remove toast from history");
+            }
         });
     }
 
+    fn is_silent(state: &std::collections::HashMap<String, String>) -> bool {
+        state.get("silent").is_some_and(|v| v == "true")
+    }
+
+    fn hides_completion(state: &std::collections::HashMap<String, String>) -> bool {
+        state.get("completion").is_some_and(|v| v == "hidden")
+    }
+
+    fn audio_xml(silent: bool) -> &'static str {
+        if silent {
+            "<audio silent=\"true\"/>"
+        } else {
+            ""
+        }
+    }
+
     fn expiration_after(delay: Duration) -> DateTime {
-        let since_unix = std::time::SystemTime::now()
+        let since_unix = crate::clock::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default();
         let base_100ns = (since_unix.as_secs() + 11_644_473_600) * 10_000_000
@@ -455,7 +540,7 @@ pub mod core {
             };
 
         let unique_tag = {
-            let since = std::time::SystemTime::now()
+            let since = crate::clock::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or_default();
             let mut hasher = std::collections::hash_map::DefaultHasher::new();
@@ -471,21 +556,23 @@ pub mod core {
         {
             let mut m = current_meta().lock().unwrap();
             *m = Some(Meta {
+                id: id.clone(),
                 title: title.clone(),
                 text: text.clone(),
                 task_name: task_name.clone(),
                 task_type: task_type.clone(),
             });
         }
-        let progress_value = state
-            .remove("progress")
-            .and_then(|s| s.parse::<f32>().ok())
-            .unwrap_or(0.0)
-            .clamp(0.0, 1.0);
-        let progress_text = state
-            .remove("percent")
-            .map(|p| format!("{}%", p))
-            .unwrap_or_else(|| format!("{:.1}%", progress_value * 100.0));
+        let progress_value = parse_progress(&state).unwrap_or(0.0);
+        let silent = is_silent(&state);
+        // 有提示文字（倒计时等）时进度条显示为 indeterminate。
+        let hint = state.remove("hint");
+        let progress_text = hint.clone().unwrap_or_else(|| {
+            state
+                .remove("percent")
+                .map(|p| format!("{}%", p))
+                .unwrap_or_else(|| format!("{:.1}%", progress_value * 100.0))
+        });
 
         let image_xml = String::new();
 
@@ -499,13 +586,15 @@ pub mod core {
                         <progress title=\"{}\" status=\"{}\" value=\"{{progressValue}}\" valueStringOverride=\"{{progressText}}\"/>\
                     </binding>\
                 </visual>\
+                {}\
             </toast>",
             escape_xml(&title),
             escape_xml(&text),
             escape_xml(&task_name),
             image_xml,
             escape_xml(&task_name),
-            escape_xml(&task_type)
+            escape_xml(&task_type),
+            audio_xml(silent)
         );
 
         let doc = XmlDocument::new()?;
@@ -516,12 +605,17 @@ pub mod core {
             ToastNotification::CreateToastNotification(&doc).context("Create toast failed")?;
         let tag_h = HSTRING::from(&unique_tag);
         toast.SetTag(&tag_h).context("Set toast tag failed")?;
+        toast.SetSuppressPopup(silent)?;
 
         let notifier = create_notifier()?;
         let data = NotificationData::new()?;
         let values = data.Values()?;
         let key_value = HSTRING::from("progressValue");
-        let value_value = HSTRING::from(format!("{:.3}", progress_value));
+        let value_value = if hint.is_some() {
+            HSTRING::from("indeterminate")
+        } else {
+            HSTRING::from(format!("{:.3}", progress_value))
+        };
         values.Insert(&key_value, &value_value)?;
         let key_text = HSTRING::from("progressText");
         let value_text = HSTRING::from(progress_text);
@@ -557,21 +651,14 @@ pub mod core {
                 .context("No active live activity to update")?
         };
 
-        let mut p = payload
-            .state
-            .get("progress")
-            .and_then(|s| s.parse::<f32>().ok())
-            .unwrap_or_else(|| {
-                payload
-                    .state
-                    .get("percent")
-                    .and_then(|s| s.parse::<f32>().ok())
-                    .map(|x| (x / 100.0) as f32)
-                    .unwrap_or(0.0)
-            });
-        p = p.clamp(0.0, 1.0);
+        let p = parse_progress(&payload.state).unwrap_or(0.0);
 
-        if (p - 1.0).abs() < f32::EPSILON {
+        if (p - 1.0).abs() < f32::EPSILON && hides_completion(&payload.state) {
+            // 用户不要完成通知，直接把进度 toast 撤掉。
+            remove_history(&tag, resolve_app_id())?;
+            *current_tag().lock().unwrap() = None;
+            *current_meta().lock().unwrap() = None;
+        } else if (p - 1.0).abs() < f32::EPSILON {
             let meta = {
                 let m = current_meta().lock().unwrap();
                 m.clone().context("No meta to update")?
@@ -588,13 +675,15 @@ pub mod core {
                             <progress title=\"{}\" status=\"{}\" value=\"{{progressValue}}\" valueStringOverride=\"{{progressText}}\"/>\
                         </binding>\
                     </visual>\
+                    {}\
                 </toast>",
                 escape_xml(&meta.title),
                 escape_xml(&meta.text),
                 escape_xml(&meta.task_name),
                 image_xml,
                 escape_xml(&meta.task_name),
-                escape_xml(&meta.task_type)
+                escape_xml(&meta.task_type),
+                audio_xml(is_silent(&payload.state))
             );
             let doc = XmlDocument::new()?;
             let xml_h = HSTRING::from(xml);
@@ -603,6 +692,7 @@ pub mod core {
                 ToastNotification::CreateToastNotification(&doc).context("Create toast failed")?;
             let tag_h = HSTRING::from(&tag);
             toast.SetTag(&tag_h).context("Set toast tag failed")?;
+            toast.SetSuppressPopup(is_silent(&payload.state))?;
             let expire = expiration_after(Duration::from_secs(2));
             let expire_ref: IReference<DateTime> = PropertyValue::CreateDateTime(expire)?.cast()?;
             toast
@@ -625,7 +715,10 @@ pub mod core {
             *g = None;
             *current_meta().lock().unwrap() = None;
         } else {
-            let pct_text = if let Some(s) = payload.state.get("percent") {
+            let hint = payload.state.get("hint");
+            let pct_text = if let Some(hint) = hint {
+                hint.clone()
+            } else if let Some(s) = payload.state.get("percent") {
                 format!("{}%", s)
             } else {
                 format!("{:.1}%", p * 100.0)
@@ -634,7 +727,12 @@ pub mod core {
             let data = NotificationData::new()?;
             let values = data.Values()?;
             let key_value = HSTRING::from("progressValue");
-            let value_value = HSTRING::from(format!("{:.3}", p));
+            // toast 进度条原生支持 indeterminate，卡住或倒计时时用它代替停在原地的百分比。
+            let value_value = if hint.is_some() {
+                HSTRING::from("indeterminate")
+            } else {
+                HSTRING::from(format!("{:.3}", p))
+            };
             values.Insert(&key_value, &value_value)?;
             let key_text = HSTRING::from("progressText");
             let value_text = HSTRING::from(pct_text);
@@ -661,10 +759,209 @@ pub mod core {
             data.SetSequenceNumber(9999)?;
             let tag_h = HSTRING::from(&tag);
             let group_h = HSTRING::from("");
-            let _ = notifier.UpdateWithTagAndGroup(&data, &tag_h, &group_h);
+            if let Err(err) = notifier.UpdateWithTagAndGroup(&data, &tag_h, &group_h) {
+                tracing::warn!(error = %err, tag, "failed to finalize toast before removal");
+            }
             schedule_remove_history(tag);
             *current_tag().lock().unwrap() = None;
         }
         Ok(())
     }
+
+    pub fn alert(_self: &impl Sized, alert: AlertRequest) -> Result<()> {
+        let xml = format!(
+            "<toast>\
+                <visual>\
+                    <binding template=\"ToastGeneric\">\
+                        <text>{}</text>\
+                        <text>{}</text>\
+                    </binding>\
+                </visual>\
+                {}\
+            </toast>",
+            escape_xml(&alert.title),
+            escape_xml(&alert.body),
+            audio_xml(alert.silent)
+        );
+        let doc = XmlDocument::new()?;
+        doc.LoadXml(&HSTRING::from(xml))
+            .context("Toast XML load failed")?;
+        let toast =
+            ToastNotification::CreateToastNotification(&doc).context("Create toast failed")?;
+        toast.SetSuppressPopup(alert.silent)?;
+        if let Some(group) = &alert.group {
+            // 同一分组只保留最新的一条汇总。
+            toast.SetTag(&HSTRING::from("summary"))?;
+            toast.SetGroup(&HSTRING::from(group))?;
+        }
+        create_notifier()?
+            .Show(&toast)
+            .context("Show toast failed")?;
+        Ok(())
+    }
+
+    fn notification_setting_state() -> PermissionState {
+        // Windows 没有授权弹窗，只有“通知被用户/组策略关掉”这一种拒绝。
+        match create_notifier().and_then(|n| Ok(n.Setting()?)) {
+            Ok(NotificationSetting::Enabled) => PermissionState::Granted,
+            _ => PermissionState::Denied,
+        }
+    }
+
+    pub fn permission_state(_self: &impl Sized) -> PermissionState {
+        notification_setting_state()
+    }
+
+    pub fn request_permission(_self: &impl Sized) -> PermissionState {
+        notification_setting_state()
+    }
+
+    pub fn capabilities(_self: &impl Sized) -> Capabilities {
+        Capabilities {
+            backend: BACKEND.to_string(),
+            presents: true,
+            progress_bar: true,
+            indeterminate_progress: true,
+            // toast 模板里没有放按钮和图片，别让调用方以为能显示。
+            buttons: false,
+            images: false,
+            updates_in_place: true,
+            max_concurrent: 1,
+        }
+    }
+
+    pub fn visible_ids(_self: &impl Sized) -> Vec<String> {
+        let Some(id) = current_meta()
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|m| m.id.clone())
+        else {
+            return Vec::new();
+        };
+        let tag = current_tag().lock().unwrap().clone();
+        let (Some(tag), Some(app_id)) = (tag, resolve_app_id()) else {
+            return Vec::new();
+        };
+        let tag_h = HSTRING::from(&tag);
+        let shown = ToastNotificationManager::History()
+            .and_then(|history| history.GetHistoryWithId(&HSTRING::from(app_id)))
+            .map(|toasts| {
+                toasts
+                    .into_iter()
+                    .any(|toast| toast.Tag().map(|t| t == tag_h).unwrap_or(false))
+            })
+            .unwrap_or(false);
+        if shown {
+            vec![id]
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn diagnostics(_self: &impl Sized, _app: &AppIdentity) -> Vec<DiagnosticCheck> {
+        let mut checks = Vec::new();
+        let Some(app_id) = resolve_app_id() else {
+            checks.push(DiagnosticCheck::new(
+                "aumid",
+                CheckStatus::Warning,
+                "no AppUserModelID, toasts are attributed to the host process",
+            ));
+            return checks;
+        };
+
+        checks.push(match read_app_id_registry(&app_id) {
+            Ok(Some((display, icon))) if !display.trim().is_empty() => DiagnosticCheck::new(
+                "aumid",
+                CheckStatus::Ok,
+                format!("{app_id}: DisplayName='{display}', IconUri='{icon}'"),
+            ),
+            Ok(Some(_)) => DiagnosticCheck::new(
+                "aumid",
+                CheckStatus::Warning,
+                format!("{app_id} is registered without a DisplayName"),
+            ),
+            Ok(None) => DiagnosticCheck::new(
+                "aumid",
+                CheckStatus::Error,
+                format!("HKCU\\Software\\Classes\\AppUserModelId\\{app_id} is missing"),
+            ),
+            Err(err) => DiagnosticCheck::new("aumid", CheckStatus::Error, format!("{err:#}")),
+        });
+
+        checks.push(match shortcut_path() {
+            Ok(path) if path.exists() => {
+                DiagnosticCheck::new("shortcut", CheckStatus::Ok, path.display().to_string())
+            }
+            Ok(path) => DiagnosticCheck::new(
+                "shortcut",
+                CheckStatus::Warning,
+                format!("{} is missing", path.display()),
+            ),
+            Err(err) => DiagnosticCheck::new("shortcut", CheckStatus::Error, format!("{err:#}")),
+        });
+
+        checks.push(match create_notifier().and_then(|n| Ok(n.Setting()?)) {
+            Ok(NotificationSetting::Enabled) => {
+                DiagnosticCheck::new("notifier", CheckStatus::Ok, "enabled")
+            }
+            Ok(setting) => {
+                let reason = match setting {
+                    NotificationSetting::DisabledForApplication => "disabled for this app",
+                    NotificationSetting::DisabledForUser => "disabled for the user",
+                    NotificationSetting::DisabledByGroupPolicy => "disabled by group policy",
+                    NotificationSetting::DisabledByManifest => "disabled by the app manifest",
+                    _ => "disabled",
+                };
+                DiagnosticCheck::new("notifier", CheckStatus::Error, reason)
+            }
+            Err(err) => DiagnosticCheck::new("notifier", CheckStatus::Error, format!("{err:#}")),
+        });
+        checks
+    }
+
+    /// Adapter exposing this module to the shared plugin layer.
+    pub struct WindowsBackend;
+
+    impl Backend for WindowsBackend {
+        fn name(&self) -> &'static str {
+            BACKEND
+        }
+
+        fn create(&self, payload: CreateLiveActivityRequest) -> Result<()> {
+            create_live_activity(self, payload)
+        }
+
+        fn update(&self, payload: UpdateLiveActivityRequest) -> Result<()> {
+            update_live_activity(self, payload)
+        }
+
+        fn remove(&self) -> Result<()> {
+            remove_live_activity(self)
+        }
+
+        fn visible_ids(&self) -> Result<Vec<String>> {
+            Ok(visible_ids(self))
+        }
+
+        fn capabilities(&self) -> Result<Capabilities> {
+            Ok(capabilities(self))
+        }
+
+        fn permission_state(&self) -> Result<PermissionState> {
+            Ok(permission_state(self))
+        }
+
+        fn request_permission(&self) -> Result<PermissionState> {
+            Ok(request_permission(self))
+        }
+
+        fn alert(&self, request: AlertRequest) -> Result<()> {
+            alert(self, request)
+        }
+
+        fn diagnostics(&self, app: &AppIdentity) -> Vec<DiagnosticCheck> {
+            diagnostics(self, app)
+        }
+    }
 }